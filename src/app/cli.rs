@@ -0,0 +1,252 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    config::theme::Preset, generators::Generator, maze::cell::GlyphTheme, solvers::Solver,
+};
+
+/// Usage text printed for `--help` and invalid arguments
+pub const USAGE: &str = "\
+Usage: mazest [OPTIONS]
+
+Options:
+  --record <FILE>            Record the visualization to an asciicast v2 file (e.g. demo.cast)
+  --fast-export              Write the recording without waiting for rendering delays.
+                             The recording still replays at the chosen speed.
+  --save-replay <FILE>       Save the grid events of a single maze run to a replay file
+  --replay <FILE>            Replay a run saved with --save-replay, with all animation controls
+  --glyphs <THEME>           Symbols to draw cells with: unicode, ascii or emoji
+                             [default: detected from TERM and the locale]
+  --theme <NAME>             Color theme: default, high-contrast or colorblind, instead of
+                             ~/.config/mazest/theme.toml
+  -h, --help                 Print this help message
+
+GIF export (non-interactive):
+  --gif <FILE>               Generate and solve a maze, and write the animation to a GIF file
+  --size <WxH>               Maze dimensions [default: 30x20]
+  --generator <NAME>         backtrack, prim, division or kruskal [default: backtrack]
+  --solver <NAME>            dfs, bfs, dijkstra or astar [default: astar]
+  --seed <N>                 Seed of the maze generator, for reproducible mazes
+  --events-per-frame <N>     Number of grid events drawn in each frame [default: 8]
+  --frame-delay <MS>         Delay between frames in milliseconds, rounded to 10ms [default: 40]
+  --scale <PX>               Pixels per grid cell [default: 4]
+  --max-size <BYTES>         Maximum file size, e.g. 500K or 2M. Events per frame are
+                             increased until the animation fits.";
+
+/// Arguments that only apply to the GIF export
+const GIF_ARGS: [&str; 8] = [
+    "--size",
+    "--generator",
+    "--solver",
+    "--seed",
+    "--events-per-frame",
+    "--frame-delay",
+    "--scale",
+    "--max-size",
+];
+
+/// Options of the non-interactive GIF export
+#[derive(Debug, Clone)]
+pub struct GifOptions {
+    /// Maze dimensions (width, height)
+    pub size: (u8, u8),
+    pub generator: Generator,
+    pub solver: Solver,
+    /// Seed of the maze generator
+    pub seed: Option<u64>,
+    /// Number of grid events drawn in each frame
+    pub events_per_frame: usize,
+    /// Delay between frames
+    pub frame_delay: Duration,
+    /// Pixels per grid cell along each side
+    pub scale: u16,
+    /// Maximum file size in bytes
+    pub max_size: Option<u64>,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            size: (30, 20),
+            generator: Generator::RecurBacktrack,
+            solver: Solver::AStar,
+            seed: None,
+            events_per_frame: 8,
+            frame_delay: Duration::from_millis(40),
+            scale: 4,
+            max_size: None,
+        }
+    }
+}
+
+/// Options given on the command line
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// Path of the asciicast file to record the visualization to
+    pub record: Option<PathBuf>,
+    /// Skip rendering delays while recording
+    pub fast_export: bool,
+    /// Path of the replay file to save the grid events of the run to
+    pub save_replay: Option<PathBuf>,
+    /// Path of the replay file to play instead of generating a maze
+    pub replay: Option<PathBuf>,
+    /// Path of the GIF file to export to, instead of running interactively
+    pub gif: Option<PathBuf>,
+    /// Options of the GIF export
+    pub gif_options: GifOptions,
+    /// Glyph theme to draw cells with, instead of detecting one from the environment
+    pub glyphs: Option<GlyphTheme>,
+    /// Color theme preset to use instead of the theme file
+    pub theme: Option<Preset>,
+    /// Print the usage text and exit
+    pub help: bool,
+}
+
+impl Options {
+    /// Parse options from command line arguments, excluding the program name.
+    /// Returns an error message for unknown or incomplete arguments.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut gif_only_arg = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if GIF_ARGS.contains(&arg.as_str()) {
+                gif_only_arg.get_or_insert_with(|| arg.clone());
+            }
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("Missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--record" => options.record = Some(PathBuf::from(value()?)),
+                "--fast-export" => options.fast_export = true,
+                "--save-replay" => options.save_replay = Some(PathBuf::from(value()?)),
+                "--replay" => options.replay = Some(PathBuf::from(value()?)),
+                "--gif" => options.gif = Some(PathBuf::from(value()?)),
+                "--size" => options.gif_options.size = parse_size(&value()?)?,
+                "--generator" => options.gif_options.generator = value()?.parse()?,
+                "--solver" => options.gif_options.solver = value()?.parse()?,
+                "--seed" => options.gif_options.seed = Some(parse_number(&value()?)?),
+                "--events-per-frame" => {
+                    options.gif_options.events_per_frame = parse_number(&value()?)?
+                }
+                "--frame-delay" => {
+                    options.gif_options.frame_delay =
+                        Duration::from_millis(parse_number(&value()?)?)
+                }
+                "--scale" => options.gif_options.scale = parse_number(&value()?)?,
+                "--max-size" => options.gif_options.max_size = Some(parse_bytes(&value()?)?),
+                "--glyphs" => options.glyphs = Some(value()?.parse()?),
+                "--theme" => options.theme = Some(value()?.parse()?),
+                "-h" | "--help" => options.help = true,
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
+        }
+        if options.fast_export && options.record.is_none() {
+            return Err("--fast-export requires --record".to_string());
+        }
+        if options.replay.is_some() && (options.save_replay.is_some() || options.gif.is_some()) {
+            return Err("--replay can't be combined with --save-replay or --gif".to_string());
+        }
+        if let Some(arg) = gif_only_arg
+            && options.gif.is_none()
+        {
+            return Err(format!("{} requires --gif", arg));
+        }
+        if options.gif_options.events_per_frame == 0 || options.gif_options.scale == 0 {
+            return Err("--events-per-frame and --scale must be at least 1".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Parse a number, with an error message naming the invalid value
+fn parse_number<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("Invalid number: {}", s))
+}
+
+/// Parse maze dimensions given as `WxH`, each between 1 and 255
+fn parse_size(s: &str) -> Result<(u8, u8), String> {
+    let error = || {
+        format!(
+            "Invalid size '{}', expected WxH with values between 1 and 255",
+            s
+        )
+    };
+    let (width, height) = s.split_once(['x', 'X']).ok_or_else(error)?;
+    match (width.parse::<u8>(), height.parse::<u8>()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(error()),
+    }
+}
+
+/// Parse a number of bytes with an optional `K` or `M` suffix
+fn parse_bytes(s: &str) -> Result<u64, String> {
+    let upper = s.to_uppercase();
+    let (number, multiplier) = if let Some(number) = upper.strip_suffix('K') {
+        (number, 1024)
+    } else if let Some(number) = upper.strip_suffix('M') {
+        (number, 1024 * 1024)
+    } else {
+        (upper.as_str(), 1)
+    };
+    parse_number::<u64>(number)?
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Size too large: {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parse_recording_options() {
+        let options = parse(&["--record", "demo.cast", "--fast-export"]).unwrap();
+        assert_eq!(options.record, Some(PathBuf::from("demo.cast")));
+        assert!(options.fast_export);
+
+        assert!(parse(&["--record"]).is_err());
+        assert!(parse(&["--fast-export"]).is_err());
+        assert!(parse(&["--unknown"]).is_err());
+
+        let options = parse(&["--replay", "run.replay", "--record", "demo.cast"]).unwrap();
+        assert_eq!(options.replay, Some(PathBuf::from("run.replay")));
+        assert!(parse(&["--replay", "run.replay", "--save-replay", "copy.replay"]).is_err());
+    }
+
+    #[test]
+    fn parse_gif_options() {
+        let options = parse(&[
+            "--gif",
+            "maze.gif",
+            "--size",
+            "40x25",
+            "--solver",
+            "bfs",
+            "--max-size",
+            "2M",
+        ])
+        .unwrap();
+        assert_eq!(options.gif, Some(PathBuf::from("maze.gif")));
+        assert_eq!(options.gif_options.size, (40, 25));
+        assert!(matches!(options.gif_options.solver, Solver::Bfs));
+        assert_eq!(options.gif_options.max_size, Some(2 * 1024 * 1024));
+
+        assert!(parse(&["--size", "40x25"]).is_err());
+        assert!(parse(&["--gif", "maze.gif", "--size", "0x25"]).is_err());
+        assert!(parse(&["--gif", "maze.gif", "--max-size", "99999999999999M"]).is_err());
+    }
+
+    #[test]
+    fn parse_display_themes() {
+        let options = parse(&["--glyphs", "ASCII"]).unwrap();
+        assert_eq!(options.glyphs, Some(GlyphTheme::Ascii));
+        assert!(parse(&["--glyphs", "braille"]).is_err());
+
+        let options = parse(&["--theme", "colorblind"]).unwrap();
+        assert_eq!(options.theme, Some(Preset::Colorblind));
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+/// Time a run has been going on, not counting pauses
+pub(super) struct RunClock {
+    started: Instant,
+    /// Total time of the previous pauses
+    paused_for: Duration,
+    /// Start of the current pause, if paused
+    paused_since: Option<Instant>,
+    /// Time counted as elapsed on top of the running time, such as game hint costs
+    penalty: Duration,
+}
+
+impl RunClock {
+    pub(super) fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            paused_for: Duration::ZERO,
+            paused_since: None,
+            penalty: Duration::ZERO,
+        }
+    }
+
+    pub(super) fn is_paused(&self) -> bool {
+        self.paused_since.is_some()
+    }
+
+    pub(super) fn pause(&mut self) {
+        self.paused_since.get_or_insert_with(Instant::now);
+    }
+
+    pub(super) fn resume(&mut self) {
+        if let Some(since) = self.paused_since.take() {
+            self.paused_for += since.elapsed();
+        }
+    }
+
+    pub(super) fn elapsed(&self) -> Duration {
+        let paused = self.paused_for
+            + self
+                .paused_since
+                .map_or(Duration::ZERO, |since| since.elapsed());
+        self.started.elapsed().saturating_sub(paused) + self.penalty
+    }
+
+    /// Count some extra time as elapsed
+    pub(super) fn add_penalty(&mut self, penalty: Duration) {
+        self.penalty += penalty;
+    }
+
+    /// Running state and elapsed time for the visualizer HUD, e.g. `paused 01:05`
+    pub(super) fn hud_label(&self, finished: bool) -> String {
+        let state = if finished {
+            "finished"
+        } else if self.is_paused() {
+            "paused"
+        } else {
+            "running"
+        };
+        let seconds = self.elapsed().as_secs();
+        format!("{} {:02}:{:02}", state, seconds / 60, seconds % 60)
+    }
+}
@@ -0,0 +1,146 @@
+use rand::seq::SliceRandom;
+
+use crate::{
+    maze::{
+        Maze,
+        cell::{DoorColor, GridCell, PathType, WallType},
+    },
+    solvers::{Solver, find_route},
+};
+
+/// Maze cells per pellet
+const CELLS_PER_PELLET: usize = 12;
+/// Fewest steps between the start, each door and the goal along the route to the goal
+const MIN_STEPS_PER_DOOR: usize = 4;
+
+/// Whether a cell can be reached from `start`, with the existing BFS on a copy of the maze
+fn reachable(maze: &Maze, start: (u8, u8), cell: (u8, u8)) -> bool {
+    find_route(maze, Solver::Bfs, start, cell).is_some()
+}
+
+/// Empty maze cells in random order
+fn shuffled_empty_cells(maze: &Maze) -> Vec<(u8, u8)> {
+    let mut cells = (0..maze.height())
+        .flat_map(|y| (0..maze.width()).map(move |x| (x, y)))
+        .filter(|&cell| *maze.cell_at(cell) == GridCell::EMPTY)
+        .collect::<Vec<_>>();
+    cells.shuffle(&mut rand::rng());
+    cells
+}
+
+/// Scatter pellets on random empty cells reachable from `start`, one per [`CELLS_PER_PELLET`]
+/// cells. Returns the number of pellets.
+pub fn scatter_pellets(maze: &mut Maze, start: (u8, u8)) -> usize {
+    let count = (maze.width() as usize * maze.height() as usize / CELLS_PER_PELLET).max(1);
+    let cells = shuffled_empty_cells(maze)
+        .into_iter()
+        .filter(|&cell| reachable(maze, start, cell))
+        .take(count)
+        .collect::<Vec<_>>();
+    for &cell in &cells {
+        maze.set(cell, GridCell::PELLET);
+    }
+    cells.len()
+}
+
+/// Lock passages along the route from `start` to `goal` with doors of different colors, spread
+/// out along the route, and place the key of each door on a random empty cell, preferably off the
+/// route. Each key is reachable from the start once the doors before it are unlocked, so the goal
+/// can always be reached. Returns the number of doors.
+pub fn place_doors(maze: &mut Maze, start: (u8, u8), goal: (u8, u8)) -> usize {
+    let Some(route) = find_route(maze, Solver::Bfs, start, goal) else {
+        return 0;
+    };
+    let steps = route.len() - 1;
+    let count = (steps / MIN_STEPS_PER_DOOR)
+        .saturating_sub(1)
+        .min(DoorColor::ALL.len());
+    let doors = DoorColor::ALL
+        .into_iter()
+        .take(count)
+        .enumerate()
+        .map(|(index, color)| {
+            let step = (index + 1) * steps / (count + 1);
+            (route[step], route[step + 1], color)
+        })
+        .collect::<Vec<_>>();
+    for &(a, b, color) in &doors {
+        maze.set_cell_between(a, b, GridCell::Wall(WallType::Door(color)));
+    }
+
+    let mut placed = 0;
+    for (index, &(a, b, color)) in doors.iter().enumerate() {
+        // Unlock the doors before this one, whose keys are collected first
+        let mut unlocked = maze.walls_only();
+        for &(a, b, _) in &doors[..index] {
+            unlocked.set_cell_between(a, b, GridCell::EMPTY);
+        }
+        let mut cells = shuffled_empty_cells(maze);
+        // Stable sort, keeping the random order on and off the route
+        cells.sort_by_key(|cell| route.contains(cell));
+        match cells
+            .into_iter()
+            .find(|&cell| reachable(&unlocked, start, cell))
+        {
+            Some(key) => {
+                maze.set(key, GridCell::Path(PathType::Key(color)));
+                placed += 1;
+            }
+            None => {
+                // No room for the key, leave the passage open
+                maze.set_cell_between(a, b, GridCell::EMPTY);
+            }
+        }
+    }
+    placed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Corridor from the start at its left end to the goal at its right end
+    fn corridor_run() -> (Maze, (u8, u8), (u8, u8)) {
+        let mut maze = Maze::corridor(13, 1);
+        let (start, goal) = ((0, 0), (12, 0));
+        maze.set(start, GridCell::PACMAN);
+        maze.set(goal, GridCell::GOAL);
+        (maze, start, goal)
+    }
+
+    /// Position along the corridor of the door of the given color
+    fn door(maze: &Maze, color: DoorColor) -> u8 {
+        (0..12)
+            .find(|&x| {
+                *maze.cell_between((x, 0), (x + 1, 0)) == GridCell::Wall(WallType::Door(color))
+            })
+            .unwrap()
+    }
+
+    /// Position along the corridor of the first cell of the given kind, if any
+    fn position(maze: &Maze, cell: GridCell) -> Option<u8> {
+        (0..13).find(|&x| *maze.cell_at((x, 0)) == cell)
+    }
+
+    #[test]
+    fn keys_are_reachable_before_their_doors() {
+        let (mut maze, start, goal) = corridor_run();
+        assert_eq!(place_doors(&mut maze, start, goal), 2);
+
+        let key = |color| position(&maze, GridCell::Path(PathType::Key(color))).unwrap();
+        assert!(key(DoorColor::Red) <= door(&maze, DoorColor::Red));
+        assert!(key(DoorColor::Green) <= door(&maze, DoorColor::Green));
+        assert!(door(&maze, DoorColor::Red) < door(&maze, DoorColor::Green));
+        assert!(!reachable(&maze, start, goal));
+    }
+
+    #[test]
+    fn pellets_are_reachable_from_the_start() {
+        let (mut maze, start, goal) = corridor_run();
+        place_doors(&mut maze, start, goal);
+
+        assert_eq!(scatter_pellets(&mut maze, start), 1);
+        let pellet = position(&maze, GridCell::PELLET).unwrap();
+        assert!(pellet <= door(&maze, DoorColor::Red));
+    }
+}
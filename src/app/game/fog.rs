@@ -0,0 +1,138 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::maze::Maze;
+
+/// How much of the maze Pacman can see, the rest being hidden in the fog
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Visibility {
+    /// The whole maze is drawn
+    #[default]
+    Full,
+    /// Cells within the given number of maze cells of Pacman, through walls
+    Radius(u8),
+    /// Cells along the straight corridors leading away from Pacman
+    LineOfSight,
+}
+
+impl Visibility {
+    /// Visibilities offered in the settings menu
+    pub const CHOICES: [Visibility; 5] = [
+        Visibility::Full,
+        Visibility::Radius(2),
+        Visibility::Radius(3),
+        Visibility::Radius(5),
+        Visibility::LineOfSight,
+    ];
+
+    /// Maze cells Pacman can see from its position, or None if the whole maze is visible
+    pub fn visible_cells(self, maze: &Maze, pacman: (u8, u8)) -> Option<Vec<(u8, u8)>> {
+        match self {
+            Visibility::Full => None,
+            Visibility::Radius(radius) => {
+                let radius = radius as i32;
+                let (x, y) = (pacman.0 as i32, pacman.1 as i32);
+                let cells = (y - radius..=y + radius)
+                    .flat_map(|cy| (x - radius..=x + radius).map(move |cx| (cx, cy)))
+                    .filter(|&(cx, cy)| (cx - x).pow(2) + (cy - y).pow(2) <= radius.pow(2))
+                    .filter(|&(cx, cy)| {
+                        (0..maze.width() as i32).contains(&cx)
+                            && (0..maze.height() as i32).contains(&cy)
+                    })
+                    .map(|(cx, cy)| (cx as u8, cy as u8))
+                    .collect();
+                Some(cells)
+            }
+            Visibility::LineOfSight => {
+                let mut cells = vec![pacman];
+                for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let mut current = pacman;
+                    // Follow the corridor until a wall blocks the view
+                    while let (Some(x), Some(y)) = (
+                        current.0.checked_add_signed(dx),
+                        current.1.checked_add_signed(dy),
+                    ) && maze.is_passage_between(current, (x, y))
+                    {
+                        current = (x, y);
+                        cells.push(current);
+                    }
+                }
+                Some(cells)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Visibility::Full => write!(f, "Full"),
+            Visibility::Radius(radius) => write!(f, "Radius of {} cells", radius),
+            Visibility::LineOfSight => write!(f, "Line of sight"),
+        }
+    }
+}
+
+/// Grid coordinates of the given maze cells and of the walls and passages around them
+pub fn grid_coords(cells: &[(u8, u8)]) -> Vec<(u16, u16)> {
+    let mut coords = cells
+        .iter()
+        .flat_map(|&(x, y)| {
+            let (x, y) = (x as u16 * 2, y as u16 * 2);
+            (y..=y + 2).flat_map(move |gy| (x..=x + 2).map(move |gx| (gx, gy)))
+        })
+        .collect::<Vec<_>>();
+    coords.sort_unstable();
+    coords.dedup();
+    coords
+}
+
+/// How a grid cell is drawn in a fogged maze
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sight {
+    /// Never seen, drawn blank
+    Hidden,
+    /// Seen before, drawn dimmed
+    Seen,
+    /// In sight of Pacman
+    Visible,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Orientation;
+
+    /// A corridor along the top row, with an opening down at its left end
+    fn corridor_with_opening() -> Maze {
+        let mut maze = Maze::corridor(4, 3);
+        maze.remove_wall_cell_after((0, 0), Orientation::Horizontal);
+        maze
+    }
+
+    #[test]
+    fn line_of_sight_stops_at_walls() {
+        let maze = corridor_with_opening();
+        let mut in_sight = Visibility::LineOfSight
+            .visible_cells(&maze, (1, 0))
+            .unwrap();
+        in_sight.sort_unstable();
+        assert_eq!(in_sight, [(0, 0), (1, 0), (2, 0), (3, 0)]);
+    }
+
+    #[test]
+    fn radius_limits_visibility() {
+        let maze = corridor_with_opening();
+        let nearby = Visibility::Radius(1).visible_cells(&maze, (0, 0)).unwrap();
+        assert_eq!(nearby.len(), 3);
+        assert_eq!(Visibility::Full.visible_cells(&maze, (0, 0)), None);
+    }
+
+    #[test]
+    fn grid_coords_cover_walls_around_cells() {
+        // Two neighboring cells share the wall between them
+        assert_eq!(grid_coords(&[(0, 0), (1, 0)]).len(), 15);
+    }
+}
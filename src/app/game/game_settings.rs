@@ -1,2 +1,662 @@
-// TODO: Define the game settings struct
-// TODO: Define the function to allow users to customize game settings
+use std::{fmt, io::Stdout, path::PathBuf, time::Duration};
+
+use crossterm::{
+    ExecutableCommand,
+    style::{self, Attribute, Stylize},
+    terminal,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{
+        self,
+        game::{
+            fog::Visibility,
+            ghosts::{Ghosts, MAX_GHOSTS},
+        },
+    },
+    config::{self, Config, by_name, theme},
+    generators::Generator,
+    maze::cell::CellLayout,
+    solvers::Solver,
+};
+
+/// Name of the file keeping the last used game settings, in the data directory
+const SETTINGS_FILE: &str = "game_settings.toml";
+/// Shortest time limit derived from the difficulty, for tiny mazes
+const MIN_TIME_LIMIT: Duration = Duration::from_secs(15);
+/// Time limits offered in the settings menu besides the one derived from the difficulty, in seconds
+const TIME_LIMITS: [u64; 4] = [30, 60, 120, 300];
+/// Maze sizes offered in the settings menu, if they fit the terminal
+const SIZE_PRESETS: [(&str, u8, u8); 3] = [("Small", 10, 8), ("Medium", 20, 12), ("Large", 30, 16)];
+
+/// Difficulty of the game, setting the time allowed per maze cell and the speed of the ghosts and
+/// the opponent
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    /// Time allowed per maze cell
+    fn time_per_cell(self) -> Duration {
+        match self {
+            Difficulty::Easy => Duration::from_millis(1000),
+            Difficulty::Normal => Duration::from_millis(600),
+            Difficulty::Hard => Duration::from_millis(350),
+        }
+    }
+
+    /// Factor of the time between two ghost steps
+    fn ghost_slowdown(self) -> f64 {
+        match self {
+            Difficulty::Easy => 1.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 0.75,
+        }
+    }
+
+    /// Time between two steps of the opponent
+    pub fn opponent_step_duration(self) -> Duration {
+        match self {
+            Difficulty::Easy => Duration::from_millis(800),
+            Difficulty::Normal => Duration::from_millis(500),
+            Difficulty::Hard => Duration::from_millis(300),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Normal => write!(f, "Normal"),
+            Difficulty::Hard => write!(f, "Hard"),
+        }
+    }
+}
+
+/// Settings of the game runs, edited in the settings menu and kept in the data directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    pub width: u8,
+    pub height: u8,
+    pub difficulty: Difficulty,
+    /// Time limit in seconds, derived from the difficulty and the maze size if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_limit: Option<u64>,
+    #[serde(
+        serialize_with = "by_name::serialize_value",
+        deserialize_with = "by_name::deserialize_value"
+    )]
+    pub generator: Generator,
+    /// Whether Pacman and the goal start on random cells rather than opposite corners
+    pub random_start_goal: bool,
+    /// Number of ghosts, scaled to the maze size if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ghosts: Option<usize>,
+    /// How much of the maze Pacman can see
+    pub visibility: Visibility,
+    /// Solver of the computer-controlled runner racing Pacman to the goal, if any
+    #[serde(with = "by_name", skip_serializing_if = "Option::is_none")]
+    pub opponent: Option<Solver>,
+    /// Whether the opponent shows the cells its solver explores before running its route
+    pub opponent_explores: bool,
+    /// Whether two players race each other on the same keyboard, without ghosts or an opponent
+    pub two_players: bool,
+    /// Whether pellets are scattered in the maze, to collect before the goal counts
+    pub pellets: bool,
+    /// Whether doors lock the route to the goal, each opened by the key of its color
+    pub doors: bool,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        let (_, width, height) = SIZE_PRESETS[0];
+        GameSettings {
+            width,
+            height,
+            difficulty: Difficulty::default(),
+            time_limit: None,
+            generator: Generator::RecurBacktrack,
+            random_start_goal: false,
+            ghosts: None,
+            visibility: Visibility::default(),
+            opponent: None,
+            opponent_explores: false,
+            two_players: false,
+            pellets: false,
+            doors: false,
+        }
+    }
+}
+
+impl GameSettings {
+    /// Time limit of a run
+    pub fn time_limit(&self) -> Duration {
+        match self.time_limit {
+            Some(seconds) => Duration::from_secs(seconds),
+            None => self.auto_time_limit(),
+        }
+    }
+
+    /// Time limit derived from the difficulty and the maze size, in whole seconds
+    fn auto_time_limit(&self) -> Duration {
+        let cells = self.width as u32 * self.height as u32;
+        let limit = (self.difficulty.time_per_cell() * cells).max(MIN_TIME_LIMIT);
+        Duration::from_secs(limit.as_secs())
+    }
+
+    /// Number of ghosts in a run, none in two-player races
+    pub fn ghost_count(&self) -> usize {
+        if self.two_players {
+            return 0;
+        }
+        self.ghosts
+            .unwrap_or_else(|| Ghosts::count_for(self.width, self.height))
+    }
+
+    /// Solver of the opponent in a run, none in two-player races, behind doors it can't unlock or
+    /// with pellets it doesn't collect
+    pub fn opponent(&self) -> Option<Solver> {
+        self.opponent
+            .filter(|_| !self.two_players && !self.doors && !self.pellets)
+    }
+
+    /// Time between two ghost steps
+    pub fn ghost_step_duration(&self) -> Duration {
+        Ghosts::step_duration_for(self.width, self.height).mul_f64(self.difficulty.ghost_slowdown())
+    }
+
+    fn path() -> std::io::Result<PathBuf> {
+        config::data_dir()
+            .map(|dir| dir.join(SETTINGS_FILE))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "No data directory, set HOME or XDG_DATA_HOME",
+                )
+            })
+    }
+
+    /// Parse settings saved in a settings file, rejecting mazes without cells
+    fn parse(contents: &str) -> std::io::Result<Self> {
+        let settings: Self = toml::from_str(contents).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e.message().to_string())
+        })?;
+        if settings.width == 0 || settings.height == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Maze size {}x{} has no cells",
+                    settings.width, settings.height
+                ),
+            ));
+        }
+        Ok(settings)
+    }
+
+    /// Load the last used settings. If there are none, or they can't be read, start from the
+    /// `config` defaults.
+    pub fn load(config: &Config) -> Self {
+        let last = Self::path().and_then(|path| {
+            let contents = std::fs::read_to_string(&path)?;
+            Self::parse(&contents).map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!("Invalid game settings {}: {}", path.display(), e),
+                )
+            })
+        });
+        match last {
+            Ok(settings) => settings,
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    tracing::warn!("Ignoring the last game settings: {}", e);
+                }
+                let defaults = GameSettings::default();
+                GameSettings {
+                    width: config.width.unwrap_or(defaults.width),
+                    height: config.height.unwrap_or(defaults.height),
+                    generator: config.generator.unwrap_or(defaults.generator),
+                    ..defaults
+                }
+            }
+        }
+    }
+
+    /// Keep these settings for the next launch, returning the path of the settings file
+    pub fn save(&self) -> std::io::Result<PathBuf> {
+        let path = Self::path()?;
+        let contents = toml::to_string(self).map_err(std::io::Error::other)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, contents)?;
+        tracing::info!("Saved game settings to {}", path.display());
+        Ok(path)
+    }
+}
+
+/// Format a duration as minutes and seconds, e.g. `1:05`
+fn format_time(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Entry of the settings menu, showing the current value of a setting
+#[derive(Clone, Copy)]
+enum SettingsItem {
+    Start,
+    TwoPlayers(bool),
+    Size(u8, u8),
+    Difficulty(Difficulty),
+    TimeLimit(Duration, bool),
+    Generator(Generator),
+    RandomStartGoal(bool),
+    Ghosts(usize, bool),
+    Visibility(Visibility),
+    Opponent(Option<Solver>),
+    OpponentExplores(bool),
+    Pellets(bool),
+    Doors(bool),
+}
+
+impl SettingsItem {
+    fn all(settings: &GameSettings) -> [SettingsItem; 13] {
+        [
+            SettingsItem::Start,
+            SettingsItem::TwoPlayers(settings.two_players),
+            SettingsItem::Size(settings.width, settings.height),
+            SettingsItem::Difficulty(settings.difficulty),
+            SettingsItem::TimeLimit(settings.time_limit(), settings.time_limit.is_none()),
+            SettingsItem::Generator(settings.generator),
+            SettingsItem::RandomStartGoal(settings.random_start_goal),
+            SettingsItem::Ghosts(settings.ghost_count(), settings.ghosts.is_none()),
+            SettingsItem::Visibility(settings.visibility),
+            SettingsItem::Opponent(settings.opponent),
+            SettingsItem::OpponentExplores(settings.opponent_explores),
+            SettingsItem::Pellets(settings.pellets),
+            SettingsItem::Doors(settings.doors),
+        ]
+    }
+}
+
+/// Items are the same entry whatever their values, so the menu keeps its selection after an edit
+impl PartialEq for SettingsItem {
+    fn eq(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+impl fmt::Display for SettingsItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let auto = |is_auto: bool| if is_auto { " (auto)" } else { "" };
+        match *self {
+            SettingsItem::Start => write!(f, "Start game"),
+            SettingsItem::TwoPlayers(two_players) => write!(
+                f,
+                "Players: {}",
+                if two_players {
+                    "2, racing with arrows and WASD, without ghosts or an opponent"
+                } else {
+                    "1"
+                }
+            ),
+            SettingsItem::Size(width, height) => write!(f, "Maze size: {}x{}", width, height),
+            SettingsItem::Difficulty(difficulty) => write!(f, "Difficulty: {}", difficulty),
+            SettingsItem::TimeLimit(limit, is_auto) => {
+                write!(f, "Time limit: {}{}", format_time(limit), auto(is_auto))
+            }
+            SettingsItem::Generator(generator) => write!(f, "Generator: {}", generator),
+            SettingsItem::RandomStartGoal(random) => write!(
+                f,
+                "Start and goal: {}",
+                if random { "random" } else { "corners" }
+            ),
+            SettingsItem::Ghosts(count, is_auto) => {
+                write!(f, "Ghosts: {}{}", count, auto(is_auto))
+            }
+            SettingsItem::Visibility(visibility) => write!(f, "Visibility: {}", visibility),
+            SettingsItem::Opponent(solver) => write!(f, "Opponent: {}", OpponentChoice(solver)),
+            SettingsItem::OpponentExplores(explores) => write!(
+                f,
+                "Opponent search: {}",
+                if explores { "shown" } else { "hidden" }
+            ),
+            SettingsItem::Pellets(pellets) => write!(
+                f,
+                "Pellets: {}",
+                if pellets {
+                    "scattered, to collect before reaching the goal, without an opponent"
+                } else {
+                    "none"
+                }
+            ),
+            SettingsItem::Doors(doors) => write!(
+                f,
+                "Keys and doors: {}",
+                if doors {
+                    "doors lock the route to the goal, without an opponent"
+                } else {
+                    "none"
+                }
+            ),
+        }
+    }
+}
+
+/// Choice of a setting that is either derived from the other settings or set to a value
+#[derive(Clone, Copy, PartialEq)]
+enum Choice<T> {
+    /// Derived value, shown for reference
+    Auto(T),
+    Fixed(T),
+}
+
+impl<T: fmt::Display> fmt::Display for Choice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Choice::Auto(value) => write!(f, "Auto ({})", value),
+            Choice::Fixed(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Time limit choice, displayed as minutes and seconds
+#[derive(Clone, Copy, PartialEq)]
+struct TimeLimit(Duration);
+
+impl fmt::Display for TimeLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_time(self.0))
+    }
+}
+
+/// Opponent choice, by the solver finding its route
+#[derive(Clone, Copy, PartialEq)]
+struct OpponentChoice(Option<Solver>);
+
+impl fmt::Display for OpponentChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(solver) => write!(f, "{}", solver),
+            None => write!(f, "None"),
+        }
+    }
+}
+
+/// Maze size choice
+#[derive(Clone, Copy, PartialEq)]
+enum SizeChoice {
+    Preset(&'static str, u8, u8),
+    /// Largest maze fitting the terminal
+    Fit(u8, u8),
+    Custom,
+}
+
+impl fmt::Display for SizeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizeChoice::Preset(name, width, height) => write!(f, "{} ({}x{})", name, width, height),
+            SizeChoice::Fit(width, height) => {
+                write!(f, "Fit the terminal ({}x{})", width, height)
+            }
+            SizeChoice::Custom => write!(f, "Custom..."),
+        }
+    }
+}
+
+/// Largest maze dimensions fitting the terminal
+fn max_maze_dims() -> std::io::Result<(u8, u8)> {
+    let (columns, rows) = terminal::size()?;
+    Ok(app::fit_maze_dims(columns, rows, CellLayout::Full))
+}
+
+/// Let the user edit the game settings in a menu, until they start the game.
+/// The maze size is first shrunk to fit the terminal if needed.
+/// Returns false if the user exits instead.
+pub fn customize_settings(
+    stdout: &mut Stdout,
+    settings: &mut GameSettings,
+) -> std::io::Result<bool> {
+    let (max_width, max_height) = max_maze_dims()?;
+    // The config defaults may be out of range too
+    settings.width = settings.width.clamp(1, max_width.max(1));
+    settings.height = settings.height.clamp(1, max_height.max(1));
+
+    let mut selected = SettingsItem::Start;
+    loop {
+        let Some(item) = app::select_from_menu(
+            stdout,
+            "Game settings, select one to change it (use arrow keys and Enter, or Esc to exit):",
+            &SettingsItem::all(settings),
+            Some(selected),
+        )?
+        else {
+            return Ok(false);
+        };
+        selected = item;
+        match item {
+            SettingsItem::Start => return Ok(true),
+            SettingsItem::TwoPlayers(two_players) => settings.two_players = !two_players,
+            SettingsItem::Size(width, height) => {
+                let (max_width, max_height) = max_maze_dims()?;
+                let mut choices = SIZE_PRESETS
+                    .into_iter()
+                    .filter(|&(_, w, h)| w <= max_width && h <= max_height)
+                    .map(|(name, w, h)| SizeChoice::Preset(name, w, h))
+                    .collect::<Vec<_>>();
+                choices.push(SizeChoice::Fit(max_width, max_height));
+                choices.push(SizeChoice::Custom);
+                let current = choices.iter().copied().find(|choice| {
+                    matches!(*choice, SizeChoice::Preset(_, w, h) | SizeChoice::Fit(w, h)
+                        if (w, h) == (width, height))
+                });
+                let dims = match app::select_from_menu(
+                    stdout,
+                    "Select maze size (use arrow keys and Enter, or Esc to go back):",
+                    &choices,
+                    current.or(Some(SizeChoice::Custom)),
+                )? {
+                    Some(SizeChoice::Preset(_, w, h) | SizeChoice::Fit(w, h)) => Some((w, h)),
+                    Some(SizeChoice::Custom) => app::ask_maze_dimensions(
+                        stdout,
+                        |columns, rows| app::fit_maze_dims(columns, rows, CellLayout::Full),
+                        false,
+                        (Some(width), Some(height)),
+                    )?,
+                    None => None,
+                };
+                if let Some((width, height)) = dims {
+                    settings.width = width;
+                    settings.height = height;
+                }
+            }
+            SettingsItem::Difficulty(difficulty) => {
+                if let Some(difficulty) = app::select_from_menu(
+                    stdout,
+                    "Select difficulty, setting the time per maze cell and the speed of the ghosts \
+(use arrow keys and Enter, or Esc to go back):",
+                    &Difficulty::ALL,
+                    Some(difficulty),
+                )? {
+                    settings.difficulty = difficulty;
+                }
+            }
+            SettingsItem::TimeLimit(..) => {
+                let choices = std::iter::once(Choice::Auto(TimeLimit(settings.auto_time_limit())))
+                    .chain(
+                        TIME_LIMITS
+                            .into_iter()
+                            .map(|seconds| Choice::Fixed(TimeLimit(Duration::from_secs(seconds)))),
+                    )
+                    .collect::<Vec<_>>();
+                let current = match settings.time_limit {
+                    Some(seconds) => Choice::Fixed(TimeLimit(Duration::from_secs(seconds))),
+                    None => choices[0],
+                };
+                match app::select_from_menu(
+                    stdout,
+                    "Select time limit, auto depending on the difficulty and maze size \
+(use arrow keys and Enter, or Esc to go back):",
+                    &choices,
+                    Some(current),
+                )? {
+                    Some(Choice::Auto(_)) => settings.time_limit = None,
+                    Some(Choice::Fixed(TimeLimit(limit))) => {
+                        settings.time_limit = Some(limit.as_secs())
+                    }
+                    None => {}
+                }
+            }
+            SettingsItem::Generator(generator) => {
+                if let Some(generator) = app::select_from_menu(
+                    stdout,
+                    "Select maze generation algorithm (use arrow keys and Enter, or Esc to go back):",
+                    &app::GENERATORS,
+                    Some(generator),
+                )? {
+                    settings.generator = generator;
+                }
+            }
+            SettingsItem::RandomStartGoal(random) => settings.random_start_goal = !random,
+            SettingsItem::Ghosts(..) => {
+                let auto_count = Ghosts::count_for(settings.width, settings.height);
+                let choices = std::iter::once(Choice::Auto(auto_count))
+                    .chain((0..=MAX_GHOSTS).map(Choice::Fixed))
+                    .collect::<Vec<_>>();
+                match app::select_from_menu(
+                    stdout,
+                    "Select number of ghosts, auto depending on the maze size \
+(use arrow keys and Enter, or Esc to go back):",
+                    &choices,
+                    Some(settings.ghosts.map_or(choices[0], Choice::Fixed)),
+                )? {
+                    Some(Choice::Auto(_)) => settings.ghosts = None,
+                    Some(Choice::Fixed(count)) => settings.ghosts = Some(count),
+                    None => {}
+                }
+            }
+            SettingsItem::Visibility(visibility) => {
+                if let Some(visibility) = app::select_from_menu(
+                    stdout,
+                    "Select how much of the maze Pacman can see, cells seen before staying dimmed \
+(use arrow keys and Enter, or Esc to go back):",
+                    &Visibility::CHOICES,
+                    Some(visibility),
+                )? {
+                    settings.visibility = visibility;
+                }
+            }
+            SettingsItem::Opponent(solver) => {
+                let choices = std::iter::once(OpponentChoice(None))
+                    .chain(app::SOLVERS.into_iter().map(|s| OpponentChoice(Some(s))))
+                    .collect::<Vec<_>>();
+                if let Some(OpponentChoice(solver)) = app::select_from_menu(
+                    stdout,
+                    "Select the solver of a computer-controlled runner racing Pacman to the goal, \
+moving faster with the difficulty (use arrow keys and Enter, or Esc to go back):",
+                    &choices,
+                    Some(OpponentChoice(solver)),
+                )? {
+                    settings.opponent = solver;
+                }
+            }
+            SettingsItem::OpponentExplores(explores) => settings.opponent_explores = !explores,
+            SettingsItem::Pellets(pellets) => settings.pellets = !pellets,
+            SettingsItem::Doors(doors) => settings.doors = !doors,
+        }
+    }
+}
+
+/// Keep the settings for the next launch, telling the user if they couldn't be saved
+pub fn keep_settings(stdout: &mut Stdout, settings: &GameSettings) -> std::io::Result<()> {
+    if let Err(e) = settings.save() {
+        tracing::warn!("Failed to save the game settings: {}", e);
+        stdout.execute(style::PrintStyledContent(
+            format!("Failed to save the game settings: {}\r\n", e)
+                .with(theme::ui().error)
+                .attribute(Attribute::Bold),
+        ))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_derive_time_limit_and_round_trip() {
+        let mut settings = GameSettings {
+            width: 20,
+            height: 10,
+            ..Default::default()
+        };
+        assert_eq!(settings.time_limit(), Duration::from_secs(120));
+        settings.difficulty = Difficulty::Hard;
+        assert_eq!(settings.time_limit(), Duration::from_secs(70));
+        settings.width = 2;
+        assert_eq!(settings.time_limit(), MIN_TIME_LIMIT);
+        settings.time_limit = Some(30);
+        assert_eq!(settings.time_limit(), Duration::from_secs(30));
+
+        settings.generator = Generator::Kruskal;
+        settings.visibility = Visibility::Radius(3);
+        settings.opponent = Some(Solver::AStar);
+        let contents = toml::to_string(&settings).unwrap();
+        assert!(contents.contains("generator = \"Kruskal's Algorithm\""));
+        assert!(contents.contains("difficulty = \"hard\""));
+        assert!(contents.contains("opponent = \"A* Search Algorithm\""));
+        assert!(!contents.contains("ghosts"));
+        assert_eq!(toml::from_str::<GameSettings>(&contents).unwrap(), settings);
+        assert_eq!(
+            toml::from_str::<GameSettings>("").unwrap(),
+            GameSettings::default()
+        );
+    }
+
+    #[test]
+    fn opponent_and_ghosts_sit_out_races_and_collectibles() {
+        let mut settings = GameSettings {
+            opponent: Some(Solver::AStar),
+            ..Default::default()
+        };
+        assert_eq!(settings.opponent(), Some(Solver::AStar));
+        // Two-player races have neither ghosts nor an opponent, nor do mazes with doors or pellets
+        settings.doors = true;
+        assert_eq!(settings.opponent(), None);
+        settings.doors = false;
+        settings.pellets = true;
+        assert_eq!(settings.opponent(), None);
+        settings.pellets = false;
+        assert_eq!(settings.opponent(), Some(Solver::AStar));
+        settings.two_players = true;
+        assert_eq!(settings.ghost_count(), 0);
+        assert_eq!(settings.opponent(), None);
+    }
+
+    #[test]
+    fn settings_without_cells_are_rejected() {
+        assert_eq!(
+            GameSettings::parse("width = 12\nheight = 8").unwrap(),
+            GameSettings {
+                width: 12,
+                height: 8,
+                ..Default::default()
+            }
+        );
+        let error = GameSettings::parse("width = 0\nheight = 8").unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(GameSettings::parse("width = 12\nheight = 0").is_err());
+        assert!(GameSettings::parse("width = 256").is_err());
+    }
+}
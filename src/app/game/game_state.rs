@@ -1,13 +1,44 @@
 use crate::{
-    app::game::UiEvent,
-    generators::{Generator, generate_maze},
+    app::{
+        clock::RunClock,
+        game::{
+            UiEvent, collectibles,
+            fog::{self, Visibility},
+            game_settings::GameSettings,
+            ghosts::{Collision, Ghosts},
+            score::RunStats,
+        },
+    },
+    config::theme,
+    generators::generate_maze,
     maze::{
         Maze, Orientation,
-        cell::{GridCell, PathType},
+        cell::{DoorColor, GridCell, PathType, WallType},
         grid::Grid,
     },
+    solvers::{Solver, find_route},
+};
+use crossterm::style::{Attribute, Stylize};
+use rand::seq::IndexedRandom;
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::Sender,
+    },
+    time::{Duration, Instant},
 };
-use std::sync::mpsc::Sender;
+
+/// Number of steps of the route to the goal flashed by a hint
+const HINT_STEPS: usize = 5;
+/// How long a hint stays drawn
+const HINT_DURATION: Duration = Duration::from_millis(1500);
+/// Time taken off the timer by each hint
+const HINT_PENALTY: Duration = Duration::from_secs(5);
+/// Solver finding the route flashed by hints
+const HINT_SOLVER: Solver = Solver::AStar;
+/// Route of a hint, with the color of the key it leads to instead of the goal
+type HintRoute = (Vec<(u8, u8)>, Option<DoorColor>);
 
 #[derive(Debug, Copy, Clone)]
 pub enum Direction {
@@ -17,47 +48,173 @@ pub enum Direction {
     Right,
 }
 
+/// A player moving through the maze
+struct Player {
+    /// Cells of the player's route from the start, ending with its current position
+    route: Vec<(u8, u8)>,
+    /// Cell drawn at the player's position
+    avatar: GridCell,
+    /// Cell drawn on the cells of the player's route it left
+    trail: GridCell,
+    /// Number of steps the player took
+    steps: usize,
+    /// Number of steps back onto an already visited cell
+    backtracks: usize,
+    /// Keys picked up and not used yet
+    keys: Vec<DoorColor>,
+}
+
+impl Player {
+    fn new(start: (u8, u8), avatar: GridCell, trail: GridCell) -> Self {
+        Player {
+            route: vec![start],
+            avatar,
+            trail,
+            steps: 0,
+            backtracks: 0,
+            keys: Vec::new(),
+        }
+    }
+
+    fn position(&self) -> (u8, u8) {
+        *self
+            .route
+            .last()
+            .expect("A route has at least the start cell")
+    }
+
+    /// Whether the player left the given cell, and it is still on its route
+    fn visited(&self, cell: (u8, u8)) -> bool {
+        self.route[..self.route.len() - 1].contains(&cell)
+    }
+
+    /// Whether the player's route goes through the passage between two neighboring cells
+    fn passes_between(&self, a: (u8, u8), b: (u8, u8)) -> bool {
+        self.route
+            .windows(2)
+            .any(|pair| pair == [a, b] || pair == [b, a])
+    }
+}
+
 pub struct GameState {
     /// The maze being played
     maze: Maze,
-    /// Tracks where the player currently is
-    current: (u8, u8),
+    /// Pacman, followed by the second player in two-player games
+    players: Vec<Player>,
+    /// Start position of every player
+    start: (u8, u8),
     /// Goal position
     goal: (u8, u8),
+    /// Number of steps of the shortest route from the start to the goal
+    optimal_steps: usize,
+    /// Time limit of the run
+    time_limit: Duration,
+    /// How much of the maze the players can see
+    visibility: Visibility,
+    /// When to clear the hint being shown, if any
+    hint_until: Option<Instant>,
+    /// Number of pellets left to collect before the goal counts, shared with the timer thread
+    pellets_left: Arc<AtomicUsize>,
+    /// Pacman's position, shared with the ghosts thread
+    shared_pacman: Arc<Mutex<(u8, u8)>>,
+    /// Ghosts roaming the maze, moved by the ghosts thread
+    ghosts: Arc<Mutex<Ghosts>>,
+    /// Time the run has been going on, shared with the timer and ghosts threads
+    clock: Arc<Mutex<RunClock>>,
     /// Sender to send UI events of the maze's grid to the render thread
     ui_event_tx: Sender<UiEvent>,
 }
 
+/// Pick random start and goal cells, at least half the maze width and height apart when
+/// the maze is large enough
+fn random_start_goal_cells(width: u8, height: u8) -> ((u8, u8), (u8, u8)) {
+    let mut rng = rand::rng();
+    let cells = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .collect::<Vec<_>>();
+    let start = *cells
+        .choose(&mut rng)
+        .expect("The maze has at least one cell");
+    let distance = |(x, y): (u8, u8)| x.abs_diff(start.0) as u16 + y.abs_diff(start.1) as u16;
+    let min_distance = (width as u16 + height as u16) / 2;
+    let far = cells
+        .iter()
+        .copied()
+        .filter(|&cell| distance(cell) >= min_distance)
+        .collect::<Vec<_>>();
+    let goal = far
+        .choose(&mut rng)
+        .or_else(|| cells.iter().rev().find(|&&cell| cell != start))
+        .copied()
+        .unwrap_or(start);
+    (start, goal)
+}
+
 impl GameState {
-    /// Set up the initial game state with:
+    /// Set up the initial game state from the settings:
     /// * Maze generation algorithm.
     /// * Maze width & height.
     /// * Start & goal positions. Either randomized (with `random_start_goal = true`) or top left
     ///   for start cell and bottom right for goal cell.
+    /// * Time limit.
+    /// * Pacman, and the second player starting from the same cell in two-player games.
+    /// * Pellets, and doors locking the route to the goal with their keys, if enabled.
+    /// * Ghosts, away from Pacman.
     ///
     /// Panics if either width or height is 0.
     /// Return the initialized [`GameState`].
-    pub fn initialize(
-        width: u8,
-        height: u8,
-        generator: Generator,
-        ui_event_tx: Sender<UiEvent>,
-    ) -> Self {
+    pub fn initialize(settings: &GameSettings, ui_event_tx: Sender<UiEvent>) -> Self {
+        let (width, height) = (settings.width, settings.height);
         // Get the initial maze
         let mut maze = Maze::new(width, height, None);
         // Carve the maze with the generator algorithm
-        generate_maze(&mut maze, generator, None);
+        generate_maze(&mut maze, settings.generator, None);
 
-        let start = (0, 0);
+        let (start, goal) = if settings.random_start_goal {
+            random_start_goal_cells(width, height)
+        } else {
+            ((0, 0), (width - 1, height - 1))
+        };
+        // Generated mazes are perfect, so the goal is always reachable
+        let optimal_steps =
+            find_route(&maze, Solver::Bfs, start, goal).map_or(0, |route| route.len() - 1);
         maze.set(start, GridCell::PACMAN);
-
-        let goal = (width - 1, height - 1);
         maze.set(goal, GridCell::GOAL);
+        // Pellets go first, so keys don't end up behind the doors they unlock
+        let pellets = if settings.pellets {
+            collectibles::scatter_pellets(&mut maze, start)
+        } else {
+            0
+        };
+        if settings.doors {
+            let doors = collectibles::place_doors(&mut maze, start, goal);
+            tracing::info!("[game] Placed {} pellets and {} doors", pellets, doors);
+        }
+
+        let mut players = vec![Player::new(start, GridCell::PACMAN, GridCell::VISITED)];
+        if settings.two_players {
+            players.push(Player::new(
+                start,
+                GridCell::SECOND_PLAYER,
+                GridCell::SECOND_TRAIL,
+            ));
+        }
+
+        let ghosts = Ghosts::new(&maze, settings.ghost_count(), start);
 
         GameState {
             maze,
+            players,
+            start,
             goal,
-            current: start,
+            optimal_steps,
+            time_limit: settings.time_limit(),
+            visibility: settings.visibility,
+            hint_until: None,
+            pellets_left: Arc::new(AtomicUsize::new(pellets)),
+            shared_pacman: Arc::new(Mutex::new(start)),
+            ghosts: Arc::new(Mutex::new(ghosts)),
+            clock: Arc::new(Mutex::new(RunClock::start())),
             ui_event_tx,
         }
     }
@@ -67,133 +224,446 @@ impl GameState {
         self.maze.grid()
     }
 
-    /// Check if the goal has been reached.
-    pub fn goal_reached(&self) -> bool {
-        self.current == self.goal
+    /// Get the game state's maze reference.
+    pub fn maze(&self) -> &Maze {
+        &self.maze
     }
 
-    /// Attempt to move Pacman in the specified direction.
-    /// Marks the previous cell as visited and updates current position if move is valid.
-    /// Unmarks the path cell in between as empty if moving to an already visited cell.
-    /// Returns the new position if the move is successful, None otherwise.
-    pub fn move_pacman(&mut self, direction: Direction) -> Option<(u8, u8)> {
-        // Calculate new position + determine orientation for wall checking + path orientation to set if no wall
-        let (new_pos, check_pos, wall_orientation, path_orientation) = match direction {
-            Direction::Left => {
-                let new_x = self.current.0.checked_sub(1)?;
-                let new_pos = (new_x, self.current.1);
-                // Moving left: vertical wall to the right of new_pos
-                (
-                    new_pos,
-                    new_pos,
-                    Orientation::Vertical,
-                    Orientation::Horizontal,
-                )
-            }
-            Direction::Right => {
-                let new_x = self.current.0.checked_add(1)?;
-                if new_x >= self.maze.width() {
-                    return None;
-                }
-                let new_pos = (new_x, self.current.1);
-                // Moving right: vertical wall to the right of current
-                (
-                    new_pos,
-                    self.current,
-                    Orientation::Vertical,
-                    Orientation::Horizontal,
-                )
-            }
-            Direction::Up => {
-                let new_y = self.current.1.checked_sub(1)?;
-                let new_pos = (self.current.0, new_y);
-                // Moving up: horizontal wall below new_pos
-                (
-                    new_pos,
-                    new_pos,
-                    Orientation::Horizontal,
-                    Orientation::Vertical,
-                )
+    /// Get Pacman's current position.
+    pub fn pacman(&self) -> (u8, u8) {
+        self.players[0].position()
+    }
+
+    /// Get the goal position.
+    pub fn goal(&self) -> (u8, u8) {
+        self.goal
+    }
+
+    /// Get Pacman's position, kept up to date across threads as Pacman moves.
+    /// Only Pacman is shared, as ghosts only chase Pacman and two-player races have none.
+    pub fn shared_pacman(&self) -> Arc<Mutex<(u8, u8)>> {
+        self.shared_pacman.clone()
+    }
+
+    /// Get the ghosts, moved by the ghosts thread while Pacman's moves are checked against them.
+    pub fn shared_ghosts(&self) -> Arc<Mutex<Ghosts>> {
+        self.ghosts.clone()
+    }
+
+    /// Check if a ghost caught Pacman, as Pacman moved or as the ghosts stepped
+    pub fn caught(&self) -> bool {
+        self.ghosts.lock().expect("Ghosts lock poisoned").caught()
+    }
+
+    /// Get the number of pellets left to collect, kept up to date across threads as they are
+    /// collected.
+    pub fn shared_pellets_left(&self) -> Arc<AtomicUsize> {
+        self.pellets_left.clone()
+    }
+
+    /// Get the run's clock, which the timer and ghosts threads stop on while it's paused.
+    pub fn shared_clock(&self) -> Arc<Mutex<RunClock>> {
+        self.clock.clone()
+    }
+
+    /// Pause the run's clock, freezing the timer and the ghosts.
+    pub fn pause(&self) {
+        self.clock.lock().expect("Clock lock poisoned").pause();
+    }
+
+    /// Resume the run's clock.
+    pub fn resume(&self) {
+        self.clock.lock().expect("Clock lock poisoned").resume();
+    }
+
+    /// Get the statistics of the run so far, for each player.
+    pub fn stats(&self) -> Vec<RunStats> {
+        let elapsed = self.clock.lock().expect("Clock lock poisoned").elapsed();
+        self.players
+            .iter()
+            .map(|player| RunStats {
+                steps: player.steps,
+                backtracks: player.backtracks,
+                optimal_steps: self.optimal_steps,
+                time_left: self.time_limit.saturating_sub(elapsed),
+            })
+            .collect()
+    }
+
+    /// Send the cells the players can see to the render thread, if the maze is fogged.
+    /// The goal is always in sight, to head for.
+    /// Returns false if the render thread has exited.
+    fn send_visibility(&self) -> bool {
+        let mut cells = Vec::new();
+        for player in &self.players {
+            match self.visibility.visible_cells(&self.maze, player.position()) {
+                Some(visible) => cells.extend(visible),
+                None => return true,
             }
-            Direction::Down => {
-                let new_y = self.current.1.checked_add(1)?;
-                if new_y >= self.maze.height() {
-                    return None;
+        }
+        cells.push(self.goal);
+        self.ui_event_tx
+            .send(UiEvent::Visibility(fog::grid_coords(&cells)))
+            .is_ok()
+    }
+
+    /// Send every cell of the grid to the render thread, to draw the whole maze.
+    /// Returns false if the render thread has exited.
+    pub fn redraw(&self) -> bool {
+        let grid = self.grid();
+        self.send_visibility()
+            && (0..grid.height())
+                .flat_map(|y| (0..grid.width()).map(move |x| (x, y)))
+                .all(|coord| {
+                    self.ui_event_tx
+                        .send(UiEvent::GridUpdate {
+                            coord,
+                            new: grid[coord],
+                        })
+                        .is_ok()
+                })
+    }
+
+    /// Copy of the maze walls, with the doors the player holds keys for open
+    fn unlocked_maze(&self, player: usize) -> Maze {
+        let mut copy = self.maze.walls_only();
+        let keys = &self.players[player].keys;
+        for (x, y) in
+            (0..self.maze.height()).flat_map(|y| (0..self.maze.width()).map(move |x| (x, y)))
+        {
+            for next in [(x + 1, y), (x, y + 1)] {
+                if self.maze.is_in_bounds(next)
+                    && let GridCell::Wall(WallType::Door(color)) =
+                        *self.maze.cell_between((x, y), next)
+                    && keys.contains(&color)
+                {
+                    copy.set_cell_between((x, y), next, GridCell::EMPTY);
                 }
-                let new_pos = (self.current.0, new_y);
-                // Moving down: horizontal wall below current
-                (
-                    new_pos,
-                    self.current,
-                    Orientation::Horizontal,
-                    Orientation::Vertical,
-                )
             }
-        };
-
-        // Check for walls; disallow movement if a wall exists
-        if self.maze.is_wall_cell_after(check_pos, wall_orientation) {
-            return None;
         }
+        copy
+    }
 
-        // Check whether new_pos is visited and send UI updates to render thread
-        // It's fine if render thread is closed
-        if *self.maze.cell_at(new_pos) == GridCell::VISITED {
-            tracing::debug!("[game] Moving to already visited cell at {:?}", new_pos);
-            // Mark the current cell as empty path
-            let current_grid_coord = self.maze.set(self.current, GridCell::EMPTY);
-            self.ui_event_tx
-                .send(UiEvent::GridUpdate {
-                    coord: current_grid_coord,
-                    new: GridCell::EMPTY,
-                })
-                .ok();
+    /// Route of a hint from Pacman: to the goal through the doors Pacman holds keys for, or else
+    /// to the closest key it can reach, along with the key's color.
+    fn hint_route(&self) -> Option<HintRoute> {
+        let pacman = self.pacman();
+        let maze = self.unlocked_maze(0);
+        if let Some(route) = find_route(&maze, HINT_SOLVER, pacman, self.goal) {
+            return Some((route, None));
+        }
+        (0..self.maze.height())
+            .flat_map(|y| (0..self.maze.width()).map(move |x| (x, y)))
+            .filter_map(|cell| match *self.maze.cell_at(cell) {
+                GridCell::Path(PathType::Key(color)) => {
+                    let route = find_route(&maze, HINT_SOLVER, pacman, cell)?;
+                    Some((route, Some(color)))
+                }
+                _ => None,
+            })
+            .min_by_key(|(route, _)| route.len())
+    }
 
-            // Mark the route cell in between as empty path
-            let route_grid_coord =
-                self.maze
-                    .set_path_cell_after(check_pos, path_orientation, Some(PathType::Empty));
+    /// Flash the next steps of the route from Pacman to the goal, taking time off the timer.
+    /// While a locked door blocks the goal, the route leads to the closest key instead, and under
+    /// fog only the steps Pacman can see are flashed.
+    /// The route is found on a copy of the maze, leaving the played one untouched.
+    pub fn show_hint(&mut self) {
+        let pacman = self.pacman();
+        let Some((route, key)) = self.hint_route() else {
+            tracing::info!("[game] No hint from {:?}", pacman);
+            let msg = "No route to the goal or to a key from here, no hint taken";
             self.ui_event_tx
-                .send(UiEvent::GridUpdate {
-                    coord: route_grid_coord,
-                    new: GridCell::EMPTY,
-                })
+                .send(UiEvent::LogMessage(Some(
+                    msg.to_string()
+                        .with(theme::ui().error)
+                        .attribute(Attribute::Bold),
+                )))
                 .ok();
-        } else {
-            tracing::debug!("[game] Moving to new cell at {:?}", new_pos);
-            // Mark the current cell as visited,
-            let current_grid_coord = self.maze.set(self.current, GridCell::VISITED);
+            return;
+        };
+        if let Some(color) = key {
+            let msg = format!(
+                "A locked door blocks the goal, the hint leads to the {} key",
+                color
+            );
             self.ui_event_tx
-                .send(UiEvent::GridUpdate {
-                    coord: current_grid_coord,
-                    new: GridCell::VISITED,
-                })
+                .send(UiEvent::LogMessage(Some(
+                    msg.with(theme::ui().accent).attribute(Attribute::Bold),
+                )))
                 .ok();
+        }
+        // Under fog, the hint stops where Pacman's sight ends rather than lifting the fog
+        let visible = self.visibility.visible_cells(&self.maze, pacman);
+        let steps = route
+            .iter()
+            .take(HINT_STEPS + 1)
+            .take_while(|cell| visible.as_ref().is_none_or(|cells| cells.contains(cell)))
+            .copied()
+            .collect::<Vec<_>>();
+        // Grid coordinates of the cells of the next steps and the passages between them
+        let coords = steps
+            .windows(2)
+            .flat_map(|pair| {
+                let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+                [
+                    (x1 as u16 + x2 as u16 + 1, y1 as u16 + y2 as u16 + 1),
+                    (x2 as u16 * 2 + 1, y2 as u16 * 2 + 1),
+                ]
+            })
+            .collect();
+        tracing::info!("[game] Showing a hint from {:?}", pacman);
+        self.clock
+            .lock()
+            .expect("Clock lock poisoned")
+            .add_penalty(HINT_PENALTY);
+        self.hint_until = Some(Instant::now() + HINT_DURATION);
+        self.ui_event_tx.send(UiEvent::ShowHint(coords)).ok();
+    }
 
-            // Mark the path cell in between as a route cell
-            let route_grid_coord = self
-                .maze
-                .set_path_cell_after(check_pos, path_orientation, None);
-            self.ui_event_tx
-                .send(UiEvent::GridUpdate {
-                    coord: route_grid_coord,
-                    new: GridCell::Path(PathType::Route(path_orientation)),
-                })
-                .ok();
+    /// Clear the hint once it has been shown long enough.
+    pub fn clear_expired_hint(&mut self) {
+        if self.hint_until.is_some_and(|until| Instant::now() >= until) {
+            self.hint_until = None;
+            self.ui_event_tx.send(UiEvent::HideHint).ok();
         }
+    }
 
-        // Mark the new position as Pacman
-        let new_grid_coord = self.maze.set(new_pos, GridCell::PACMAN);
+    /// Get the index of the player who reached the goal once every pellet was collected, if any.
+    pub fn winner(&self) -> Option<usize> {
+        if self.pellets_left.load(Ordering::Relaxed) > 0 {
+            return None;
+        }
+        self.players
+            .iter()
+            .position(|player| player.position() == self.goal)
+    }
+
+    /// Set a maze cell and send its update to the render thread.
+    /// It's fine if the render thread is closed.
+    fn update_cell(&mut self, cell: (u8, u8), new: GridCell) {
+        let coord = self.maze.set(cell, new);
+        self.ui_event_tx
+            .send(UiEvent::GridUpdate { coord, new })
+            .ok();
+    }
+
+    /// Set the passage between two neighboring cells to a route cell if `on_route`, or an empty
+    /// path otherwise, and send its update to the render thread.
+    fn update_passage(&mut self, a: (u8, u8), b: (u8, u8), on_route: bool) {
+        // Route cells are oriented along the passage
+        let orientation = if a.1 == b.1 {
+            Orientation::Horizontal
+        } else {
+            Orientation::Vertical
+        };
+        let path = if on_route {
+            PathType::Route(orientation)
+        } else {
+            PathType::Empty
+        };
+        let coord = self
+            .maze
+            .set_path_cell_after(a.min(b), orientation, Some(path));
         self.ui_event_tx
             .send(UiEvent::GridUpdate {
-                coord: new_grid_coord,
-                new: GridCell::PACMAN,
+                coord,
+                new: GridCell::Path(path),
             })
-            .ok(); // Error when render thread is closed, ignore
+            .ok();
+    }
+
+    /// Another player than the given one standing on a maze cell, if any
+    fn other_player_at(&self, player: usize, cell: (u8, u8)) -> Option<&Player> {
+        self.players
+            .iter()
+            .enumerate()
+            .find(|&(index, other)| index != player && other.position() == cell)
+            .map(|(_, other)| other)
+    }
+
+    /// Cell to draw on a maze cell the given player backtracked from: another player on it, the
+    /// goal, another player's route through it, or an empty path
+    fn cell_left_by(&self, player: usize, cell: (u8, u8)) -> GridCell {
+        if let Some(other) = self.other_player_at(player, cell) {
+            return other.avatar;
+        }
+        if cell == self.goal {
+            return GridCell::GOAL;
+        }
+        self.players
+            .iter()
+            .find(|other| other.visited(cell))
+            .map_or(GridCell::EMPTY, |other| other.trail)
+    }
+
+    /// Unlock the door between two neighboring cells if the player holds the key of its color,
+    /// using the key up. The door is replaced as the player moves through it.
+    /// Returns whether the door was unlocked.
+    fn unlock_door(&mut self, player: usize, from: (u8, u8), to: (u8, u8)) -> bool {
+        if !self.maze.is_in_bounds(to) {
+            return false;
+        }
+        let GridCell::Wall(WallType::Door(color)) = *self.maze.cell_between(from, to) else {
+            return false;
+        };
+        let keys = &mut self.players[player].keys;
+        let Some(index) = keys.iter().position(|&key| key == color) else {
+            return false;
+        };
+        keys.remove(index);
+        tracing::info!("[game] Player {} unlocked the {} door", player + 1, color);
+        true
+    }
+
+    /// Pick up the pellet or key on the cell the player moves to, if any
+    fn pick_up(&mut self, player: usize, cell: (u8, u8)) {
+        match *self.maze.cell_at(cell) {
+            GridCell::PELLET => {
+                self.pellets_left.fetch_sub(1, Ordering::Relaxed);
+            }
+            GridCell::Path(PathType::Key(color)) => {
+                tracing::info!("[game] Player {} picked up the {} key", player + 1, color);
+                self.players[player].keys.push(color);
+            }
+            _ => {}
+        }
+    }
+
+    /// Attempt to move the given player in the specified direction.
+    /// Marks the previous cell with the player's trail and updates its position if the move is
+    /// valid. Moving back to the previous cell of its route backtracks instead, unmarking the
+    /// cell left and the path cell in between.
+    /// Players can't step onto each other, except on the start cell they all begin from.
+    /// Moving onto a pellet or a key picks it up, and moving into a door unlocks it with the key
+    /// of its color, if the player holds one.
+    /// Returns the new position if the move is successful, None otherwise.
+    pub fn move_player(&mut self, player: usize, direction: Direction) -> Option<(u8, u8)> {
+        let current = self.players[player].position();
+        let (x, y) = current;
+        let new_pos = match direction {
+            Direction::Up => (x, y.checked_sub(1)?),
+            Direction::Down => (x, y.checked_add(1)?),
+            Direction::Left => (x.checked_sub(1)?, y),
+            Direction::Right => (x.checked_add(1)?, y),
+        };
+
+        if new_pos != self.start && self.other_player_at(player, new_pos).is_some() {
+            tracing::debug!("[game] Player {} bumped into another player", player + 1);
+            return None;
+        }
+        // Check for walls and the maze bounds; disallow movement if a wall or locked door exists
+        if !self.maze.is_passage_between(current, new_pos)
+            && !self.unlock_door(player, current, new_pos)
+        {
+            return None;
+        }
+        self.pick_up(player, new_pos);
+
+        // Update the cells left behind, and send UI updates to render thread
+        let route = &self.players[player].route;
+        if route.len() >= 2 && route[route.len() - 2] == new_pos {
+            tracing::debug!("[game] Moving to already visited cell at {:?}", new_pos);
+            self.players[player].backtracks += 1;
+            self.players[player].route.pop();
+            // Unmark the current cell and the route cell in between, unless another player's
+            // route goes through them
+            self.update_cell(current, self.cell_left_by(player, current));
+            let on_route = self
+                .players
+                .iter()
+                .any(|other| other.passes_between(current, new_pos));
+            self.update_passage(current, new_pos, on_route);
+        } else {
+            tracing::debug!("[game] Moving to new cell at {:?}", new_pos);
+            self.players[player].route.push(new_pos);
+            // Mark the current cell as visited, and the path cell in between as a route cell
+            let left = match self.other_player_at(player, current) {
+                Some(other) => other.avatar,
+                None if current == self.goal => GridCell::GOAL,
+                None => self.players[player].trail,
+            };
+            self.update_cell(current, left);
+            self.update_passage(current, new_pos, true);
+        }
+
+        // Mark the new position with the player
+        self.players[player].steps += 1;
+        self.update_cell(new_pos, self.players[player].avatar);
+        if player == 0 {
+            *self
+                .shared_pacman
+                .lock()
+                .expect("Pacman position lock poisoned") = new_pos;
+            // Ghosts only step every few hundred milliseconds, so check for Pacman running into
+            // or through them on every move
+            let mut ghosts = self.ghosts.lock().expect("Ghosts lock poisoned");
+            if ghosts.collide(current, new_pos) == Collision::SentHome {
+                self.ui_event_tx.send(ghosts.ui_event()).ok();
+            }
+        }
+        self.send_visibility();
+
+        Some(new_pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn players_race_along_their_own_routes() {
+        // A single corridor from the start to the goal
+        let mut maze = Maze::corridor(3, 1);
+        let (start, goal) = ((0, 0), (2, 0));
+        maze.set(start, GridCell::PACMAN);
+        maze.set(goal, GridCell::GOAL);
+        let ghosts = Ghosts::new(&maze, 0, start);
+        let (ui_event_tx, _ui_event_rx) = std::sync::mpsc::channel();
+        let mut state = GameState {
+            maze,
+            players: vec![
+                Player::new(start, GridCell::PACMAN, GridCell::VISITED),
+                Player::new(start, GridCell::SECOND_PLAYER, GridCell::SECOND_TRAIL),
+            ],
+            start,
+            goal,
+            optimal_steps: 2,
+            time_limit: Duration::from_secs(60),
+            visibility: Visibility::Full,
+            hint_until: None,
+            pellets_left: Arc::new(AtomicUsize::new(0)),
+            shared_pacman: Arc::new(Mutex::new(start)),
+            ghosts: Arc::new(Mutex::new(ghosts)),
+            clock: Arc::new(Mutex::new(RunClock::start())),
+            ui_event_tx,
+        };
+        let passage = GridCell::Path(PathType::Route(Orientation::Horizontal));
+
+        // Walls block both players, and they can't step onto each other off the start cell
+        assert_eq!(state.move_player(1, Direction::Up), None);
+        assert_eq!(state.move_player(0, Direction::Right), Some((1, 0)));
+        assert_eq!(state.move_player(1, Direction::Right), None);
+        assert_eq!(state.move_player(0, Direction::Right), Some(goal));
+        assert_eq!(state.move_player(1, Direction::Right), Some((1, 0)));
+        assert_eq!(*state.shared_pacman.lock().unwrap(), goal);
+
+        // Backtracking across Pacman's route leaves Pacman's trail behind
+        assert_eq!(state.move_player(1, Direction::Left), Some(start));
+        assert_eq!(*state.maze.cell_at((1, 0)), GridCell::VISITED);
+        assert_eq!(*state.maze.cell_between(start, (1, 0)), passage);
+        assert_eq!(*state.maze.cell_at(start), GridCell::SECOND_PLAYER);
 
-        // Update current position
-        self.current = new_pos;
+        // Pacman backtracks onto the start cell, shared with the second player
+        assert_eq!(state.move_player(0, Direction::Left), Some((1, 0)));
+        assert_eq!(*state.maze.cell_at(goal), GridCell::GOAL);
+        assert_eq!(state.move_player(0, Direction::Left), Some(start));
+        assert_eq!(*state.maze.cell_at((1, 0)), GridCell::EMPTY);
+        assert_eq!(*state.maze.cell_between(start, (1, 0)), GridCell::EMPTY);
 
-        Some(self.current)
+        let stats = state.stats();
+        assert_eq!((stats[0].steps, stats[0].backtracks), (4, 2));
+        assert_eq!((stats[1].steps, stats[1].backtracks), (2, 1));
     }
 }
@@ -0,0 +1,330 @@
+use std::{
+    sync::{Arc, Mutex, atomic::AtomicBool, mpsc::Sender},
+    time::Duration,
+};
+
+use rand::seq::IndexedRandom;
+
+use crate::{
+    app::{clock::RunClock, game::UiEvent},
+    maze::{Maze, get_neighbors},
+    solvers::{Solver, find_route},
+};
+
+/// How long the ghosts head to their home corners before chasing Pacman
+const SCATTER_DURATION: Duration = Duration::from_secs(7);
+/// How long the ghosts chase Pacman before they get frightened
+const CHASE_DURATION: Duration = Duration::from_secs(20);
+/// How long the ghosts flee from Pacman before scattering again
+const FRIGHTENED_DURATION: Duration = Duration::from_secs(5);
+/// Maze cells per ghost, up to [`MAX_GHOSTS`]
+const CELLS_PER_GHOST: usize = 150;
+pub const MAX_GHOSTS: usize = 4;
+
+/// What the ghosts are currently doing, cycling through scatter, chase and frightened
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GhostMode {
+    /// Head to their home corners, giving Pacman some room
+    Scatter,
+    /// Pursue Pacman along the shortest route
+    Chase,
+    /// Wander away from Pacman at half speed. Pacman sends the ghosts it touches back home.
+    Frightened,
+}
+
+impl GhostMode {
+    /// Mode of the ghosts after the given time since the start of the run
+    fn at(elapsed: Duration) -> Self {
+        let cycle = SCATTER_DURATION + CHASE_DURATION + FRIGHTENED_DURATION;
+        let elapsed = Duration::from_nanos((elapsed.as_nanos() % cycle.as_nanos()) as u64);
+        if elapsed < SCATTER_DURATION {
+            GhostMode::Scatter
+        } else if elapsed < SCATTER_DURATION + CHASE_DURATION {
+            GhostMode::Chase
+        } else {
+            GhostMode::Frightened
+        }
+    }
+}
+
+struct Ghost {
+    position: (u8, u8),
+    /// Position before the last step, to tell when the ghost and Pacman swap cells
+    previous: (u8, u8),
+    /// Corner the ghost starts from and heads to when scattering
+    home: (u8, u8),
+    /// Solver finding the ghost's route to its target
+    solver: Solver,
+}
+
+/// Ghosts roaming the maze of a game run
+pub struct Ghosts {
+    /// Copy of the maze walls, to find routes without touching the played maze
+    maze: Maze,
+    ghosts: Vec<Ghost>,
+    mode: GhostMode,
+    /// Number of steps taken, to move at half speed when frightened
+    steps: usize,
+    /// Whether a ghost caught Pacman, ending the run
+    caught: bool,
+}
+
+impl Ghosts {
+    /// Number of ghosts for a maze of the given size, one per [`CELLS_PER_GHOST`] cells
+    pub fn count_for(width: u8, height: u8) -> usize {
+        (width as usize * height as usize / CELLS_PER_GHOST).clamp(1, MAX_GHOSTS)
+    }
+
+    /// Time between two ghost steps for a maze of the given size. Ghosts are slower in small
+    /// mazes, where they are never far from Pacman.
+    pub fn step_duration_for(width: u8, height: u8) -> Duration {
+        let millis = 800u64.saturating_sub(10 * (width as u64 + height as u64));
+        Duration::from_millis(millis.max(250))
+    }
+
+    /// Place `count` ghosts in the corners and the center of the maze, away from `pacman`.
+    /// Ghosts alternate between BFS and A* to find their way.
+    pub fn new(maze: &Maze, count: usize, pacman: (u8, u8)) -> Self {
+        let (right, bottom) = (maze.width() - 1, maze.height() - 1);
+        let homes = [
+            (right, 0),
+            (0, bottom),
+            (right / 2, bottom / 2),
+            (right, bottom),
+            (0, 0),
+        ];
+        let ghosts = homes
+            .into_iter()
+            .filter(|&home| home != pacman)
+            .cycle()
+            .zip([Solver::Bfs, Solver::AStar].into_iter().cycle())
+            .take(count)
+            .map(|(home, solver)| Ghost {
+                position: home,
+                previous: home,
+                home,
+                solver,
+            })
+            .collect();
+        Ghosts {
+            maze: maze.walls_only(),
+            ghosts,
+            mode: GhostMode::Scatter,
+            steps: 0,
+            caught: false,
+        }
+    }
+
+    /// Whether a ghost that isn't frightened is on Pacman's cell.
+    /// Frightened ghosts on Pacman's cell are sent back home instead.
+    pub fn catch(&mut self, pacman: (u8, u8)) -> bool {
+        self.collide(pacman, pacman) == Collision::Caught
+    }
+
+    /// Check for ghosts running into Pacman as it moves from `from` to `to`: ghosts on `to`, and
+    /// ghosts that swapped cells with Pacman, moving from `to` to `from` in their last step.
+    /// Frightened ghosts running into Pacman are sent back home.
+    pub fn collide(&mut self, from: (u8, u8), to: (u8, u8)) -> Collision {
+        let hit = |ghost: &Ghost| {
+            ghost.position == to || (ghost.position == from && ghost.previous == to)
+        };
+        if self.mode == GhostMode::Frightened {
+            let mut collision = Collision::None;
+            for ghost in self.ghosts.iter_mut().filter(|ghost| hit(ghost)) {
+                ghost.position = ghost.home;
+                ghost.previous = ghost.home;
+                collision = Collision::SentHome;
+            }
+            collision
+        } else if self.ghosts.iter().any(hit) {
+            self.caught = true;
+            Collision::Caught
+        } else {
+            Collision::None
+        }
+    }
+
+    /// Whether a ghost caught Pacman, either as Pacman moved or as the ghosts stepped
+    pub fn caught(&self) -> bool {
+        self.caught
+    }
+
+    /// Move every ghost one step in the given mode, toward its target or away from Pacman
+    pub fn step(&mut self, mode: GhostMode, pacman: (u8, u8)) {
+        self.mode = mode;
+        self.steps += 1;
+        for ghost in &mut self.ghosts {
+            ghost.previous = ghost.position;
+        }
+        if mode == GhostMode::Frightened && self.steps.is_multiple_of(2) {
+            return;
+        }
+        let mut rng = rand::rng();
+        for ghost in &mut self.ghosts {
+            let next = match mode {
+                GhostMode::Chase | GhostMode::Scatter => {
+                    let target = if mode == GhostMode::Chase {
+                        pacman
+                    } else {
+                        ghost.home
+                    };
+                    find_route(&self.maze, ghost.solver, ghost.position, target)
+                        .and_then(|route| route.get(1).copied())
+                }
+                GhostMode::Frightened => {
+                    let distance = |(x, y): (u8, u8)| x.abs_diff(pacman.0) + y.abs_diff(pacman.1);
+                    let open = get_neighbors(ghost.position, &self.maze)
+                        .filter(|&next| self.maze.is_passage_between(ghost.position, next))
+                        .collect::<Vec<_>>();
+                    // Prefer moving away from Pacman, unless cornered
+                    let away = open
+                        .iter()
+                        .copied()
+                        .filter(|&next| distance(next) >= distance(ghost.position))
+                        .collect::<Vec<_>>();
+                    if away.is_empty() { &open } else { &away }
+                        .choose(&mut rng)
+                        .copied()
+                }
+            };
+            if let Some(next) = next {
+                ghost.position = next;
+            }
+        }
+    }
+
+    /// Event drawing the ghosts at their grid coordinates, for the render thread
+    pub fn ui_event(&self) -> UiEvent {
+        let coords = self
+            .ghosts
+            .iter()
+            .map(|ghost| {
+                (
+                    ghost.position.0 as u16 * 2 + 1,
+                    ghost.position.1 as u16 * 2 + 1,
+                )
+            })
+            .collect();
+        UiEvent::Ghosts {
+            coords,
+            frightened: self.mode == GhostMode::Frightened,
+        }
+    }
+}
+
+/// Outcome of ghosts running into Pacman
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collision {
+    None,
+    /// Only frightened ghosts ran into Pacman, and were sent back home
+    SentHome,
+    /// A ghost that isn't frightened caught Pacman
+    Caught,
+}
+
+/// Move the ghosts every `step_duration` until one catches Pacman, sending their positions to
+/// the render thread. The ghosts, Pacman's position and the clock of the run are shared with the
+/// main thread, which checks for collisions as Pacman moves, and the ghosts don't move while the
+/// clock is paused.
+/// This function will be run in a separate thread, and returns once Pacman is caught or
+/// `should_stop` is set.
+pub fn move_ghosts(
+    ghosts: &Mutex<Ghosts>,
+    step_duration: Duration,
+    pacman: Arc<Mutex<(u8, u8)>>,
+    clock: &Mutex<RunClock>,
+    should_stop: &AtomicBool,
+    ui_event_tx: Sender<UiEvent>,
+) -> std::io::Result<()> {
+    let lock_ghosts = || ghosts.lock().expect("Ghosts lock poisoned");
+    if ui_event_tx.send(lock_ghosts().ui_event()).is_err() {
+        return Ok(());
+    }
+    loop {
+        std::thread::sleep(step_duration);
+        if should_stop.load(std::sync::atomic::Ordering::Acquire) {
+            return Ok(());
+        }
+        let (elapsed, is_paused) = {
+            let clock = clock.lock().expect("Clock lock poisoned");
+            (clock.elapsed(), clock.is_paused())
+        };
+        if is_paused {
+            continue;
+        }
+        // Hold the ghosts while they step, so Pacman's moves are checked against whole steps
+        let mut ghosts = lock_ghosts();
+        let pacman = *pacman.lock().expect("Pacman position lock poisoned");
+        // Pacman may have run into a ghost since the last step
+        if ghosts.catch(pacman) {
+            break;
+        }
+        ghosts.step(GhostMode::at(elapsed), pacman);
+        let caught = ghosts.catch(pacman);
+        if ui_event_tx.send(ghosts.ui_event()).is_err() {
+            // Receiver has been dropped, exit the thread
+            return Ok(());
+        }
+        if caught {
+            break;
+        }
+    }
+    tracing::info!("[ghosts] Pacman was caught");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ghost at the right end of a corridor, away from Pacman at its left end
+    fn corridor_ghost() -> Ghosts {
+        let ghosts = Ghosts::new(&Maze::corridor(4, 2), 1, (0, 0));
+        assert_eq!(ghosts.ghosts[0].position, (3, 0));
+        ghosts
+    }
+
+    #[test]
+    fn ghosts_chase_and_scatter() {
+        let mut ghosts = corridor_ghost();
+        ghosts.step(GhostMode::Chase, (0, 0));
+        assert_eq!(ghosts.ghosts[0].position, (2, 0));
+        ghosts.step(GhostMode::Scatter, (0, 0));
+        assert_eq!(ghosts.ghosts[0].position, (3, 0));
+        ghosts.step(GhostMode::Chase, (2, 0));
+        assert!(ghosts.catch((2, 0)));
+        assert!(ghosts.caught());
+    }
+
+    #[test]
+    fn frightened_ghosts_are_sent_home() {
+        let mut ghosts = corridor_ghost();
+        ghosts.step(GhostMode::Chase, (2, 0));
+        // Frightened ghosts move at half speed, staying on Pacman's cell for this step
+        ghosts.step(GhostMode::Frightened, (2, 0));
+        assert_eq!(ghosts.ghosts[0].position, (2, 0));
+        assert!(!ghosts.catch((2, 0)));
+        assert!(!ghosts.caught());
+        assert_eq!(ghosts.ghosts[0].position, (3, 0));
+    }
+
+    #[test]
+    fn ghosts_swapping_cells_with_pacman_collide() {
+        let mut ghosts = corridor_ghost();
+        ghosts.step(GhostMode::Chase, (2, 0));
+        assert_eq!(ghosts.ghosts[0].position, (2, 0));
+        assert!(!ghosts.caught());
+        assert_eq!(ghosts.collide((2, 0), (3, 0)), Collision::Caught);
+        assert!(ghosts.caught());
+        assert_eq!(ghosts.collide((1, 0), (0, 0)), Collision::None);
+        ghosts.mode = GhostMode::Frightened;
+        assert_eq!(ghosts.collide((2, 0), (3, 0)), Collision::SentHome);
+        assert_eq!(ghosts.ghosts[0].position, (3, 0));
+    }
+
+    #[test]
+    fn ghost_mode_follows_the_clock() {
+        assert_eq!(GhostMode::at(Duration::ZERO), GhostMode::Scatter);
+        assert_eq!(GhostMode::at(SCATTER_DURATION), GhostMode::Chase);
+    }
+}
@@ -1,7 +1,20 @@
+mod collectibles;
+mod fog;
 mod game_settings;
 mod game_state;
+mod ghosts;
+mod opponent;
+mod score;
 
-use crate::{app, generators::Generator, maze::cell::GridCell};
+use crate::{
+    app::{
+        self,
+        clock::RunClock,
+        keymap::{self, Action, Context, Keymap},
+    },
+    config::{Config, theme},
+    maze::cell::{GlyphTheme, GridCell},
+};
 use crossterm::{
     ExecutableCommand, cursor,
     event::{self, Event, KeyCode},
@@ -9,15 +22,21 @@ use crossterm::{
     style::{self, Attribute, Color, StyledContent, Stylize},
     terminal::{self, ClearType},
 };
+use fog::Sight;
+use game_settings::GameSettings;
 use game_state::{Direction, GameState};
+use opponent::Opponent;
+use score::{HighScore, HighScores, RunStats};
 use std::{
+    collections::HashSet,
     io::{Stdout, Write},
     sync::{
-        Arc,
-        atomic::AtomicBool,
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize},
         mpsc::{Receiver, Sender},
     },
-    time::{Duration, Instant},
+    thread::JoinHandle,
+    time::Duration,
 };
 
 #[derive(Debug)]
@@ -32,8 +51,39 @@ enum UiEvent {
     GridInit { width: u16, height: u16 },
     /// Update a single cell in the grid
     GridUpdate { coord: (u16, u16), new: GridCell },
+    /// Move the ghosts to the given grid coordinates, drawn over the maze cells
+    Ghosts {
+        coords: Vec<(u16, u16)>,
+        /// Whether the ghosts are frightened, which highlights them
+        frightened: bool,
+    },
+    /// Grid coordinates Pacman can see in a fogged maze. Other cells are hidden, or dimmed if
+    /// they were seen before.
+    Visibility(Vec<(u16, u16)>),
+    /// Move the opponent to the given grid coordinates, drawn over the maze cells
+    Opponent((u16, u16)),
+    /// Mark the given grid coordinates as explored by the opponent's solver
+    OpponentExplored(Vec<(u16, u16)>),
+    /// Highlight the given grid coordinates of the route to the goal, over the maze cells
+    ShowHint(Vec<(u16, u16)>),
+    /// Draw back the maze cells under the hint
+    HideHint,
     /// Log a message to the terminal below the maze
     LogMessage(Option<StyledContent<String>>),
+    /// Draw the key bindings of the given context over the maze, skipping other events until
+    /// they are hidden
+    ShowHelp(Context),
+    /// Clear the key bindings. The maze must be sent again to be drawn.
+    HideHelp,
+    /// Replace the maze with a message asking for a terminal of at least the given
+    /// (columns, rows), skipping other events until it fits
+    TerminalTooSmall { required: (u16, u16) },
+    /// Clear the message once the terminal fits. The maze must be sent again to be drawn.
+    TerminalFits,
+    /// Clear the maze while the game is paused, skipping grid events until it is shown again
+    HideMaze,
+    /// Stop hiding the maze. The maze must be sent again to be drawn.
+    ShowMaze,
 }
 
 /// Game run result after a single run
@@ -43,17 +93,30 @@ enum GameRunResult {
     GoalReached,
     /// Timer runs out before goal is reached
     Timeout,
+    /// A ghost catches Pacman before goal is reached
+    Caught,
+    /// The opponent reaches the goal first
+    Outrun,
+    /// A player reaches the goal first in a two-player race, by index
+    Won(usize),
     /// Game is canceled by user
     Canceled,
 }
 
+/// Key bindings of the game, split across the keyboard in two-player races
+fn key_context(settings: &GameSettings) -> Context {
+    if settings.two_players {
+        Context::TwoPlayer
+    } else {
+        Context::Game
+    }
+}
+
 /// Timeout for polling input events in the input thread, a.k.a.
 /// how often to check for done/cancel flags for the game
 const USER_INPUT_EVENT_POLL_TIMEOUT: Duration = Duration::from_millis(100);
 /// Timeout for receiving input events, a.k.a. how often to check for render done/cancel flags
 const INPUT_RECV_TIMEOUT: Duration = Duration::from_millis(100);
-/// Max time for one run
-const GAME_RUN_DURATION: Duration = Duration::from_secs(60);
 /// Tick duration for the game timer
 const GAME_TIMER_TICK_DURATION: Duration = Duration::from_secs(1);
 
@@ -68,33 +131,235 @@ fn render_ui_events(
     let mut stdout = stdout.lock();
     // Store grid dimensions once received
     let mut grid_dims = None;
+    // Cells of the maze, to draw them back once the ghosts covering them move away
+    let mut cells = Vec::new();
+    // Ghosts and the opponent, drawn over the maze cells
+    let mut overlays = Overlays::default();
+    // How each grid cell is drawn, once the maze is fogged
+    let mut sights: Option<Vec<Sight>> = None;
+    // Grid coordinates of the hint drawn over the maze cells
+    let mut hint: Vec<(u16, u16)> = Vec::new();
+    // Whether the key bindings are drawn over the maze
+    let mut showing_help = false;
+    // Whether the terminal is too small for the maze, which is then replaced by a message
+    let mut too_small = false;
+    // Whether the maze is hidden while the game is paused
+    let mut maze_hidden = false;
 
     // Closure to handle a single UI event
     let mut handle_event = |event: UiEvent| -> std::io::Result<()> {
         match event {
             UiEvent::GridInit { width, height } => {
                 grid_dims = Some((width, height));
+                cells = vec![GridCell::EMPTY; width as usize * height as usize];
+                sights = None;
             }
-            UiEvent::GridUpdate { coord, new } => {
-                match grid_dims {
-                    Some(grid_dims) => {
-                        if coord.0 >= grid_dims.0 || coord.1 >= grid_dims.1 {
-                            // Out of bounds, skip rendering
-                            return Ok(());
-                        }
-                    }
-                    // Grid dimensions not yet received, cannot render
-                    None => return Ok(()),
-                }
-                // Move the cursor to the specified coordinate and print the
-                // new cell using the grid dimensions
+            UiEvent::ShowHelp(context) => {
+                showing_help = true;
+                keymap::draw_help(&mut stdout, context)?;
+            }
+            UiEvent::HideHelp => {
+                showing_help = false;
+                queue!(stdout, terminal::Clear(ClearType::All))?;
+                stdout.flush()?;
+            }
+            UiEvent::TerminalTooSmall { required } => {
+                too_small = true;
+                let msg = format!(
+                    "Terminal size is too small ({}x{}) for the maze to display. The timer is paused.\r\n",
+                    required.0, required.1
+                );
                 queue!(
                     stdout,
-                    cursor::MoveTo(coord.0 * GridCell::CELL_WIDTH, coord.1),
-                    style::Print(new)
+                    terminal::Clear(ClearType::All),
+                    cursor::MoveTo(0, 0),
+                    style::PrintStyledContent(
+                        msg.with(theme::ui().accent).attribute(Attribute::Bold)
+                    ),
+                    style::PrintStyledContent(
+                        "Please resize the terminal, or press Esc to exit...\r\n"
+                            .with(theme::ui().hint)
+                            .attribute(Attribute::Bold)
+                    )
                 )?;
                 stdout.flush()?;
             }
+            UiEvent::TerminalFits => {
+                too_small = false;
+                queue!(stdout, terminal::Clear(ClearType::All))?;
+                stdout.flush()?;
+            }
+            UiEvent::HideMaze | UiEvent::ShowMaze => {
+                maze_hidden = matches!(event, UiEvent::HideMaze);
+                queue!(stdout, terminal::Clear(ClearType::All))?;
+                stdout.flush()?;
+            }
+            UiEvent::GridUpdate { coord, new } => {
+                // Grid dimensions not yet received, cannot render
+                let Some((width, height)) = grid_dims else {
+                    return Ok(());
+                };
+                if coord.0 >= width || coord.1 >= height {
+                    // Out of bounds, skip rendering
+                    return Ok(());
+                }
+                cells[coord.1 as usize * width as usize + coord.0 as usize] = new;
+                // The key bindings or the terminal size message cover the maze, unless it's hidden
+                if showing_help || too_small || maze_hidden {
+                    return Ok(());
+                }
+                // Print the new cell at the specified coordinate, unless a ghost covers it
+                redraw_cell(
+                    &mut stdout,
+                    coord,
+                    width,
+                    &cells,
+                    sights.as_deref(),
+                    &overlays,
+                )?;
+                stdout.flush()?;
+            }
+            UiEvent::Ghosts { coords, frightened } => {
+                let previous = std::mem::replace(&mut overlays.ghosts, coords);
+                overlays.ghosts_frightened = frightened;
+                let Some((width, _)) =
+                    grid_dims.filter(|_| !showing_help && !too_small && !maze_hidden)
+                else {
+                    return Ok(());
+                };
+                // Draw back the cells the ghosts left, then the ghosts
+                for coord in previous.into_iter().chain(overlays.ghosts.clone()) {
+                    redraw_cell(
+                        &mut stdout,
+                        coord,
+                        width,
+                        &cells,
+                        sights.as_deref(),
+                        &overlays,
+                    )?;
+                }
+                stdout.flush()?;
+            }
+            UiEvent::Opponent(coord) => {
+                let previous = overlays.opponent.replace(coord);
+                let Some((width, _)) =
+                    grid_dims.filter(|_| !showing_help && !too_small && !maze_hidden)
+                else {
+                    return Ok(());
+                };
+                for coord in previous.into_iter().chain([coord]) {
+                    redraw_cell(
+                        &mut stdout,
+                        coord,
+                        width,
+                        &cells,
+                        sights.as_deref(),
+                        &overlays,
+                    )?;
+                }
+                stdout.flush()?;
+            }
+            UiEvent::OpponentExplored(coords) => {
+                overlays.explored.extend(coords.iter().copied());
+                let Some((width, _)) =
+                    grid_dims.filter(|_| !showing_help && !too_small && !maze_hidden)
+                else {
+                    return Ok(());
+                };
+                for coord in coords {
+                    redraw_cell(
+                        &mut stdout,
+                        coord,
+                        width,
+                        &cells,
+                        sights.as_deref(),
+                        &overlays,
+                    )?;
+                }
+                stdout.flush()?;
+            }
+            UiEvent::Visibility(coords) => {
+                let Some((width, height)) = grid_dims else {
+                    return Ok(());
+                };
+                let fog = sights
+                    .get_or_insert_with(|| vec![Sight::Hidden; width as usize * height as usize]);
+                // Cells out of sight stay dimmed
+                let mut changed = Vec::new();
+                for (index, sight) in fog.iter_mut().enumerate() {
+                    if *sight == Sight::Visible {
+                        *sight = Sight::Seen;
+                        changed.push(index);
+                    }
+                }
+                for coord in coords
+                    .into_iter()
+                    .filter(|coord| coord.0 < width && coord.1 < height)
+                {
+                    let index = coord.1 as usize * width as usize + coord.0 as usize;
+                    fog[index] = Sight::Visible;
+                    changed.push(index);
+                }
+                if showing_help || too_small || maze_hidden {
+                    return Ok(());
+                }
+                changed.sort_unstable();
+                changed.dedup();
+                for index in changed {
+                    let coord = (
+                        (index % width as usize) as u16,
+                        (index / width as usize) as u16,
+                    );
+                    redraw_cell(
+                        &mut stdout,
+                        coord,
+                        width,
+                        &cells,
+                        sights.as_deref(),
+                        &overlays,
+                    )?;
+                }
+                stdout.flush()?;
+            }
+            UiEvent::ShowHint(coords) => {
+                hint = coords;
+                if grid_dims.is_none() || showing_help || too_small || maze_hidden {
+                    return Ok(());
+                }
+                for coord in &hint {
+                    queue!(
+                        stdout,
+                        cursor::MoveTo(coord.0 * GridCell::CELL_WIDTH, coord.1),
+                        style::PrintStyledContent(
+                            " ".repeat(GridCell::CELL_WIDTH as usize)
+                                .with(theme::ui().hint)
+                                .attribute(Attribute::Reverse)
+                        )
+                    )?;
+                }
+                stdout.flush()?;
+            }
+            UiEvent::HideHint => {
+                let previous = std::mem::take(&mut hint);
+                let Some((width, _)) =
+                    grid_dims.filter(|_| !showing_help && !too_small && !maze_hidden)
+                else {
+                    return Ok(());
+                };
+                for coord in previous {
+                    redraw_cell(
+                        &mut stdout,
+                        coord,
+                        width,
+                        &cells,
+                        sights.as_deref(),
+                        &overlays,
+                    )?;
+                }
+                stdout.flush()?;
+            }
+            // The key bindings or the terminal size message cover the log
+            UiEvent::LogMessage(_) if showing_help || too_small => {}
             UiEvent::LogMessage(msg) => {
                 // Log message to terminal below the maze
                 app::log_terminal(
@@ -140,13 +405,122 @@ fn render_ui_events(
     Ok(())
 }
 
-/// Spawn a run of the game, including user interaction, timer, and maze rendering
-fn start_game(
-    stdout: &mut Stdout,
-    width: u8,
-    height: u8,
-    generator: Generator,
-) -> std::io::Result<GameRunResult> {
+/// Characters and marks drawn over the maze cells
+#[derive(Default)]
+struct Overlays {
+    /// Grid coordinates of the ghosts
+    ghosts: Vec<(u16, u16)>,
+    ghosts_frightened: bool,
+    /// Grid coordinates of the opponent, if racing one
+    opponent: Option<(u16, u16)>,
+    /// Grid coordinates of the cells the opponent's solver explored
+    explored: HashSet<(u16, u16)>,
+}
+
+/// What is drawn over a maze cell
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Overlay {
+    Ghost { frightened: bool },
+    Opponent,
+    Explored,
+}
+
+impl Overlays {
+    /// What covers the given cell. Ghosts cover any cell, the opponent any cell but Pacman's,
+    /// and the cells the opponent explored are only marked while empty.
+    fn at(&self, coord: (u16, u16), cell: GridCell) -> Option<Overlay> {
+        if self.ghosts.contains(&coord) {
+            Some(Overlay::Ghost {
+                frightened: self.ghosts_frightened,
+            })
+        } else if self.opponent == Some(coord) && cell != GridCell::PACMAN {
+            Some(Overlay::Opponent)
+        } else if self.explored.contains(&coord) && cell == GridCell::EMPTY {
+            Some(Overlay::Explored)
+        } else {
+            None
+        }
+    }
+}
+
+/// Draw back the grid cell at the given coordinate of a grid `width` cells wide, with what
+/// covers it and how much of it is in sight
+fn redraw_cell(
+    stdout: &mut impl Write,
+    coord: (u16, u16),
+    width: u16,
+    cells: &[GridCell],
+    sights: Option<&[Sight]>,
+    overlays: &Overlays,
+) -> std::io::Result<()> {
+    let index = coord.1 as usize * width as usize + coord.0 as usize;
+    let cell = cells[index];
+    print_cell(
+        stdout,
+        coord,
+        cell,
+        sights.map_or(Sight::Visible, |sights| sights[index]),
+        overlays.at(coord, cell),
+    )
+}
+
+/// Print a grid cell at its coordinate: blank in the fog, dimmed if it was only seen before, or
+/// covered by the given overlay if it is in sight
+fn print_cell(
+    stdout: &mut impl Write,
+    coord: (u16, u16),
+    cell: GridCell,
+    sight: Sight,
+    overlay: Option<Overlay>,
+) -> std::io::Result<()> {
+    queue!(
+        stdout,
+        cursor::MoveTo(coord.0 * GridCell::CELL_WIDTH, coord.1)
+    )?;
+    match (sight, overlay) {
+        (Sight::Hidden, _) => queue!(
+            stdout,
+            style::Print(" ".repeat(GridCell::CELL_WIDTH as usize))
+        ),
+        (Sight::Seen, _) => queue!(
+            stdout,
+            style::SetAttribute(Attribute::Dim),
+            style::Print(cell),
+            style::SetAttribute(Attribute::NormalIntensity),
+        ),
+        (Sight::Visible, Some(Overlay::Ghost { frightened })) => print_ghost(stdout, frightened),
+        (Sight::Visible, Some(Overlay::Opponent)) => {
+            queue!(stdout, style::Print(GridCell::OPPONENT))
+        }
+        // The visited symbol, in the color of the opponent
+        (Sight::Visible, Some(Overlay::Explored)) => queue!(
+            stdout,
+            style::PrintStyledContent(
+                GlyphTheme::current()
+                    .symbol(GridCell::VISITED)
+                    .with(GridCell::OPPONENT.color().unwrap_or(Color::Reset))
+            )
+        ),
+        (Sight::Visible, None) => queue!(stdout, style::Print(cell)),
+    }
+}
+
+/// Print a ghost at the cursor, in reverse video while frightened
+fn print_ghost(stdout: &mut impl Write, frightened: bool) -> std::io::Result<()> {
+    if frightened {
+        queue!(
+            stdout,
+            style::SetAttribute(Attribute::Reverse),
+            style::Print(GridCell::GHOST),
+            style::SetAttribute(Attribute::NoReverse),
+        )
+    } else {
+        queue!(stdout, style::Print(GridCell::GHOST))
+    }
+}
+
+/// Spawn a run of the game, including user interaction, timer, ghosts, and maze rendering
+fn start_game(stdout: &mut Stdout, settings: &GameSettings) -> std::io::Result<GameRunResult> {
     // Clear screen
     execute!(
         stdout,
@@ -167,7 +541,7 @@ fn start_game(
     });
 
     // Initialize game state and render initial maze
-    let game_state = GameState::initialize(width, height, generator, ui_event_tx.clone());
+    let game_state = GameState::initialize(settings, ui_event_tx.clone());
     // Send grid dimensions to render thread
     if ui_event_tx
         .send(UiEvent::GridInit {
@@ -180,61 +554,125 @@ fn start_game(
         return Ok(GameRunResult::Canceled);
     }
     // Send initial grid cells to render thread
-    for y in 0..game_state.grid().height() {
-        for x in 0..game_state.grid().width() {
-            if ui_event_tx
-                .send(UiEvent::GridUpdate {
-                    coord: (x, y),
-                    new: game_state.grid()[(x, y)],
-                })
-                .is_err()
-            {
-                // Just return if render thread has exited
-                return Ok(GameRunResult::Canceled);
-            }
-        }
+    if !game_state.redraw() {
+        // Just return if render thread has exited
+        return Ok(GameRunResult::Canceled);
     }
 
     let (user_input_event_tx, user_input_event_rx) = std::sync::mpsc::channel::<UserInputEvent>();
+    // Both the input thread and the game loop resolve keys with the same bindings
+    let context = key_context(settings);
 
     // Spawn user input thread
     let should_stop_for_input = should_stop.clone();
     let input_thread_handle = std::thread::spawn(move || -> std::io::Result<()> {
         listen_to_user_input(
             user_input_event_tx,
+            context,
             USER_INPUT_EVENT_POLL_TIMEOUT,
             &should_stop_for_input,
         )
     });
 
     // Spawn a thread to start the timer
-    let start_time = Instant::now();
+    let clock = game_state.shared_clock();
+    let pellets_left = game_state.shared_pellets_left();
+    let time_limit = settings.time_limit();
     let should_stop_for_timer = should_stop.clone();
+    let ui_event_tx_for_timer = ui_event_tx.clone();
     let timer_thread_handle = std::thread::spawn(move || -> std::io::Result<()> {
         start_timer(
-            start_time,
-            GAME_RUN_DURATION,
+            &clock,
+            &pellets_left,
+            time_limit,
             GAME_TIMER_TICK_DURATION,
             &should_stop_for_timer,
-            ui_event_tx,
+            ui_event_tx_for_timer,
         )
     });
 
+    // Spawn a thread to move the ghosts, sharing them and Pacman's position with the game state
+    let ghosts = game_state.shared_ghosts();
+    let ghost_step_duration = settings.ghost_step_duration();
+    let pacman_for_ghosts = game_state.shared_pacman();
+    let clock_for_ghosts = game_state.shared_clock();
+    let should_stop_for_ghosts = should_stop.clone();
+    let ui_event_tx_for_ghosts = ui_event_tx.clone();
+    let ghosts_thread_handle = std::thread::spawn(move || -> std::io::Result<()> {
+        ghosts::move_ghosts(
+            &ghosts,
+            ghost_step_duration,
+            pacman_for_ghosts,
+            &clock_for_ghosts,
+            &should_stop_for_ghosts,
+            ui_event_tx_for_ghosts,
+        )
+    });
+
+    // Spawn a thread to move the opponent along the route of its solver, if racing one
+    let opponent_arrived = Arc::new(AtomicBool::new(false));
+    let opponent_thread_handle = settings
+        .opponent()
+        .and_then(|solver| {
+            Opponent::new(
+                game_state.maze(),
+                solver,
+                game_state.pacman(),
+                game_state.goal(),
+                settings.opponent_explores,
+            )
+        })
+        .map(|opponent| {
+            let opponent_step_duration = settings.difficulty.opponent_step_duration();
+            let clock_for_opponent = game_state.shared_clock();
+            let should_stop_for_opponent = should_stop.clone();
+            let arrived = opponent_arrived.clone();
+            let ui_event_tx_for_opponent = ui_event_tx.clone();
+            std::thread::spawn(move || -> std::io::Result<()> {
+                opponent::move_opponent(
+                    opponent,
+                    opponent_step_duration,
+                    &clock_for_opponent,
+                    &should_stop_for_opponent,
+                    &arrived,
+                    ui_event_tx_for_opponent,
+                )
+            })
+        });
+
     let grid_height = game_state.grid().height();
 
     // Start game loop in main thread
-    let game_result = game_loop(
+    let threads = RunThreads {
+        timer: timer_thread_handle,
+        ghosts: ghosts_thread_handle,
+        opponent: opponent_thread_handle,
+        opponent_arrived,
+    };
+    let (game_result, stats) = game_loop(
         game_state,
+        context,
+        ui_event_tx,
         user_input_event_rx,
         INPUT_RECV_TIMEOUT,
         &should_stop,
-        &timer_thread_handle,
+        &threads,
     )?;
-    tracing::debug!("[game] Game loop exited with result: {:?}", game_result);
+    tracing::debug!(
+        "[game] Game loop exited with result: {:?}, {:?}",
+        game_result,
+        stats
+    );
 
-    tracing::debug!("[game] Waiting for render and input threads to finish...");
+    tracing::debug!("[game] Waiting for render, input, ghosts and opponent threads to finish...");
     input_thread_handle.join().expect("Input thread panicked")?;
     tracing::debug!("[game] Input thread finished");
+    threads.ghosts.join().expect("Ghosts thread panicked")?;
+    tracing::debug!("[game] Ghosts thread finished");
+    if let Some(handle) = threads.opponent {
+        handle.join().expect("Opponent thread panicked")?;
+        tracing::debug!("[game] Opponent thread finished");
+    }
     // At this point, all UI event senders are dropped, so the render thread will exit
     // Wait for render and input threads to finish
     render_thread_handle
@@ -249,74 +687,357 @@ fn start_game(
                 stdout,
                 grid_height,
                 Some(
-                    "Congratulations! You reached the goal! Press Enter to continue, or Esc to exit."
-                        .with(Color::Green)
+                    "Congratulations! You reached the goal! Press Enter to see your score, or Esc to exit."
+                        .with(theme::ui().success)
                         .attribute(Attribute::Bold),
                 ),
             )?;
         }
         GameRunResult::Timeout => {
+            // Races have results rather than high scores
+            let msg = format!(
+                "Time's up! You failed to reach the goal. Press Enter to see the {}, or Esc to exit.",
+                if settings.two_players {
+                    "results"
+                } else {
+                    "high scores"
+                }
+            );
+            app::log_terminal(
+                stdout,
+                grid_height,
+                Some(msg.with(theme::ui().error).attribute(Attribute::Bold)),
+            )?;
+        }
+        GameRunResult::Caught => {
             app::log_terminal(
                 stdout,
                 grid_height,
                 Some(
-                    "Time's up! You failed to reach the goal. Press Enter to continue, or Esc to exit."
-                        .with(Color::Red)
+                    "Caught by a ghost! Press Enter to see the high scores, or Esc to exit."
+                        .with(theme::ui().error)
                         .attribute(Attribute::Bold),
                 ),
             )?;
         }
+        GameRunResult::Outrun => {
+            let msg = format!(
+                "The opponent ({}) reached the goal first! Press Enter to see the high scores, or Esc to exit.",
+                settings
+                    .opponent()
+                    .map_or(String::new(), |solver| solver.to_string())
+            );
+            app::log_terminal(
+                stdout,
+                grid_height,
+                Some(msg.with(theme::ui().error).attribute(Attribute::Bold)),
+            )?;
+        }
+        GameRunResult::Won(winner) => {
+            let msg = format!(
+                "Player {} wins the race! Press Enter to see the results, or Esc to exit.",
+                winner + 1
+            );
+            app::log_terminal(
+                stdout,
+                grid_height,
+                Some(msg.with(theme::ui().success).attribute(Attribute::Bold)),
+            )?;
+        }
         GameRunResult::Canceled => {
             // Just return immediately
             return Ok(game_result);
         }
     }
 
+    if !wait_for_enter()? {
+        return Ok(GameRunResult::Canceled);
+    }
+    show_results(stdout, settings, &game_result, &stats)?;
+    if !wait_for_enter()? {
+        return Ok(GameRunResult::Canceled);
+    }
+    Ok(game_result)
+}
+
+/// Wait for the user to press Enter to go on, returning false if Esc is pressed instead
+fn wait_for_enter() -> std::io::Result<bool> {
     loop {
         if let Event::Key(event::KeyEvent { code, kind, .. }) = event::read()?
             && kind == event::KeyEventKind::Press
         {
             match code {
-                KeyCode::Enter => {
-                    break;
-                }
-                KeyCode::Esc => {
-                    return Ok(GameRunResult::Canceled);
-                }
+                KeyCode::Enter => return Ok(true),
+                KeyCode::Esc => return Ok(false),
                 _ => {}
             }
         }
     }
-    Ok(game_result)
+}
+
+/// Clear the screen and show the score of the run, how its route compares to the optimal one,
+/// and the high scores of the maze size and generator.
+/// Only single-player runs reaching the goal are scored and recorded in the high score file.
+/// Two-player races show how each player's route compares to the optimal one instead, however
+/// they ended.
+fn show_results(
+    stdout: &mut Stdout,
+    settings: &GameSettings,
+    game_result: &GameRunResult,
+    stats: &[RunStats],
+) -> std::io::Result<()> {
+    let [stats] = stats else {
+        let winner = match *game_result {
+            GameRunResult::Won(winner) => Some(winner),
+            _ => None,
+        };
+        return show_race_results(stdout, winner, stats);
+    };
+    let table = HighScores::table_name(
+        settings.width,
+        settings.height,
+        settings.generator,
+        settings.pellets,
+        settings.doors,
+    );
+    execute!(
+        stdout,
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0),
+        style::PrintStyledContent(
+            format!("High scores of {} mazes\r\n\r\n", table)
+                .with(theme::ui().accent)
+                .attribute(Attribute::Bold)
+        ),
+    )?;
+
+    let goal_reached = *game_result == GameRunResult::GoalReached;
+    let summary = if goal_reached {
+        format!(
+            "You scored {}: {} steps for an optimal route of {} ({:.0}% efficient), {} backtracks, {}s left",
+            stats.score(),
+            stats.steps,
+            stats.optimal_steps,
+            stats.efficiency() * 100.0,
+            stats.backtracks,
+            stats.time_left.as_secs(),
+        )
+    } else {
+        format!(
+            "No score, the goal wasn't reached: {} steps, {} backtracks",
+            stats.steps, stats.backtracks,
+        )
+    };
+    stdout.execute(style::PrintStyledContent(
+        format!("{}\r\n\r\n", summary).with(theme::ui().info),
+    ))?;
+
+    // Keep a high score file that can't be read as it is, rather than overwriting it
+    let (high_scores, new_entry) = match HighScores::load() {
+        Ok(mut high_scores) => {
+            let new_entry = goal_reached
+                .then(|| high_scores.record(&table, HighScore::from(stats)))
+                .flatten();
+            if new_entry.is_some()
+                && let Err(e) = high_scores.save()
+            {
+                tracing::warn!("Failed to save the high scores: {}", e);
+                stdout.execute(style::PrintStyledContent(
+                    format!("Failed to save the high scores: {}\r\n\r\n", e)
+                        .with(theme::ui().error)
+                        .attribute(Attribute::Bold),
+                ))?;
+            }
+            (high_scores, new_entry)
+        }
+        Err(e) => {
+            tracing::warn!("Failed to load the high scores: {}", e);
+            stdout.execute(style::PrintStyledContent(
+                format!("Failed to load the high scores: {}\r\n\r\n", e)
+                    .with(theme::ui().error)
+                    .attribute(Attribute::Bold),
+            ))?;
+            (HighScores::default(), None)
+        }
+    };
+
+    let top = high_scores.top(&table);
+    if top.is_empty() {
+        stdout.execute(style::PrintStyledContent(
+            "No high scores yet, reach the goal to set one\r\n".with(theme::ui().info),
+        ))?;
+    }
+    for (index, entry) in top.iter().enumerate() {
+        let line = format!(
+            "{:>2}. {:>5}  {:>4} steps, optimal {:>4}  {:>3} backtracks  {:>4}s left\r\n",
+            index + 1,
+            entry.score,
+            entry.steps,
+            entry.optimal_steps,
+            entry.backtracks,
+            entry.time_left,
+        );
+        let line = line.with(theme::ui().info);
+        // Highlight the score of this run
+        let line = if new_entry == Some(index) {
+            line.attribute(Attribute::Reverse)
+        } else {
+            line
+        };
+        stdout.execute(style::PrintStyledContent(line))?;
+    }
+
+    stdout.execute(style::PrintStyledContent(
+        "\r\nPress Enter to play again, or Esc to exit.\r\n"
+            .with(theme::ui().accent)
+            .attribute(Attribute::Bold),
+    ))?;
+    Ok(())
+}
+
+/// Clear the screen and show the winner of a two-player race, if any, and the routes of the
+/// players
+fn show_race_results(
+    stdout: &mut Stdout,
+    winner: Option<usize>,
+    stats: &[RunStats],
+) -> std::io::Result<()> {
+    let title = match winner.map(|winner| (winner, &stats[winner])) {
+        Some((winner, stats)) => format!(
+            "Player {} wins the race: {} steps for an optimal route of {} ({:.0}% efficient), {} backtracks, {}s left",
+            winner + 1,
+            stats.steps,
+            stats.optimal_steps,
+            stats.efficiency() * 100.0,
+            stats.backtracks,
+            stats.time_left.as_secs(),
+        ),
+        None => "Nobody reached the goal".to_string(),
+    };
+    execute!(
+        stdout,
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0),
+        style::PrintStyledContent(
+            format!("{}\r\n\r\n", title)
+                .with(theme::ui().accent)
+                .attribute(Attribute::Bold)
+        ),
+    )?;
+    for (index, stats) in stats.iter().enumerate() {
+        let line = format!(
+            "Player {}: {} steps for an optimal route of {}, {} backtracks\r\n",
+            index + 1,
+            stats.steps,
+            stats.optimal_steps,
+            stats.backtracks,
+        );
+        let line = line.with(theme::ui().info);
+        // Highlight the winner
+        let line = if Some(index) == winner {
+            line.attribute(Attribute::Reverse)
+        } else {
+            line
+        };
+        stdout.execute(style::PrintStyledContent(line))?;
+    }
+    stdout.execute(style::PrintStyledContent(
+        "\r\nPress Enter to play again, or Esc to exit.\r\n"
+            .with(theme::ui().accent)
+            .attribute(Attribute::Bold),
+    ))?;
+    Ok(())
+}
+
+/// Threads of a game run besides the render and input threads
+struct RunThreads {
+    /// Finishes when the time is up, ending the run
+    timer: JoinHandle<std::io::Result<()>>,
+    /// Finishes when a ghost catches Pacman
+    ghosts: JoinHandle<std::io::Result<()>>,
+    /// Finishes when the opponent reaches the goal, if racing one
+    opponent: Option<JoinHandle<std::io::Result<()>>>,
+    /// Set once the opponent reaches the goal, as its thread also finishes when the render
+    /// thread is gone
+    opponent_arrived: Arc<AtomicBool>,
 }
 
 /// Main game loop, running in the main thread
 /// Polls for user input events and updates game state accordingly
-/// Exits when either the goal is reached, time runs out, or user cancels
+/// Exits when either the goal is reached, time runs out, a ghost catches Pacman, the opponent
+/// reaches the goal first, or user cancels.
+/// Catches and the opponent's arrival are read from shared state, since the ghosts and opponent
+/// threads also finish when the render thread is gone.
 fn game_loop(
     mut game_state: GameState,
+    context: Context,
+    ui_event_tx: Sender<UiEvent>,
     user_input_event_rx: Receiver<UserInputEvent>,
     input_recv_timeout: Duration,
     should_stop: &AtomicBool,
-    timer_thread_handle: &std::thread::JoinHandle<Result<(), std::io::Error>>,
-) -> std::io::Result<GameRunResult> {
+    threads: &RunThreads,
+) -> std::io::Result<(GameRunResult, Vec<RunStats>)> {
+    // Whether the key bindings are drawn over the maze
+    let mut showing_help = false;
+    // Whether the terminal is too small for the maze, pausing the game
+    let mut too_small = false;
+    // Whether the user paused the game
+    let mut paused = false;
+    // Hide the maze and show how to resume
+    let hide_maze = || {
+        ui_event_tx.send(UiEvent::HideMaze).ok();
+        let msg = format!(
+            "Paused, press {} to resume",
+            Keymap::current().keys(Action::Pause)
+        );
+        ui_event_tx
+            .send(UiEvent::LogMessage(Some(
+                msg.with(theme::ui().accent).attribute(Attribute::Bold),
+            )))
+            .ok();
+    };
     loop {
+        game_state.clear_expired_hint();
+
         // Check if render thread is finished
-        if timer_thread_handle.is_finished() {
+        if threads.timer.is_finished() {
             tracing::info!("[game loop] Timer thread finished, game result is Timeout");
             // Notify all threads to stop
             should_stop.store(true, std::sync::atomic::Ordering::Release);
-            return Ok(GameRunResult::Timeout);
+            return Ok((GameRunResult::Timeout, game_state.stats()));
+        }
+
+        // Check if a ghost caught Pacman
+        if game_state.caught() {
+            tracing::info!("[game loop] A ghost caught Pacman, game result is Caught");
+            should_stop.store(true, std::sync::atomic::Ordering::Release);
+            return Ok((GameRunResult::Caught, game_state.stats()));
         }
 
         // Check if goal is reached
-        if game_state.goal_reached() {
-            tracing::info!("[game loop] Goal reached!");
+        if let Some(winner) = game_state.winner() {
+            tracing::info!("[game loop] Goal reached by player {}!", winner + 1);
+            let result = if context == Context::TwoPlayer {
+                GameRunResult::Won(winner)
+            } else {
+                GameRunResult::GoalReached
+            };
+            let stats = game_state.stats();
             // Drop the game state's event sender first
             drop(game_state);
             // Notify all threads to stop
             should_stop.store(true, std::sync::atomic::Ordering::Release);
-            return Ok(GameRunResult::GoalReached);
+            return Ok((result, stats));
+        }
+
+        // Check if the opponent beat Pacman to the goal
+        if threads
+            .opponent_arrived
+            .load(std::sync::atomic::Ordering::Acquire)
+        {
+            tracing::info!("[game loop] Opponent reached the goal, game result is Outrun");
+            should_stop.store(true, std::sync::atomic::Ordering::Release);
+            return Ok((GameRunResult::Outrun, game_state.stats()));
         }
 
         // Poll user input event
@@ -331,77 +1052,161 @@ fn game_loop(
                         // Input thread has exited, set should_stop flag to true
                         // to tell other threads to stop
                         should_stop.store(true, std::sync::atomic::Ordering::Release);
-                        return Ok(GameRunResult::Canceled);
+                        return Ok((GameRunResult::Canceled, game_state.stats()));
                     }
                 }
             }
             Ok(event) => match event {
                 UserInputEvent::KeyPress(key_event) => {
-                    match key_event.code {
-                        KeyCode::Esc => {
-                            // Game should exit on Esc key
+                    match Keymap::current().action(&key_event, context) {
+                        Some(Action::Exit) => {
+                            // Game should exit on the exit key
                             should_stop.store(true, std::sync::atomic::Ordering::Release);
                             // Set the game as canceled
-                            return Ok(GameRunResult::Canceled);
+                            return Ok((GameRunResult::Canceled, game_state.stats()));
                         }
-                        KeyCode::Left => {
-                            game_state.move_pacman(Direction::Left);
+                        // Wait for the terminal to be resized
+                        _ if too_small => {}
+                        // Any other key closes the key bindings, then the maze is drawn again
+                        _ if showing_help => {
+                            showing_help = false;
+                            if !paused {
+                                game_state.resume();
+                            }
+                            ui_event_tx.send(UiEvent::HideHelp).ok();
+                            game_state.redraw();
                         }
-                        KeyCode::Right => {
-                            game_state.move_pacman(Direction::Right);
+                        Some(Action::Pause) => {
+                            paused = !paused;
+                            if paused {
+                                tracing::info!("[game loop] Game paused");
+                                game_state.pause();
+                                hide_maze();
+                            } else {
+                                tracing::info!("[game loop] Game resumed");
+                                game_state.resume();
+                                ui_event_tx.send(UiEvent::ShowMaze).ok();
+                                game_state.redraw();
+                            }
                         }
-                        KeyCode::Up => {
-                            game_state.move_pacman(Direction::Up);
+                        // Pacman can't move while paused
+                        _ if paused => {}
+                        // No hints in two-player races
+                        Some(Action::Hint) if context == Context::Game => game_state.show_hint(),
+                        // The run is paused while the key bindings hide the maze
+                        Some(Action::Help) => {
+                            showing_help = true;
+                            game_state.pause();
+                            ui_event_tx.send(UiEvent::ShowHelp(context)).ok();
                         }
-                        KeyCode::Down => {
-                            game_state.move_pacman(Direction::Down);
+                        Some(action) => {
+                            let (player, direction) = match action {
+                                Action::MoveUp => (0, Direction::Up),
+                                Action::MoveDown => (0, Direction::Down),
+                                Action::MoveLeft => (0, Direction::Left),
+                                Action::MoveRight => (0, Direction::Right),
+                                Action::Player2Up => (1, Direction::Up),
+                                Action::Player2Down => (1, Direction::Down),
+                                Action::Player2Left => (1, Direction::Left),
+                                Action::Player2Right => (1, Direction::Right),
+                                _ => continue,
+                            };
+                            game_state.move_player(player, direction);
                         }
-                        _ => {}
+                        None => {}
                     };
                 }
-                UserInputEvent::Resize => todo!("Check resize"),
+                UserInputEvent::Resize => {
+                    let grid = game_state.grid();
+                    let required = (
+                        grid.width() * GridCell::CELL_WIDTH,
+                        grid.height() + app::NUM_STATUS_ROWS,
+                    );
+                    let (columns, rows) = terminal::size()?;
+                    if columns < required.0 || rows < required.1 {
+                        if !too_small {
+                            tracing::info!("[game loop] Terminal too small, pausing the game");
+                            too_small = true;
+                            game_state.pause();
+                        }
+                        // Draw the message again for the new terminal size
+                        ui_event_tx
+                            .send(UiEvent::TerminalTooSmall { required })
+                            .ok();
+                    } else {
+                        if too_small {
+                            tracing::info!("[game loop] Terminal size restored");
+                            too_small = false;
+                            if !paused && !showing_help {
+                                game_state.resume();
+                            }
+                        }
+                        // Draw everything again, as resizing may have garbled the screen
+                        ui_event_tx.send(UiEvent::TerminalFits).ok();
+                        if showing_help {
+                            ui_event_tx.send(UiEvent::ShowHelp(context)).ok();
+                        } else if paused {
+                            hide_maze();
+                        } else {
+                            game_state.redraw();
+                        }
+                    }
+                }
             },
         }
     }
 }
 
-/// Start the game timer, logging remaining time to terminal every tick
+/// Start the game timer, logging remaining time to terminal every tick unless paused
 /// This function will be run in a separate thread
 /// # Arguments
-/// * `start_time`: The instant when the game started
+/// * `clock`: The time the game has been going on, not counting pauses
+/// * `pellets_left`: The number of pellets left to collect, logged after the remaining time
 /// * `game_run_duration`: The total duration of the game run
 /// * `tick_duration`: The duration between each tick to log remaining time
 /// * `grid_height`: The height of the maze grid, used to position the log correctly
 /// * `should_stop`: Flag to check for exiting early
 fn start_timer(
-    start_time: Instant,
+    clock: &Mutex<RunClock>,
+    pellets_left: &AtomicUsize,
     game_run_duration: Duration,
     tick_duration: Duration,
     should_stop: &AtomicBool,
     ui_event_tx: Sender<UiEvent>,
 ) -> std::io::Result<()> {
-    // Get a new stdout handle
-    while start_time.elapsed() < game_run_duration {
+    let elapsed = || clock.lock().expect("Clock lock poisoned").elapsed();
+    let is_paused = || clock.lock().expect("Clock lock poisoned").is_paused();
+    while elapsed() < game_run_duration {
         // Check if the timer should stop early
         if should_stop.load(std::sync::atomic::Ordering::Acquire) {
             return Ok(());
         }
+        // Leave the log to the pause message
+        if is_paused() {
+            std::thread::sleep(tick_duration);
+            continue;
+        }
         // Clear previous log
         if ui_event_tx.send(UiEvent::LogMessage(None)).is_err() {
             // Receiver has been dropped, exit the thread
             return Ok(());
         }
 
-        let remaining_time = GAME_RUN_DURATION - start_time.elapsed();
-        let msg = format!("Time remain: {}", remaining_time.as_secs())
+        let remaining_time = game_run_duration.saturating_sub(elapsed());
+        let mut msg = format!("Time remain: {}", remaining_time.as_secs());
+        match pellets_left.load(std::sync::atomic::Ordering::Relaxed) {
+            0 => {}
+            pellets => msg.push_str(&format!("  Pellets left: {}", pellets)),
+        }
+        let msg = msg
             .with({
                 // Set message color based on remaining time
-                if remaining_time <= GAME_RUN_DURATION / 4 {
-                    Color::Red
-                } else if remaining_time <= GAME_RUN_DURATION / 2 {
-                    Color::Yellow
+                if remaining_time <= game_run_duration / 4 {
+                    theme::ui().error
+                } else if remaining_time <= game_run_duration / 2 {
+                    theme::ui().accent
                 } else {
-                    Color::Green
+                    theme::ui().success
                 }
             })
             .attribute(Attribute::Bold);
@@ -421,6 +1226,7 @@ fn start_timer(
 /// This function runs in a separate thread, and is the only place where user input is read
 fn listen_to_user_input(
     user_input_event_tx: Sender<UserInputEvent>,
+    context: Context,
     event_poll_timeout: Duration,
     should_stop: &AtomicBool,
 ) -> std::io::Result<()> {
@@ -446,13 +1252,11 @@ fn listen_to_user_input(
             _ => continue, // Ignore other events
         };
 
-        // Should exit input thread on Esc key
+        // Should exit input thread on the exit key
         let should_exit = matches!(
-            input_event,
-            UserInputEvent::KeyPress(event::KeyEvent {
-                code: KeyCode::Esc,
-                ..
-            })
+            &input_event,
+            UserInputEvent::KeyPress(key_event)
+                if Keymap::current().action(key_event, context) == Some(Action::Exit)
         );
 
         // Send the input event to the main thread
@@ -462,75 +1266,56 @@ fn listen_to_user_input(
         }
 
         if should_exit {
-            tracing::debug!("[input loop] Esc key pressed, exiting");
+            tracing::debug!("[input loop] Exit key pressed, exiting");
             return Ok(());
         }
     }
 }
 
-pub fn run(stdout: &mut Stdout) -> std::io::Result<()> {
+/// Entry point of the game, with the settings menu starting from the last used settings, or the
+/// `config` defaults
+pub fn run(stdout: &mut Stdout, config: &Config) -> std::io::Result<()> {
     execute!(
         stdout,
         style::SetAttribute(Attribute::Reverse),
-        style::PrintStyledContent("Game Mode\r\n".with(Color::Yellow)),
+        style::PrintStyledContent("Game Mode\r\n".with(theme::ui().accent)),
         style::SetAttribute(Attribute::NoReverse),
     )?;
 
-    // Ask user for maze dimensions
-    let (width, height) = match app::ask_maze_dimensions(stdout)? {
-        Some(dims) => dims,
-        None => {
-            return Ok(());
-        }
-    };
+    // Let the user edit the last used settings, and keep them for the next launch
+    let mut settings = GameSettings::load(config);
+    if !game_settings::customize_settings(stdout, &mut settings)? {
+        return Ok(());
+    }
+    game_settings::keep_settings(stdout, &settings)?;
 
-    // Ask user for maze generation algorithm
-    let generator = match app::select_from_menu(
-        stdout,
-        "Select maze generation algorithm (use arrow keys and Enter, or Esc to exit):",
-        &app::GENERATORS,
-    )? {
-        Some(generator) => {
-            stdout.execute(style::PrintStyledContent(
-                format!("Selected generator: {}\r\n", generator)
-                    .with(Color::Green)
-                    .attribute(Attribute::Bold),
-            ))?;
-            generator
-        }
-        None => {
-            return Ok(());
-        }
+    let intro = if settings.two_players {
+        "Race each other through the maze to its destination before time's over!\r\n"
+    } else if settings.ghost_count() > 0 {
+        "Move your Pacman through the maze to its destination before time's over, and keep away from the ghosts!\r\n"
+    } else {
+        "Move your Pacman through the maze to its destination before time's over!\r\n"
     };
-
     execute!(
-            stdout,
-            style::PrintStyledContent(
-                "Move your Pacman through the maze using arrow keys to its destination before time's over!\r\n"
-                    .with(Color::Yellow)
-                    .attribute(Attribute::Bold)
-            ),
-            style::PrintStyledContent(
-                "Controls:\r\n"
-                    .with(Color::Yellow)
-                    .attribute(Attribute::Bold)
-            ),
-            style::PrintStyledContent(
-                "  ←/→/↑/↓: Step up/down/left/right to control Pacman\r\n".with(Color::Cyan)
-            ),
-            style::PrintStyledContent("  Esc: Exit game\r\n\r\n".with(Color::Cyan)),
-            cursor::Hide,
-        )?;
-
-    tracing::info!(
-        "[game] Starting game with maze size {}x{} and generator {:?}",
-        width,
-        height,
-        generator
-    );
+        stdout,
+        style::PrintStyledContent(intro.with(theme::ui().accent).attribute(Attribute::Bold)),
+        style::PrintStyledContent(
+            "Controls:\r\n"
+                .with(theme::ui().accent)
+                .attribute(Attribute::Bold)
+        ),
+    )?;
+    for line in Keymap::current().help(key_context(&settings)) {
+        stdout.execute(style::PrintStyledContent(
+            format!("  {}\r\n", line).with(theme::ui().info),
+        ))?;
+    }
+    execute!(stdout, style::Print("\r\n"), cursor::Hide)?;
+
+    tracing::info!("[game] Starting game with settings {:?}", settings);
 
     loop {
-        let game_result = start_game(stdout, width, height, generator)?;
+        let game_result = start_game(stdout, &settings)?;
         if game_result == GameRunResult::Canceled {
             break;
         }
@@ -0,0 +1,95 @@
+use std::{
+    sync::{Mutex, atomic::AtomicBool, mpsc::Sender},
+    time::Duration,
+};
+
+use crate::{
+    app::{clock::RunClock, game::UiEvent},
+    maze::Maze,
+    solvers::{Solver, explore_route, find_route},
+};
+
+/// Cells the opponent explores per step when showing its search
+const EXPLORED_PER_STEP: usize = 4;
+
+/// Computer-controlled runner racing Pacman to the goal along the route of a solver
+pub struct Opponent {
+    /// Cells the solver explored, in order, shown before running if the search is shown
+    explored: Vec<(u8, u8)>,
+    /// Route from the start to the goal
+    route: Vec<(u8, u8)>,
+}
+
+impl Opponent {
+    /// Plan the run of the opponent from `start` to `goal` on a copy of the maze, keeping the
+    /// cells the solver explores on the way if `explores` is set.
+    /// Returns None if the goal can't be reached.
+    pub fn new(
+        maze: &Maze,
+        solver: Solver,
+        start: (u8, u8),
+        goal: (u8, u8),
+        explores: bool,
+    ) -> Option<Self> {
+        let (explored, route) = if explores {
+            let exploration = explore_route(maze, solver, start, goal)?;
+            (exploration.explored, exploration.route)
+        } else {
+            (Vec::new(), find_route(maze, solver, start, goal)?)
+        };
+        Some(Opponent { explored, route })
+    }
+}
+
+/// Grid coordinates of a maze cell
+fn grid_coord((x, y): (u8, u8)) -> (u16, u16) {
+    (x as u16 * 2 + 1, y as u16 * 2 + 1)
+}
+
+/// Show the cells the opponent explores, then move it along its route every `step_duration`,
+/// sending its steps to the render thread. The opponent doesn't move while the clock is paused.
+/// This function will be run in a separate thread, and returns once the opponent reaches the goal,
+/// setting `arrived`, or `should_stop` is set.
+pub fn move_opponent(
+    opponent: Opponent,
+    step_duration: Duration,
+    clock: &Mutex<RunClock>,
+    should_stop: &AtomicBool,
+    arrived: &AtomicBool,
+    ui_event_tx: Sender<UiEvent>,
+) -> std::io::Result<()> {
+    // Wait for the next step, skipping the steps while paused.
+    // Returns false if the thread should stop.
+    let next_step = || loop {
+        std::thread::sleep(step_duration);
+        if should_stop.load(std::sync::atomic::Ordering::Acquire) {
+            return false;
+        }
+        if !clock.lock().expect("Clock lock poisoned").is_paused() {
+            return true;
+        }
+    };
+
+    let start = grid_coord(opponent.route[0]);
+    if ui_event_tx.send(UiEvent::Opponent(start)).is_err() {
+        return Ok(());
+    }
+    for cells in opponent.explored.chunks(EXPLORED_PER_STEP) {
+        let coords = cells.iter().copied().map(grid_coord).collect();
+        if !next_step() || ui_event_tx.send(UiEvent::OpponentExplored(coords)).is_err() {
+            return Ok(());
+        }
+    }
+    for &cell in &opponent.route[1..] {
+        if !next_step()
+            || ui_event_tx
+                .send(UiEvent::Opponent(grid_coord(cell)))
+                .is_err()
+        {
+            return Ok(());
+        }
+    }
+    tracing::info!("[opponent] Reached the goal");
+    arrived.store(true, std::sync::atomic::Ordering::Release);
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config, generators::Generator};
+
+/// Name of the high score file, in the data directory
+const HIGH_SCORES_FILE: &str = "high_scores.toml";
+/// Number of high scores kept per maze size and generator
+pub const MAX_HIGH_SCORES: usize = 10;
+/// Points for a route as short as the optimal one, scaled down by the route efficiency
+const ROUTE_POINTS: f64 = 1000.0;
+/// Points per second left on the timer
+const POINTS_PER_SECOND_LEFT: u32 = 20;
+/// Points lost per step back onto an already visited cell
+const POINTS_PER_BACKTRACK: u32 = 10;
+
+/// Statistics of a game run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunStats {
+    /// Number of steps Pacman took
+    pub steps: usize,
+    /// Number of steps back onto an already visited cell
+    pub backtracks: usize,
+    /// Number of steps of the shortest route from the start to the goal
+    pub optimal_steps: usize,
+    /// Time left on the timer when the run ended
+    pub time_left: Duration,
+}
+
+impl RunStats {
+    /// Ratio of the optimal route length to the length of Pacman's route, 1 for a perfect run
+    pub fn efficiency(&self) -> f64 {
+        if self.steps == 0 {
+            return 1.0;
+        }
+        self.optimal_steps as f64 / self.steps as f64
+    }
+
+    /// Score of a run that reached the goal, rewarding short routes and time left, and
+    /// penalizing backtracking
+    pub fn score(&self) -> u32 {
+        let route = (ROUTE_POINTS * self.efficiency()).round() as u32;
+        let time = self.time_left.as_secs() as u32 * POINTS_PER_SECOND_LEFT;
+        (route + time).saturating_sub(self.backtracks as u32 * POINTS_PER_BACKTRACK)
+    }
+}
+
+/// Entry of the high score table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HighScore {
+    pub score: u32,
+    pub steps: usize,
+    pub optimal_steps: usize,
+    pub backtracks: usize,
+    /// Seconds left on the timer
+    pub time_left: u64,
+}
+
+impl From<&RunStats> for HighScore {
+    fn from(stats: &RunStats) -> Self {
+        HighScore {
+            score: stats.score(),
+            steps: stats.steps,
+            optimal_steps: stats.optimal_steps,
+            backtracks: stats.backtracks,
+            time_left: stats.time_left.as_secs(),
+        }
+    }
+}
+
+/// Best scores per maze size and generator, kept in the data directory
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighScores {
+    /// Best scores first, by table name such as `20x12 Prim's Algorithm`
+    tables: BTreeMap<String, Vec<HighScore>>,
+}
+
+impl HighScores {
+    /// Name of the table of the scores of a maze size and generator. Pellets and doors force
+    /// detours off the shortest route the efficiency is measured against, so their runs get
+    /// tables of their own.
+    pub fn table_name(
+        width: u8,
+        height: u8,
+        generator: Generator,
+        pellets: bool,
+        doors: bool,
+    ) -> String {
+        let extras = match (pellets, doors) {
+            (false, false) => "",
+            (true, false) => " with pellets",
+            (false, true) => " with keys and doors",
+            (true, true) => " with pellets, keys and doors",
+        };
+        format!("{}x{} {}{}", width, height, generator, extras)
+    }
+
+    fn path() -> std::io::Result<PathBuf> {
+        config::data_dir()
+            .map(|dir| dir.join(HIGH_SCORES_FILE))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "No data directory, set HOME or XDG_DATA_HOME",
+                )
+            })
+    }
+
+    /// Load the high scores, or an empty table if there are none yet
+    pub fn load() -> std::io::Result<Self> {
+        let path = match Self::path() {
+            Ok(path) if path.exists() => path,
+            _ => return Ok(HighScores::default()),
+        };
+        let contents = std::fs::read_to_string(&path)?;
+        toml::from_str(&contents).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid high score file {}: {}",
+                    path.display(),
+                    e.message()
+                ),
+            )
+        })
+    }
+
+    /// Write the high scores, returning the path of the high score file
+    pub fn save(&self) -> std::io::Result<PathBuf> {
+        let path = Self::path()?;
+        let contents = toml::to_string(self).map_err(std::io::Error::other)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, contents)?;
+        tracing::info!("Saved high scores to {}", path.display());
+        Ok(path)
+    }
+
+    /// Best scores of the given table, best first
+    pub fn top(&self, table: &str) -> &[HighScore] {
+        self.tables.get(table).map_or(&[], Vec::as_slice)
+    }
+
+    /// Add a score to the given table, keeping the best [`MAX_HIGH_SCORES`].
+    /// Returns its index in the table, or None if it didn't make it.
+    pub fn record(&mut self, table: &str, score: HighScore) -> Option<usize> {
+        let scores = self.tables.entry(table.to_string()).or_default();
+        // Ties go to the earlier score
+        let index = scores.partition_point(|other| other.score >= score.score);
+        if index >= MAX_HIGH_SCORES {
+            return None;
+        }
+        scores.insert(index, score);
+        scores.truncate(MAX_HIGH_SCORES);
+        Some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_rank_and_round_trip() {
+        let stats = RunStats {
+            steps: 20,
+            backtracks: 3,
+            optimal_steps: 15,
+            time_left: Duration::from_secs(10),
+        };
+        assert_eq!(stats.efficiency(), 0.75);
+        assert_eq!(stats.score(), 750 + 200 - 30);
+
+        let table = HighScores::table_name(20, 12, Generator::Prim, false, false);
+        assert_eq!(table, "20x12 Prim's Algorithm");
+        assert_eq!(
+            HighScores::table_name(20, 12, Generator::Prim, true, true),
+            "20x12 Prim's Algorithm with pellets, keys and doors"
+        );
+        let mut high_scores = HighScores::default();
+        let entry = |score| HighScore {
+            score,
+            ..HighScore::from(&stats)
+        };
+        for score in 1..=MAX_HIGH_SCORES as u32 {
+            assert_eq!(high_scores.record(&table, entry(score * 10)), Some(0));
+        }
+        assert_eq!(high_scores.record(&table, entry(5)), None);
+        assert_eq!(high_scores.record(&table, entry(55)), Some(5));
+        assert_eq!(high_scores.top(&table).len(), MAX_HIGH_SCORES);
+        assert_eq!(high_scores.top(&table)[MAX_HIGH_SCORES - 1].score, 20);
+        assert!(high_scores.top("10x8 Prim's Algorithm").is_empty());
+
+        let contents = toml::to_string(&high_scores).unwrap();
+        assert_eq!(
+            toml::from_str::<HighScores>(&contents).unwrap(),
+            high_scores
+        );
+    }
+}
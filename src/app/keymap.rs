@@ -0,0 +1,674 @@
+//! Key bindings of the visualizer and the game, remappable in the `[keys]` section of the config file
+
+use std::{collections::BTreeMap, fmt, io::Write, sync::OnceLock};
+
+use crossterm::{
+    cursor,
+    event::{KeyCode, KeyEvent, KeyModifiers},
+    queue,
+    style::{self, Attribute, Stylize},
+    terminal,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+use unicode_truncate::UnicodeTruncateStr;
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::theme;
+
+/// Keymap used by the whole app, installed once at startup
+static KEYMAP: OnceLock<Keymap> = OnceLock::new();
+
+/// Part of the app where key bindings apply. A key can be bound to one action per context.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Context {
+    Visualize,
+    Game,
+    /// Two-player game, where the keys of the second player take precedence over the game keys
+    TwoPlayer,
+}
+
+/// Action triggered by a key, named in snake case in the config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Pause or resume the animation
+    Pause,
+    StepBackward,
+    StepForward,
+    JumpBackward,
+    JumpForward,
+    PhaseStart,
+    PhaseEnd,
+    /// Seek to 0%, 10%, ..., 90% of the run
+    Seek0,
+    Seek10,
+    Seek20,
+    Seek30,
+    Seek40,
+    Seek50,
+    Seek60,
+    Seek70,
+    Seek80,
+    Seek90,
+    SpeedUp,
+    SlowDown,
+    Heatmap,
+    VisitOrder,
+    /// Pan the viewport by one maze cell
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    /// Pan the viewport by half the visible region
+    PanPageUp,
+    PanPageDown,
+    PanPageLeft,
+    PanPageRight,
+    Follow,
+    /// Move Pacman in the game
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    /// Move the second player in a two-player game
+    Player2Up,
+    Player2Down,
+    Player2Left,
+    Player2Right,
+    /// Flash the next steps of the route to the goal in the game
+    Hint,
+    /// Show the key bindings
+    Help,
+    Exit,
+}
+
+impl Action {
+    const ALL: [Action; 41] = [
+        Action::Pause,
+        Action::StepBackward,
+        Action::StepForward,
+        Action::JumpBackward,
+        Action::JumpForward,
+        Action::PhaseStart,
+        Action::PhaseEnd,
+        Action::Seek0,
+        Action::Seek10,
+        Action::Seek20,
+        Action::Seek30,
+        Action::Seek40,
+        Action::Seek50,
+        Action::Seek60,
+        Action::Seek70,
+        Action::Seek80,
+        Action::Seek90,
+        Action::SpeedUp,
+        Action::SlowDown,
+        Action::Heatmap,
+        Action::VisitOrder,
+        Action::PanUp,
+        Action::PanDown,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::PanPageUp,
+        Action::PanPageDown,
+        Action::PanPageLeft,
+        Action::PanPageRight,
+        Action::Follow,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Player2Up,
+        Action::Player2Down,
+        Action::Player2Left,
+        Action::Player2Right,
+        Action::Hint,
+        Action::Help,
+        Action::Exit,
+    ];
+
+    /// Actions seeking to 0%, 10%, ..., 90% of the run, in order
+    pub const SEEK: [Action; 10] = [
+        Action::Seek0,
+        Action::Seek10,
+        Action::Seek20,
+        Action::Seek30,
+        Action::Seek40,
+        Action::Seek50,
+        Action::Seek60,
+        Action::Seek70,
+        Action::Seek80,
+        Action::Seek90,
+    ];
+
+    /// Whether the action is available in the given context
+    fn applies_to(&self, context: Context) -> bool {
+        match self {
+            Action::MoveUp
+            | Action::MoveDown
+            | Action::MoveLeft
+            | Action::MoveRight
+            | Action::Hint => context == Context::Game,
+            Action::Player2Up
+            | Action::Player2Down
+            | Action::Player2Left
+            | Action::Player2Right => context == Context::TwoPlayer,
+            Action::Pause | Action::Help | Action::Exit => true,
+            _ => context == Context::Visualize,
+        }
+    }
+
+    /// Keys bound to the action unless the config file overrides them
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Pause => &["Enter"],
+            Action::StepBackward => &["Left", "h"],
+            Action::StepForward => &["Right", "l"],
+            Action::JumpBackward => &["PageUp"],
+            Action::JumpForward => &["PageDown"],
+            Action::PhaseStart => &["Home"],
+            Action::PhaseEnd => &["End"],
+            Action::Seek0 => &["0"],
+            Action::Seek10 => &["1"],
+            Action::Seek20 => &["2"],
+            Action::Seek30 => &["3"],
+            Action::Seek40 => &["4"],
+            Action::Seek50 => &["5"],
+            Action::Seek60 => &["6"],
+            Action::Seek70 => &["7"],
+            Action::Seek80 => &["8"],
+            Action::Seek90 => &["9"],
+            Action::SpeedUp => &["Up", "k"],
+            Action::SlowDown => &["Down", "j"],
+            Action::Heatmap => &["m"],
+            Action::VisitOrder => &["o"],
+            Action::PanUp => &["w"],
+            Action::PanDown => &["s"],
+            Action::PanLeft => &["a"],
+            Action::PanRight => &["d"],
+            Action::PanPageUp => &["W", "Shift+Up"],
+            Action::PanPageDown => &["S", "Shift+Down"],
+            Action::PanPageLeft => &["A", "Shift+Left"],
+            Action::PanPageRight => &["D", "Shift+Right"],
+            Action::Follow => &["f"],
+            Action::MoveUp => &["Up", "k", "w"],
+            Action::MoveDown => &["Down", "j", "s"],
+            Action::MoveLeft => &["Left", "h", "a"],
+            Action::MoveRight => &["Right", "l", "d"],
+            Action::Player2Up => &["w"],
+            Action::Player2Down => &["s"],
+            Action::Player2Left => &["a"],
+            Action::Player2Right => &["d"],
+            Action::Hint => &["Space"],
+            Action::Help => &["?"],
+            Action::Exit => &["Esc"],
+        }
+    }
+}
+
+/// Help entries of each context, as the actions sharing an entry and their description
+const VISUALIZE_HELP: [(&[Action], &str); 13] = [
+    (&[Action::Pause], "Pause/Resume animation"),
+    (
+        &[Action::StepBackward, Action::StepForward],
+        "Step backward/forward when paused",
+    ),
+    (
+        &[Action::JumpBackward, Action::JumpForward],
+        "Jump backward/forward when paused",
+    ),
+    (
+        &[Action::PhaseStart, Action::PhaseEnd],
+        "Jump to start/end of generation or solving when paused",
+    ),
+    (&Action::SEEK, "Seek to 0%-90% of the run when paused"),
+    (
+        &[Action::SpeedUp, Action::SlowDown],
+        "Speed up/slow down animation",
+    ),
+    (&[Action::Heatmap], "Toggle distance-from-start heatmap"),
+    (&[Action::VisitOrder], "Toggle visit order coloring"),
+    (
+        &[
+            Action::PanUp,
+            Action::PanLeft,
+            Action::PanDown,
+            Action::PanRight,
+        ],
+        "Pan mazes larger than the terminal",
+    ),
+    (
+        &[
+            Action::PanPageUp,
+            Action::PanPageLeft,
+            Action::PanPageDown,
+            Action::PanPageRight,
+        ],
+        "Pan by half a screen",
+    ),
+    (
+        &[Action::Follow],
+        "Toggle following the latest update when panning",
+    ),
+    (&[Action::Help], "Show key bindings"),
+    (&[Action::Exit], "Exit"),
+];
+const GAME_HELP: [(&[Action], &str); 5] = [
+    (
+        &[
+            Action::MoveUp,
+            Action::MoveDown,
+            Action::MoveLeft,
+            Action::MoveRight,
+        ],
+        "Step up/down/left/right to control Pacman",
+    ),
+    (&[Action::Pause], "Pause/resume the game, hiding the maze"),
+    (
+        &[Action::Hint],
+        "Flash the next steps toward the goal, taking time off the timer",
+    ),
+    (&[Action::Help], "Show key bindings"),
+    (&[Action::Exit], "Exit game"),
+];
+const TWO_PLAYER_HELP: [(&[Action], &str); 5] = [
+    (
+        &[
+            Action::MoveUp,
+            Action::MoveDown,
+            Action::MoveLeft,
+            Action::MoveRight,
+        ],
+        "Step player 1 (Pacman) up/down/left/right",
+    ),
+    (
+        &[
+            Action::Player2Up,
+            Action::Player2Down,
+            Action::Player2Left,
+            Action::Player2Right,
+        ],
+        "Step player 2 up/down/left/right",
+    ),
+    (&[Action::Pause], "Pause/resume the game, hiding the maze"),
+    (&[Action::Help], "Show key bindings"),
+    (&[Action::Exit], "Exit game"),
+];
+
+/// Key combination, written like `k`, `PageUp` or `Ctrl+Left` in the config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Whether the key event is this key combination. Characters already tell whether Shift is
+    /// held, e.g. `W` and `?`, so Shift only matters for other keys.
+    fn matches(&self, event: &KeyEvent) -> bool {
+        let ignored = match self.code {
+            KeyCode::Char(_) => KeyModifiers::SHIFT,
+            _ => KeyModifiers::NONE,
+        };
+        event.code == self.code
+            && event.modifiers.difference(ignored) == self.modifiers.difference(ignored)
+    }
+}
+
+/// Names of the keys other than characters
+const KEY_NAMES: [(KeyCode, &str); 13] = [
+    (KeyCode::Enter, "Enter"),
+    (KeyCode::Esc, "Esc"),
+    (KeyCode::Char(' '), "Space"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Backspace, "Backspace"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+];
+/// Names of the modifiers, in the order they are written
+const MODIFIER_NAMES: [(KeyModifiers, &str); 3] = [
+    (KeyModifiers::CONTROL, "Ctrl"),
+    (KeyModifiers::ALT, "Alt"),
+    (KeyModifiers::SHIFT, "Shift"),
+];
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in MODIFIER_NAMES {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match KEY_NAMES.iter().find(|(code, _)| *code == self.code) {
+            Some((_, name)) => write!(f, "{}", name),
+            None => match self.code {
+                KeyCode::Char(c) => write!(f, "{}", c),
+                code => write!(f, "{}", code),
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for KeyBinding {
+    type Err = String;
+
+    /// Parse a key combination from modifiers followed by a key name or character, joined by `+`.
+    /// Names ignore case, characters don't.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = s;
+        // The key itself may be `+`, so only split before a non-empty rest
+        while let Some((prefix, rest)) = key.split_once('+')
+            && !rest.is_empty()
+        {
+            let Some((modifier, _)) = MODIFIER_NAMES
+                .iter()
+                .find(|(_, name)| name.eq_ignore_ascii_case(prefix))
+            else {
+                return Err(format!(
+                    "Unknown modifier '{}' in key '{}', expected Ctrl, Alt or Shift",
+                    prefix, s
+                ));
+            };
+            modifiers |= *modifier;
+            key = rest;
+        }
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => {
+                let named = KEY_NAMES
+                    .iter()
+                    .find(|(_, name)| name.eq_ignore_ascii_case(key))
+                    .map(|(code, _)| *code);
+                let function = key
+                    .strip_prefix(['F', 'f'])
+                    .and_then(|n| n.parse::<u8>().ok())
+                    .filter(|n| (1..=12).contains(n))
+                    .map(KeyCode::F);
+                named.or(function).ok_or_else(|| {
+                    format!(
+                        "Unknown key '{}', expected a character or one of: {}, F1-F12",
+                        key,
+                        KEY_NAMES.map(|(_, name)| name).join(", ")
+                    )
+                })?
+            }
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl Serialize for KeyBinding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Keys bound to each action
+#[derive(Debug)]
+pub(super) struct Keymap {
+    bindings: Vec<(Action, Vec<KeyBinding>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new())
+    }
+}
+
+impl Keymap {
+    /// Default key bindings, with the keys of the actions in `overrides` replaced
+    pub(super) fn new(overrides: &BTreeMap<Action, Vec<KeyBinding>>) -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .map(|&action| {
+                let keys = match overrides.get(&action) {
+                    Some(keys) => keys.clone(),
+                    None => action
+                        .default_keys()
+                        .iter()
+                        .map(|key| key.parse().expect("Default keys are valid"))
+                        .collect(),
+                };
+                (action, keys)
+            })
+            .collect();
+        let keymap = Self { bindings };
+        keymap.warn_conflicts();
+        keymap
+    }
+
+    /// Log keys bound to several actions of a context, of which only the first one is triggered
+    fn warn_conflicts(&self) {
+        for (i, (action, keys)) in self.bindings.iter().enumerate() {
+            for (other, other_keys) in &self.bindings[i + 1..] {
+                let shared = [Context::Visualize, Context::Game, Context::TwoPlayer]
+                    .iter()
+                    .any(|&context| action.applies_to(context) && other.applies_to(context));
+                if let Some(key) = keys.iter().find(|key| other_keys.contains(key))
+                    && shared
+                {
+                    tracing::warn!(
+                        "Key {} is bound to both {:?} and {:?}, {:?} takes precedence",
+                        key,
+                        action,
+                        other,
+                        action
+                    );
+                }
+            }
+        }
+    }
+
+    /// Install the keymap for the whole app
+    pub(super) fn install(self) {
+        if KEYMAP.set(self).is_err() {
+            tracing::warn!("A keymap is already installed, ignoring the new one");
+        }
+    }
+
+    /// Keymap in use, the default keymap if none was installed
+    pub(super) fn current() -> &'static Keymap {
+        KEYMAP.get_or_init(Keymap::default)
+    }
+
+    /// Action bound to the key event in the given context, if any
+    pub(super) fn action(&self, event: &KeyEvent, context: Context) -> Option<Action> {
+        let bound = |context| {
+            self.bindings
+                .iter()
+                .find(|(action, keys)| {
+                    action.applies_to(context) && keys.iter().any(|key| key.matches(event))
+                })
+                .map(|(action, _)| *action)
+        };
+        match context {
+            Context::TwoPlayer => bound(Context::TwoPlayer).or_else(|| bound(Context::Game)),
+            _ => bound(context),
+        }
+    }
+
+    /// Keys bound to the action, joined by `/`
+    pub(super) fn keys(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, keys)| {
+                keys.iter()
+                    .map(|key| key.to_string())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .unwrap_or_default()
+    }
+
+    /// Keys triggering the action in the given context, joined by `/`. Keys taken by another
+    /// action of the context are left out.
+    fn keys_in(&self, action: Action, context: Context) -> String {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, keys)| {
+                keys.iter()
+                    .filter(|key| {
+                        let event = KeyEvent::new(key.code, key.modifiers);
+                        self.action(&event, context) == Some(action)
+                    })
+                    .map(|key| key.to_string())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .unwrap_or_default()
+    }
+
+    /// Lines of help for the given context, listing the keys of each entry with its description
+    pub(super) fn help(&self, context: Context) -> Vec<String> {
+        let entries: &[(&[Action], &str)] = match context {
+            Context::Visualize => &VISUALIZE_HELP,
+            Context::Game => &GAME_HELP,
+            Context::TwoPlayer => &TWO_PLAYER_HELP,
+        };
+        entries
+            .iter()
+            .map(|(actions, description)| {
+                let keys = actions
+                    .iter()
+                    .map(|&action| self.keys_in(action, context))
+                    .filter(|keys| !keys.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let keys = if keys.is_empty() { "unbound" } else { &keys };
+                format!("{}: {}", keys, description)
+            })
+            .collect()
+    }
+}
+
+/// Draw the key bindings of the given context in a box at the center of the terminal, over
+/// whatever is displayed. Lines that don't fit the terminal are cut off.
+pub(super) fn draw_help(stdout: &mut impl Write, context: Context) -> std::io::Result<()> {
+    let (term_width, term_height) = terminal::size()?;
+    let title = "Key bindings (press any key to close)";
+    let lines = Keymap::current().help(context);
+    let content_width = lines
+        .iter()
+        .map(|line| line.width())
+        .chain([title.width()])
+        .max()
+        .unwrap_or(0);
+    // One column of padding on each side, and an empty row below the lines
+    let width = (content_width + 2).min(term_width as usize);
+    let height = (lines.len() + 2).min(term_height as usize);
+    let (left, top) = (
+        (term_width as usize - width) / 2,
+        (term_height as usize - height) / 2,
+    );
+
+    let pad = |text: &str| {
+        let (text, text_width) = text.unicode_truncate(width.saturating_sub(2));
+        format!(
+            " {}{}",
+            text,
+            " ".repeat(width.saturating_sub(1 + text_width))
+        )
+    };
+    let rows = [title.to_string()]
+        .into_iter()
+        .chain(lines)
+        .chain([String::new()])
+        .take(height);
+    for (i, row) in rows.enumerate() {
+        let row = pad(&row);
+        let styled = if i == 0 {
+            row.with(theme::ui().accent)
+                .attribute(Attribute::Reverse)
+                .attribute(Attribute::Bold)
+        } else {
+            row.with(theme::ui().info)
+        };
+        queue!(
+            stdout,
+            cursor::MoveTo(left as u16, (top + i) as u16),
+            style::PrintStyledContent(styled),
+        )?;
+    }
+    stdout.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_map_to_actions_per_context() {
+        let key = |s: &str| s.parse::<KeyBinding>().unwrap();
+        let event = |code, modifiers| KeyEvent::new(code, modifiers);
+        assert_eq!(key("Ctrl+Shift+left").to_string(), "Ctrl+Shift+Left");
+        assert_eq!(key("+").code, KeyCode::Char('+'));
+        assert_eq!(key("Alt++").modifiers, KeyModifiers::ALT);
+        assert_eq!(key("F5").code, KeyCode::F(5));
+        assert!("Hyper+k".parse::<KeyBinding>().is_err());
+        assert!("Escape".parse::<KeyBinding>().is_err());
+
+        let keymap = Keymap::new(&BTreeMap::from([(Action::Pause, vec![key("Space")])]));
+        let action = |code, modifiers, context| keymap.action(&event(code, modifiers), context);
+        let none = KeyModifiers::NONE;
+        assert_eq!(
+            action(KeyCode::Char(' '), none, Context::Visualize),
+            Some(Action::Pause)
+        );
+        assert_eq!(action(KeyCode::Enter, none, Context::Visualize), None);
+        assert_eq!(
+            action(KeyCode::Char('k'), none, Context::Visualize),
+            Some(Action::SpeedUp)
+        );
+        assert_eq!(
+            action(KeyCode::Char('k'), none, Context::Game),
+            Some(Action::MoveUp)
+        );
+        // Shift is part of characters, but tells arrows apart
+        assert_eq!(
+            action(KeyCode::Char('W'), KeyModifiers::SHIFT, Context::Visualize),
+            Some(Action::PanPageUp)
+        );
+        assert_eq!(
+            action(KeyCode::Char('?'), KeyModifiers::SHIFT, Context::Game),
+            Some(Action::Help)
+        );
+        assert_eq!(
+            action(KeyCode::Left, KeyModifiers::SHIFT, Context::Visualize),
+            Some(Action::PanPageLeft)
+        );
+        assert_eq!(
+            action(KeyCode::Left, KeyModifiers::SHIFT, Context::Game),
+            None
+        );
+        assert!(keymap.help(Context::Visualize)[0].starts_with("Space: "));
+
+        // The second player's keys take precedence over the game keys
+        assert_eq!(
+            action(KeyCode::Char('w'), none, Context::TwoPlayer),
+            Some(Action::Player2Up)
+        );
+        assert_eq!(
+            action(KeyCode::Up, none, Context::TwoPlayer),
+            Some(Action::MoveUp)
+        );
+        assert!(keymap.help(Context::TwoPlayer)[0].starts_with("Up/k, Down/j, Left/h, Right/l: "));
+    }
+}
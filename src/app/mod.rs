@@ -1,6 +1,12 @@
+mod cli;
+mod clock;
 mod game;
+mod keymap;
 mod visualize;
 
+pub use cli::{GifOptions, Options, USAGE};
+pub use keymap::{Action, KeyBinding};
+
 use crossterm::{QueueableCommand, event::Event, execute};
 use std::{
     fmt::Display,
@@ -13,11 +19,21 @@ use crossterm::{
     ExecutableCommand, cursor,
     event::{self, KeyCode},
     queue,
-    style::{self, Attribute, Color, Stylize},
+    style::{self, Attribute, Stylize},
     terminal::{self, ClearType},
 };
 
-use crate::{generators::Generator, maze::cell::GridCell, solvers::Solver};
+use keymap::Keymap;
+
+use crate::{
+    config::{
+        Config,
+        theme::{self, Theme},
+    },
+    generators::Generator,
+    maze::cell::{CellLayout, GlyphTheme},
+    solvers::Solver,
+};
 
 /// Available maze generators
 const GENERATORS: [Generator; 4] = [
@@ -45,16 +61,27 @@ const COMBOS: [(Generator, Solver); 16] = {
     combos
 };
 
-/// Number of rows reserved at the bottom of the terminal for logging or status messages
-const NUM_STATUS_ROWS: u16 = 1;
+/// Rows reserved below the grid for status messages, from top to bottom
+#[derive(Debug, Clone, Copy)]
+enum StatusRow {
+    /// Messages about the latest action
+    Log,
+    /// Heads-up display of the animation state
+    Hud,
+}
+
+/// Number of rows reserved at the bottom of the terminal for status messages, one per [`StatusRow`]
+const NUM_STATUS_ROWS: u16 = 2;
 
 /// Present a menu of options to the user and let them select one using up/down arrow keys
+/// The `default` option, e.g. from the config file, is selected initially if it's in the menu
 /// Returns None if user cancels input with Esc
 /// Returns Some(T) if user selects an option and presses Enter, where T is the option type
-fn select_from_menu<T: std::fmt::Display + Copy>(
+fn select_from_menu<T: std::fmt::Display + Copy + PartialEq>(
     stdout: &mut Stdout,
     prompt: &str,
     options: &[T],
+    default: Option<T>,
 ) -> std::io::Result<Option<T>> {
     if options.is_empty() {
         return Ok(None);
@@ -63,7 +90,9 @@ fn select_from_menu<T: std::fmt::Display + Copy>(
     // Save cursor position so we can restore / redraw
     queue!(stdout, cursor::Hide, cursor::SavePosition)?;
 
-    let mut selected = 0;
+    let mut selected = default
+        .and_then(|default| options.iter().position(|&option| option == default))
+        .unwrap_or(0);
 
     let selected_option = loop {
         // Re-render prompt line
@@ -74,7 +103,7 @@ fn select_from_menu<T: std::fmt::Display + Copy>(
         )?;
 
         // Print prompt
-        stdout.queue(style::PrintStyledContent(prompt.with(Color::Yellow)))?;
+        stdout.queue(style::PrintStyledContent(prompt.with(theme::ui().accent)))?;
 
         // Print options
         for (i, option) in options.iter().enumerate() {
@@ -151,25 +180,45 @@ fn get_max_maze_size(term_size: u16, cell_size: u16) -> u8 {
     (max_grid_size / 2).min(u8::MAX as u16) as u8
 }
 
+/// Calculate max maze dimensions (width, height) for a single grid filling the terminal when drawn
+/// with the given cell layout, with the status rows reserved below it
+fn fit_maze_dims(term_width: u16, term_height: u16, layout: CellLayout) -> (u8, u8) {
+    (
+        get_max_maze_size(term_width, layout.cell_width()),
+        get_max_maze_size(
+            term_height.saturating_sub(NUM_STATUS_ROWS) * layout.rows_per_line(),
+            1,
+        ),
+    )
+}
+
 /// Ask user for maze dimensions (width and height between 1 and 255)
+/// `max_maze_dims` maps the terminal size (columns, rows) to the max maze dimensions that fit,
+/// e.g. [`fit_maze_dims`], used as the default values.
+/// If `allow_larger` is set, values up to 255 are accepted even if they don't fit the terminal.
+/// The inputs are prefilled with the `default` width and height, e.g. from the config file.
 /// Returns None if user cancels input with Esc
 /// Returns Some((width, height)) if user inputs valid dimensions
-fn ask_maze_dimensions(stdout: &mut Stdout) -> std::io::Result<Option<(u8, u8)>> {
-    stdout.execute(style::PrintStyledContent(
+fn ask_maze_dimensions(
+    stdout: &mut Stdout,
+    max_maze_dims: impl Fn(u16, u16) -> (u8, u8),
+    allow_larger: bool,
+    default: (Option<u8>, Option<u8>),
+) -> std::io::Result<Option<(u8, u8)>> {
+    let msg = if allow_larger {
+        "Enter maze dimensions (width and height between 1 and 255), or press Esc to exit. \
+Leave empty to fit the current terminal size. Larger mazes can be panned around.\r\n"
+    } else {
         "Enter maze dimensions (width and height between 1 and 255), or press Esc to exit. \
 Maximum acceptable values are based on current terminal size.\r\n"
-            .with(Color::Blue),
-    ))?;
+    };
+    stdout.execute(style::PrintStyledContent(msg.with(theme::ui().hint)))?;
 
     // Validation closure based on default sizes
     let validate = |s: &str, is_width| {
         let max_size = if let Ok((term_width, term_height)) = terminal::size() {
-            if is_width {
-                get_max_maze_size(term_width, GridCell::CELL_WIDTH)
-            } else {
-                // Reserve rows for logs
-                get_max_maze_size(term_height.saturating_sub(NUM_STATUS_ROWS), 1)
-            }
+            let (max_width, max_height) = max_maze_dims(term_width, term_height);
+            if is_width { max_width } else { max_height }
         } else {
             // Fallback to max size if terminal size cannot be determined
             u8::MAX
@@ -178,6 +227,7 @@ Maximum acceptable values are based on current terminal size.\r\n"
         if s.trim().is_empty() {
             return Ok(max_size);
         }
+        let max_size = if allow_larger { u8::MAX } else { max_size };
 
         let error_msg = format!("Please enter a valid number between 1 and {}.", max_size);
         s.parse::<u8>()
@@ -191,35 +241,39 @@ Maximum acceptable values are based on current terminal size.\r\n"
     let validate_width = |s: &str| validate(s, true);
     let validate_height = |s: &str| validate(s, false);
 
-    let width = match prompt_with_validation(stdout, "Width: ", validate_width)? {
-        Some(w) => w,
-        None => return Ok(None),
-    };
+    let prefill = |value: Option<u8>| value.map(|n| n.to_string()).unwrap_or_default();
+    let width =
+        match prompt_with_validation(stdout, "Width: ", &prefill(default.0), validate_width)? {
+            Some(w) => w,
+            None => return Ok(None),
+        };
     stdout.execute(style::PrintStyledContent(
         format!("Width set to {}\r\n", width)
-            .with(Color::Green)
+            .with(theme::ui().success)
             .attribute(Attribute::Bold),
     ))?;
 
-    let height = match prompt_with_validation(stdout, "Height: ", validate_height)? {
-        Some(h) => h,
-        None => return Ok(None),
-    };
+    let height =
+        match prompt_with_validation(stdout, "Height: ", &prefill(default.1), validate_height)? {
+            Some(h) => h,
+            None => return Ok(None),
+        };
     stdout.execute(style::PrintStyledContent(
         format!("Height set to {}\r\n", height)
-            .with(Color::Green)
+            .with(theme::ui().success)
             .attribute(Attribute::Bold),
     ))?;
 
     Ok(Some((width, height)))
 }
 
-/// Get user input with real-time validation and feedback
+/// Get user input with real-time validation and feedback, starting from the `initial` input
 /// Returns None if user cancels input with Esc
 /// Returns Some(T) if user inputs a valid input and presses Enter, where T is the validated type
 fn prompt_with_validation<F, T>(
     stdout: &mut Stdout,
     prompt: &str,
+    initial: &str,
     validate: F,
 ) -> std::io::Result<Option<T>>
 where
@@ -229,7 +283,7 @@ where
     queue!(stdout, cursor::Hide, cursor::SavePosition)?;
     stdout.flush()?;
 
-    let mut input = String::new();
+    let mut input = initial.to_string();
 
     let number_option = loop {
         // Re-render
@@ -241,17 +295,17 @@ where
 
         // Print prompt
         stdout.queue(style::PrintStyledContent(
-            prompt.with(Color::Cyan).attribute(Attribute::Bold),
+            prompt.with(theme::ui().info).attribute(Attribute::Bold),
         ))?;
 
         // Decide color based on validity
         let validation_result = validate(input.trim());
         match validation_result {
             Ok(_) => {
-                stdout.queue(style::SetForegroundColor(Color::Green))?;
+                stdout.queue(style::SetForegroundColor(theme::ui().success))?;
             }
             Err(_) => {
-                stdout.queue(style::SetForegroundColor(Color::Red))?;
+                stdout.queue(style::SetForegroundColor(theme::ui().error))?;
             }
         }
 
@@ -262,7 +316,7 @@ where
         // Error message line (if any)
         if let Err(msg) = validation_result {
             stdout.queue(style::PrintStyledContent(
-                msg.with(Color::DarkGrey).attribute(Attribute::Dim),
+                msg.with(theme::ui().muted).attribute(Attribute::Dim),
             ))?;
         }
 
@@ -278,10 +332,12 @@ where
                     }
                     // otherwise, stay in loop
                 }
-                KeyCode::Char(c) if kind == event::KeyEventKind::Press => {
-                    if !c.is_whitespace() && !c.is_control() {
-                        input.push(c);
-                    }
+                KeyCode::Char(c)
+                    if kind == event::KeyEventKind::Press
+                        && !c.is_whitespace()
+                        && !c.is_control() =>
+                {
+                    input.push(c);
                 }
                 KeyCode::Backspace => {
                     input.pop();
@@ -306,6 +362,50 @@ where
     Ok(number_option)
 }
 
+/// Last menu before starting, to optionally save the selections as defaults
+#[derive(Copy, Clone, PartialEq)]
+enum StartChoice {
+    Start,
+    SaveAndStart,
+}
+
+impl std::fmt::Display for StartChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartChoice::Start => write!(f, "Start"),
+            StartChoice::SaveAndStart => write!(f, "Save these selections as defaults, then start"),
+        }
+    }
+}
+
+/// Ask user to start with the selections made in the menus, optionally saving them to the config
+/// file so the menus preselect them on the next launch
+/// Returns false if user cancels input with Esc
+fn ask_start(stdout: &mut Stdout, selections: &Config) -> std::io::Result<bool> {
+    let Some(choice) = select_from_menu(
+        stdout,
+        "Ready to start (use arrow keys and Enter, or Esc to exit):",
+        &[StartChoice::Start, StartChoice::SaveAndStart],
+        None,
+    )?
+    else {
+        return Ok(false);
+    };
+    if choice == StartChoice::SaveAndStart {
+        let msg = match selections.save() {
+            Ok(path) => {
+                format!("Saved selections to {}\r\n", path.display()).with(theme::ui().success)
+            }
+            Err(e) => {
+                tracing::error!("Failed to save config: {}", e);
+                format!("Failed to save selections: {}\r\n", e).with(theme::ui().error)
+            }
+        };
+        stdout.execute(style::PrintStyledContent(msg.attribute(Attribute::Bold)))?;
+    }
+    Ok(true)
+}
+
 /// Wait for a specific key press event from the user
 fn wait_for_keypress(key: KeyCode) -> std::io::Result<()> {
     loop {
@@ -322,20 +422,31 @@ fn wait_for_keypress(key: KeyCode) -> std::io::Result<()> {
 /// Print a line of message below the grid without disrupting the grid display
 /// The cursor position is saved and restored after logging
 /// Returns `Err` if there was an I/O error
-/// If msg is None, clears all messages from the reserved rows
+/// If msg is None, clears the log message
 fn log_terminal(
     stdout: &mut impl Write,
     grid_height: u16,
     msg: Option<style::StyledContent<impl Display + AsRef<str>>>,
+) -> std::io::Result<()> {
+    print_status(stdout, grid_height, StatusRow::Log, msg)
+}
+
+/// Print a line of message to the given status row below the grid, like [`log_terminal`]
+/// If msg is None, clears the row
+fn print_status(
+    stdout: &mut impl Write,
+    grid_height: u16,
+    row: StatusRow,
+    msg: Option<style::StyledContent<impl Display + AsRef<str>>>,
 ) -> std::io::Result<()> {
     let term_width = terminal::size()?.0 as usize;
     queue!(
         stdout,
         // Save cursor position first
         cursor::SavePosition,
-        // Move cursor to the log line (below the grid)
-        cursor::MoveTo(0, grid_height),
-        // Clear previous log line
+        // Move cursor to the status row (below the grid)
+        cursor::MoveTo(0, grid_height + row as u16),
+        // Clear previous message of the row
         terminal::Clear(ClearType::CurrentLine),
     )?;
     if let Some(msg) = msg {
@@ -362,8 +473,8 @@ fn log_terminal(
     Ok(())
 }
 
-#[derive(Copy, Clone)]
-enum AppMode {
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AppMode {
     Visualize,
     Game,
 }
@@ -377,19 +488,45 @@ impl std::fmt::Display for AppMode {
     }
 }
 
+impl std::str::FromStr for AppMode {
+    type Err = String;
+
+    /// Parse an app mode from a short name (e.g. `game`) or its display name, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "visualize" | "visualization mode" => Ok(AppMode::Visualize),
+            "game" | "game mode" => Ok(AppMode::Game),
+            _ => Err(format!(
+                "Unknown app mode '{}', expected one of: visualize, game",
+                s
+            )),
+        }
+    }
+}
+
 pub struct App {
     stdout: Stdout,
+    /// Options given on the command line
+    options: Options,
+    /// Defaults loaded from the config file
+    config: Config,
 }
 
 impl Default for App {
     fn default() -> Self {
+        Self::new(Options::default())
+    }
+}
+
+impl App {
+    pub fn new(options: Options) -> Self {
         Self {
             stdout: std::io::stdout(),
+            options,
+            config: Config::default(),
         }
     }
-}
 
-impl App {
     /// Set a panic hook to restore terminal state on panic
     /// This ensures that the terminal is not left in raw mode or alternate screen on panic
     /// even if the panic occurs in a different thread
@@ -430,6 +567,21 @@ impl App {
     /// Entry point to run the application.
     /// Sets up the terminal, runs the app logic, and restores the terminal state on exit.
     pub fn run(&mut self) -> std::io::Result<()> {
+        self.config = Config::load()?;
+        let glyphs = self
+            .options
+            .glyphs
+            .or(self.config.glyphs)
+            .unwrap_or_else(GlyphTheme::detect);
+        tracing::info!("Using the {} glyph theme", glyphs);
+        glyphs.set();
+        Theme::load(self.options.theme)?.install();
+        Keymap::new(&self.config.keys).install();
+
+        // Exports run without the interactive terminal
+        if let Some(path) = &self.options.gif {
+            return visualize::export_gif(path, &self.options.gif_options);
+        }
         self.setup_terminal()?;
         self.app()?;
         App::restore_terminal(&mut self.stdout)?;
@@ -440,14 +592,20 @@ impl App {
     fn app(&mut self) -> std::io::Result<()> {
         self.stdout.execute(style::PrintStyledContent(
             "Welcome to Mazest!\r\n"
-                .with(Color::Yellow)
+                .with(theme::ui().accent)
                 .attribute(Attribute::Bold),
         ))?;
 
+        // Replays skip straight to the visualizer
+        if let Some(path) = &self.options.replay {
+            return visualize::replay(&mut self.stdout, path, &self.options, &self.config);
+        }
+
         let mode = match select_from_menu(
             &mut self.stdout,
             "Select app mode (use arrow keys and Enter, or Esc to exit):",
             &[AppMode::Visualize, AppMode::Game],
+            self.config.mode,
         )? {
             Some(m) => m,
             None => return Ok(()),
@@ -461,10 +619,10 @@ impl App {
         )?;
         match mode {
             AppMode::Visualize => {
-                visualize::run(&mut self.stdout)?;
+                visualize::run(&mut self.stdout, &self.options, &self.config)?;
             }
             AppMode::Game => {
-                game::run(&mut self.stdout)?;
+                game::run(&mut self.stdout, &self.config)?;
             }
         }
         Ok(())
@@ -0,0 +1,193 @@
+use std::{
+    fs::File,
+    io::{BufWriter, StdoutLock, Write},
+    path::Path,
+    time::Duration,
+};
+
+/// Longest pause kept when replaying a recording, in seconds. Time spent paused by the user is
+/// recorded as it happened, so players compress long pauses down to this.
+const IDLE_TIME_LIMIT: f64 = 2.0;
+
+/// Records terminal output into an asciicast v2 file.
+/// Timestamps come from a clock advanced explicitly by the renderer, so the recording replays
+/// at the chosen rendering speed regardless of how long rendering actually took.
+pub(super) struct CastRecorder {
+    writer: BufWriter<File>,
+    /// Time of the recording
+    clock: Duration,
+    /// Output written since the last recorded event
+    pending: Vec<u8>,
+    /// Terminal size (columns, rows) last recorded
+    size: (u16, u16),
+}
+
+impl CastRecorder {
+    /// Create the recording file at the given path and write the header for a terminal of the given size
+    pub(super) fn create(path: &Path, size: (u16, u16)) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let term = std::env::var("TERM").unwrap_or_else(|_| "xterm-256color".to_string());
+        writeln!(
+            writer,
+            r#"{{"version": 2, "width": {}, "height": {}, "timestamp": {}, "idle_time_limit": {}, "env": {{"TERM": {}}}}}"#,
+            size.0,
+            size.1,
+            timestamp,
+            IDLE_TIME_LIMIT,
+            json_string(&term)
+        )?;
+        tracing::info!("Recording to {}", path.display());
+        Ok(Self {
+            writer,
+            clock: Duration::ZERO,
+            pending: Vec::new(),
+            size,
+        })
+    }
+
+    /// Move the recording clock forward
+    pub(super) fn advance(&mut self, duration: Duration) {
+        self.clock += duration;
+    }
+
+    /// Record a terminal resize, if the size changed
+    pub(super) fn resize(&mut self, size: (u16, u16)) -> std::io::Result<()> {
+        if size != self.size {
+            self.size = size;
+            self.commit()?;
+            self.write_event("r", &format!("{}x{}", size.0, size.1))?;
+        }
+        Ok(())
+    }
+
+    /// Record the output written since the last event as a single output event at the current time.
+    /// A multi-byte character cut off at the end is kept for the next event.
+    fn commit(&mut self) -> std::io::Result<()> {
+        let valid_len = match std::str::from_utf8(&self.pending) {
+            Ok(s) => s.len(),
+            Err(e) => e.valid_up_to(),
+        };
+        if valid_len == 0 {
+            return Ok(());
+        }
+        let rest = self.pending.split_off(valid_len);
+        let data = String::from_utf8(std::mem::replace(&mut self.pending, rest))
+            .expect("Checked to be valid UTF-8");
+        self.write_event("o", &data)
+    }
+
+    fn write_event(&mut self, code: &str, data: &str) -> std::io::Result<()> {
+        writeln!(
+            self.writer,
+            "[{:.6}, \"{}\", {}]",
+            self.clock.as_secs_f64(),
+            code,
+            json_string(data)
+        )
+    }
+}
+
+impl Drop for CastRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.commit().and_then(|_| self.writer.flush()) {
+            tracing::error!("Failed to finish recording: {}", e);
+        }
+    }
+}
+
+/// Quote and escape a string as a JSON string literal
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Terminal output of the renderer. Everything written is copied to the recorder, if recording.
+pub(super) struct RenderOutput<'a> {
+    stdout: StdoutLock<'a>,
+    recorder: Option<CastRecorder>,
+    /// Skip rendering delays, only advancing the recording clock
+    fast: bool,
+}
+
+impl<'a> RenderOutput<'a> {
+    pub(super) fn new(stdout: StdoutLock<'a>, recorder: Option<CastRecorder>, fast: bool) -> Self {
+        Self {
+            stdout,
+            recorder,
+            fast,
+        }
+    }
+
+    /// Wait for a rendering delay, and advance the recording clock by it.
+    /// In fast mode, only the recording clock is advanced.
+    pub(super) fn delay(&mut self, duration: Duration) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.advance(duration);
+        }
+        if !self.fast {
+            std::thread::sleep(duration);
+        }
+    }
+
+    /// Advance the recording clock by time that already passed, e.g. while paused by the user
+    pub(super) fn elapsed(&mut self, duration: Duration) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.advance(duration);
+        }
+    }
+
+    /// Record the terminal size, if it changed
+    pub(super) fn resize(&mut self, size: (u16, u16)) -> std::io::Result<()> {
+        match &mut self.recorder {
+            Some(recorder) => recorder.resize(size),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Write for RenderOutput<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.stdout.write(buf)?;
+        if let Some(recorder) = &mut self.recorder {
+            recorder.pending.extend_from_slice(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stdout.flush()?;
+        if let Some(recorder) = &mut self.recorder {
+            recorder.commit()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_string_escapes_terminal_output() {
+        assert_eq!(
+            json_string("\u{1b}[2J\"⬜\"\r\n\\"),
+            r#""\u001b[2J\"⬜\"\r\n\\""#
+        );
+    }
+}
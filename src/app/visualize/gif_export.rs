@@ -0,0 +1,250 @@
+use std::{borrow::Cow, path::Path};
+
+use crate::{
+    app::{cli::GifOptions, visualize::MAX_EVENTS_IN_CHANNEL_BUFFER},
+    config::theme::rgb,
+    maze::{cell::GridCell, grid::GridEvent},
+};
+
+/// Frames of the final grid are held this many times longer than the other frames,
+/// so the solution stays visible before the animation loops
+const FINAL_FRAME_DELAY_FACTOR: u16 = 50;
+/// Number of palette entries, fitting the background and every cell encoding, as GIF palettes
+/// have a power of two size
+const PALETTE_SIZE: usize = (GridCell::MAX_ENCODING as usize + 2).next_power_of_two();
+/// Palette index of empty cells, drawn as the background
+const BACKGROUND: u8 = 0;
+
+/// Summary of a finished export
+pub(super) struct GifSummary {
+    pub(super) frames: usize,
+    pub(super) bytes: usize,
+    pub(super) events_per_frame: usize,
+    pub(super) solved: bool,
+}
+
+/// Palette index of a cell. Indices follow the one byte cell encoding, shifted past the background.
+fn palette_index(cell: GridCell) -> u8 {
+    match cell.color() {
+        Some(_) => u8::from(cell) + 1,
+        None => BACKGROUND,
+    }
+}
+
+/// Palette with the color of each cell at its [`palette_index`], as packed RGB bytes
+fn palette() -> Vec<u8> {
+    let mut palette = vec![0; PALETTE_SIZE * 3];
+    for byte in 0..=u8::MAX {
+        if let Ok(cell) = GridCell::try_from(byte)
+            && let Some(color) = cell.color()
+        {
+            let index = palette_index(cell) as usize;
+            palette[index * 3..index * 3 + 3].copy_from_slice(&rgb(color));
+        }
+    }
+    palette
+}
+
+/// Canvas of palette indices, one per grid cell, tracking the region changed since the last frame
+struct Canvas {
+    cells: Vec<u8>,
+    width: u16,
+    height: u16,
+    /// Changed region (min x, min y, max x, max y), inclusive
+    dirty: Option<(u16, u16, u16, u16)>,
+}
+
+impl Canvas {
+    fn new(width: u16, height: u16, cell: GridCell) -> Self {
+        Self {
+            cells: vec![palette_index(cell); width as usize * height as usize],
+            width,
+            height,
+            dirty: Some((0, 0, width - 1, height - 1)),
+        }
+    }
+
+    fn set(&mut self, (x, y): (u16, u16), cell: GridCell) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        self.cells[y as usize * self.width as usize + x as usize] = palette_index(cell);
+        self.dirty = Some(match self.dirty {
+            Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+            None => (x, y, x, y),
+        });
+    }
+
+    /// Make a frame of the changed region, scaled by `scale` pixels per cell, and reset the region
+    fn take_frame(&mut self, scale: u16, delay: u16) -> Option<gif::Frame<'static>> {
+        let (x0, y0, x1, y1) = self.dirty.take()?;
+        let (width, height) = ((x1 - x0 + 1) * scale, (y1 - y0 + 1) * scale);
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        for y in y0..=y1 {
+            let row = &self.cells[y as usize * self.width as usize..][x0 as usize..=x1 as usize];
+            let scaled_row = row
+                .iter()
+                .flat_map(|&index| std::iter::repeat_n(index, scale as usize));
+            let start = pixels.len();
+            pixels.extend(scaled_row);
+            for _ in 1..scale {
+                pixels.extend_from_within(start..);
+            }
+        }
+        Some(gif::Frame {
+            left: x0 * scale,
+            top: y0 * scale,
+            width,
+            height,
+            delay,
+            dispose: gif::DisposalMethod::Keep,
+            buffer: Cow::Owned(pixels),
+            ..Default::default()
+        })
+    }
+}
+
+/// Encode the grid events into an animated GIF, drawing `events_per_frame` events per frame
+fn encode(
+    events: &[GridEvent],
+    options: &GifOptions,
+    events_per_frame: usize,
+) -> std::io::Result<(Vec<u8>, usize)> {
+    let Some(&GridEvent::Initial { width, height, .. }) = events.first() else {
+        return Err(std::io::Error::other("The run has no grid to export"));
+    };
+    let scale = options.scale;
+    let (image_width, image_height) = (width.checked_mul(scale), height.checked_mul(scale));
+    let (Some(image_width), Some(image_height)) = (image_width, image_height) else {
+        return Err(std::io::Error::other(
+            "Image is too large for a GIF, try a smaller scale",
+        ));
+    };
+    // GIF delays are in hundredths of a second
+    let delay = (options.frame_delay.as_millis() / 10).clamp(1, u16::MAX as u128) as u16;
+
+    let mut bytes = Vec::new();
+    let mut encoder = gif::Encoder::new(&mut bytes, image_width, image_height, &palette())
+        .map_err(std::io::Error::other)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(std::io::Error::other)?;
+
+    let mut canvas = Canvas::new(width, height, GridCell::EMPTY);
+    let mut frames = 0;
+    let batches = events.chunks(events_per_frame);
+    let num_batches = batches.len();
+    for (i, batch) in batches.enumerate() {
+        for event in batch {
+            match *event {
+                GridEvent::Initial {
+                    cell,
+                    width,
+                    height,
+                } => {
+                    canvas = Canvas::new(width, height, cell);
+                }
+                GridEvent::Update { coord, new, .. } => canvas.set(coord, new),
+            }
+        }
+        let delay = if i + 1 == num_batches {
+            delay.saturating_mul(FINAL_FRAME_DELAY_FACTOR)
+        } else {
+            delay
+        };
+        if let Some(frame) = canvas.take_frame(scale, delay) {
+            encoder.write_frame(&frame).map_err(std::io::Error::other)?;
+            frames += 1;
+        }
+    }
+    drop(encoder);
+    Ok((bytes, frames))
+}
+
+/// Generate and solve a maze with the given options, and write the animation to a GIF file.
+/// If a maximum file size is set, the number of events per frame is doubled until the file fits.
+pub(super) fn export(path: &Path, options: &GifOptions) -> std::io::Result<GifSummary> {
+    let (width, height) = options.size;
+    let (grid_event_tx, grid_event_rx) =
+        std::sync::mpsc::sync_channel::<GridEvent>(MAX_EVENTS_IN_CHANNEL_BUFFER);
+    let (generator, solver, seed) = (options.generator, options.solver, options.seed);
+    let compute_thread_handle = std::thread::spawn(move || {
+        super::compute(width, height, grid_event_tx, generator, solver, seed)
+    });
+    let events: Vec<GridEvent> = grid_event_rx.iter().collect();
+    let solved = compute_thread_handle
+        .join()
+        .map_err(|_| std::io::Error::other("Compute thread panicked"))?;
+    tracing::info!(
+        "Exporting {} grid events to {}",
+        events.len(),
+        path.display()
+    );
+
+    let mut events_per_frame = options.events_per_frame;
+    loop {
+        let (bytes, frames) = encode(&events, options, events_per_frame)?;
+        match options.max_size {
+            Some(max_size) if bytes.len() as u64 > max_size => {
+                if events_per_frame >= events.len() {
+                    return Err(std::io::Error::other(format!(
+                        "Even a single frame takes {} bytes, over the maximum of {} bytes",
+                        bytes.len(),
+                        max_size
+                    )));
+                }
+                tracing::debug!(
+                    "{} bytes with {} events per frame is over the maximum, retrying",
+                    bytes.len(),
+                    events_per_frame
+                );
+                events_per_frame = events_per_frame.saturating_mul(2);
+            }
+            _ => {
+                std::fs::write(path, &bytes)?;
+                return Ok(GifSummary {
+                    frames,
+                    bytes: bytes.len(),
+                    events_per_frame,
+                    solved,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_cover_changed_cells_only() {
+        let mut canvas = Canvas::new(5, 5, GridCell::WALL);
+        let first = canvas.take_frame(2, 4).unwrap();
+        assert_eq!((first.width, first.height), (10, 10));
+
+        canvas.set((1, 1), GridCell::EMPTY);
+        canvas.set((3, 2), GridCell::START);
+        let frame = canvas.take_frame(2, 4).unwrap();
+        assert_eq!(
+            (frame.left, frame.top, frame.width, frame.height),
+            (2, 2, 6, 4)
+        );
+        assert_eq!(frame.buffer[0], BACKGROUND);
+        assert_eq!(
+            frame.buffer[frame.buffer.len() - 1],
+            palette_index(GridCell::START)
+        );
+        assert!(canvas.take_frame(2, 4).is_none());
+    }
+
+    #[test]
+    fn palette_fits_every_cell() {
+        let palette = palette();
+        assert_eq!(palette.len(), PALETTE_SIZE * 3);
+        for cell in (0..=u8::MAX).filter_map(|byte| GridCell::try_from(byte).ok()) {
+            assert!(u8::from(cell) <= GridCell::MAX_ENCODING, "{:?}", cell);
+            assert!((palette_index(cell) as usize) < PALETTE_SIZE, "{:?}", cell);
+        }
+    }
+}
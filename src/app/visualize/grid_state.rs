@@ -0,0 +1,576 @@
+use std::{collections::HashMap, io::Write};
+
+use crossterm::{
+    QueueableCommand, cursor, queue,
+    style::{self, Color, StyledContent, Stylize},
+};
+
+use crate::{
+    config::theme::Theme,
+    maze::{
+        cell::{CellLayout, GridCell},
+        grid::{Grid, GridEvent},
+    },
+};
+
+/// How grid cells are colored on screen
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(super) enum DisplayMode {
+    /// Plain cell glyphs and colors
+    #[default]
+    Normal,
+    /// Visited cells are colored on a gradient by their distance from the start cell
+    Heatmap,
+    /// Visited cells are colored on a gradient by the order they were expanded, oldest to newest
+    VisitOrder,
+}
+
+/// Payload stored for each cell that differs from the initial cell
+#[derive(Debug, Clone, Copy)]
+pub(super) struct CellState {
+    /// The cell itself
+    cell: GridCell,
+    /// Distance from the start cell in maze steps, for start and visited cells
+    distance: Option<u32>,
+    /// Order in which the cell was visited, for visited cells
+    visit_order: Option<u32>,
+}
+
+/// Copy of the grid contents of a [`GridState`], without its viewport.
+/// Used as a keyframe to jump around the rendering history.
+#[derive(Debug, Default)]
+pub(super) struct GridSnapshot {
+    initial: Option<(GridCell, u16, u16)>,
+    changes: Vec<((u16, u16), CellState)>,
+    max_distance: u32,
+    visit_count: u32,
+}
+
+impl GridSnapshot {
+    /// Number of changed cells held by the snapshot
+    pub(super) fn len(&self) -> usize {
+        self.changes.len()
+    }
+}
+
+/// Region of the grid that is shown on screen, and where on screen it is drawn
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Viewport {
+    /// Terminal position (column, row) where the top left visible cell is drawn
+    origin: (u16, u16),
+    /// Grid coordinate of the top left visible cell
+    offset: (u16, u16),
+    /// Max number of visible grid cells (columns, rows)
+    size: (u16, u16),
+    /// How grid cells are laid out on screen
+    layout: CellLayout,
+}
+
+impl Default for Viewport {
+    /// Unbounded viewport drawn at the top left of the terminal
+    fn default() -> Self {
+        Self {
+            origin: (0, 0),
+            offset: (0, 0),
+            size: (u16::MAX, u16::MAX),
+            layout: CellLayout::default(),
+        }
+    }
+}
+
+impl Viewport {
+    /// Terminal position of the given grid coordinate, if it is visible.
+    /// With [`CellLayout::HalfBlock`], both grid rows packed into a terminal row share its position.
+    fn to_screen(self, coord: (u16, u16)) -> Option<(u16, u16)> {
+        let x = coord.0.checked_sub(self.offset.0)?;
+        let y = coord.1.checked_sub(self.offset.1)?;
+        (x < self.size.0 && y < self.size.1).then(|| {
+            (
+                self.origin.0 + x * self.layout.cell_width(),
+                self.origin.1 + y / self.layout.rows_per_line(),
+            )
+        })
+    }
+}
+
+/// Compact representation of the current grid state.
+/// Stores the initial fill cell and only the cells that differ from it.
+#[derive(Default)]
+pub(super) struct GridState {
+    /// initial cell and grid dimensions (width, height)
+    initial: Option<(GridCell, u16, u16)>,
+    /// map of (x,y) -> cell state for cells that differ from initial cell
+    changes: HashMap<(u16, u16), CellState>,
+    /// Largest distance from the start cell seen so far
+    pub(super) max_distance: u32,
+    /// Number of visited cells seen so far, used to order visited cells
+    pub(super) visit_count: u32,
+    /// Visible region of the grid
+    viewport: Viewport,
+}
+
+impl GridState {
+    /// Create an empty grid state drawn with its top left cell at the given terminal position,
+    /// using the given cell layout
+    pub(super) fn with_origin(origin: (u16, u16), layout: CellLayout) -> Self {
+        Self {
+            viewport: Viewport {
+                origin,
+                layout,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Create a grid state holding a snapshot of the given grid, drawn at the given terminal position
+    /// using the given cell layout
+    pub(super) fn from_grid(grid: &Grid, origin: (u16, u16), layout: CellLayout) -> Self {
+        let mut state = Self::with_origin(origin, layout);
+        state.add_event(GridEvent::Initial {
+            cell: GridCell::WALL,
+            width: grid.width(),
+            height: grid.height(),
+        });
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                state.add_event(GridEvent::Update {
+                    coord: (x, y),
+                    old: GridCell::WALL,
+                    new: grid[(x, y)],
+                });
+            }
+        }
+        state
+    }
+
+    /// Take a snapshot of the grid contents
+    pub(super) fn snapshot(&self) -> GridSnapshot {
+        GridSnapshot {
+            initial: self.initial,
+            changes: self
+                .changes
+                .iter()
+                .map(|(&coord, &state)| (coord, state))
+                .collect(),
+            max_distance: self.max_distance,
+            visit_count: self.visit_count,
+        }
+    }
+
+    /// Replace the grid contents with the given snapshot, keeping the viewport
+    pub(super) fn restore(&mut self, snapshot: &GridSnapshot) {
+        self.initial = snapshot.initial;
+        self.changes = snapshot.changes.iter().copied().collect();
+        self.max_distance = snapshot.max_distance;
+        self.visit_count = snapshot.visit_count;
+    }
+
+    pub(super) fn dims(&self) -> Option<(u16, u16)> {
+        self.initial.map(|(_, w, h)| (w, h))
+    }
+
+    /// Dimensions (columns, rows) of the visible part of the grid
+    pub(super) fn visible_dims(&self) -> Option<(u16, u16)> {
+        let (width, height) = self.dims()?;
+        Some((
+            width.min(self.viewport.size.0),
+            height.min(self.viewport.size.1),
+        ))
+    }
+
+    /// How grid cells are laid out on screen
+    pub(super) fn layout(&self) -> CellLayout {
+        self.viewport.layout
+    }
+
+    /// Terminal size (columns, rows) taken up by the visible part of the grid
+    pub(super) fn screen_dims(&self) -> Option<(u16, u16)> {
+        let (width, height) = self.visible_dims()?;
+        let layout = self.viewport.layout;
+        Some((
+            width * layout.cell_width(),
+            height.div_ceil(layout.rows_per_line()),
+        ))
+    }
+
+    /// Whether only part of the grid fits in the viewport
+    pub(super) fn is_scrollable(&self) -> bool {
+        self.dims() != self.visible_dims()
+    }
+
+    /// Set the max number of visible grid cells (columns, rows), keeping the visible region
+    /// inside the grid. Returns whether the visible region changed.
+    pub(super) fn set_view_size(&mut self, size: (u16, u16)) -> bool {
+        let before = (self.viewport.offset, self.visible_dims());
+        self.viewport.size = size;
+        self.scroll_to(self.viewport.offset);
+        before != (self.viewport.offset, self.visible_dims())
+    }
+
+    /// Move the top left visible cell to the given grid coordinate, clamped so the visible region
+    /// stays inside the grid. Returns whether the visible region changed.
+    fn scroll_to(&mut self, offset: (u16, u16)) -> bool {
+        let before = self.viewport.offset;
+        if let (Some((width, height)), Some((visible_width, visible_height))) =
+            (self.dims(), self.visible_dims())
+        {
+            let rows_per_line = self.viewport.layout.rows_per_line();
+            self.viewport.offset = (
+                offset.0.min(width - visible_width),
+                // Keep grid rows packed into terminal rows in the same pairs, rounding up so the
+                // last grid row can still be reached
+                offset
+                    .1
+                    .min(height - visible_height)
+                    .next_multiple_of(rows_per_line),
+            );
+        }
+        before != self.viewport.offset
+    }
+
+    /// Scroll the visible region by the given number of grid cells.
+    /// Returns whether the visible region changed.
+    pub(super) fn pan(&mut self, dx: i32, dy: i32) -> bool {
+        let (x, y) = self.viewport.offset;
+        self.scroll_to((
+            (x as i32 + dx).clamp(0, u16::MAX as i32) as u16,
+            (y as i32 + dy).clamp(0, u16::MAX as i32) as u16,
+        ))
+    }
+
+    /// Scroll so the given grid coordinate is centered, unless it is already within the middle
+    /// half of the visible region. Returns whether the visible region changed.
+    pub(super) fn follow(&mut self, coord: (u16, u16)) -> bool {
+        let Some((visible_width, visible_height)) = self.visible_dims() else {
+            return false;
+        };
+        let (x, y) = self.viewport.offset;
+        let near_center = |pos: u16, offset: u16, visible: u16| {
+            pos >= offset + visible / 4 && pos < offset + visible - visible / 4
+        };
+        if near_center(coord.0, x, visible_width) && near_center(coord.1, y, visible_height) {
+            return false;
+        }
+        self.scroll_to((
+            coord.0.saturating_sub(visible_width / 2),
+            coord.1.saturating_sub(visible_height / 2),
+        ))
+    }
+
+    /// Short description of the visible region within the full grid
+    pub(super) fn view_indicator(&self) -> Option<String> {
+        let (width, height) = self.dims()?;
+        let (visible_width, visible_height) = self.visible_dims()?;
+        let (x, y) = self.viewport.offset;
+        Some(format!(
+            "View x {}-{}/{}, y {}-{}/{}",
+            x,
+            x + visible_width - 1,
+            width,
+            y,
+            y + visible_height - 1,
+            height
+        ))
+    }
+
+    /// Get the cell at the given grid coordinate, if the initial cell is set
+    fn cell_at(&self, coord: (u16, u16)) -> Option<GridCell> {
+        let (initial, _, _) = self.initial?;
+        Some(self.changes.get(&coord).map_or(initial, |state| state.cell))
+    }
+
+    /// Compute the distance of a newly visited cell from the start cell.
+    /// Maze cells sit on odd grid coordinates, so each neighboring maze cell is two grid cells away
+    /// with the passage cell in between. Since a visited cell is always reached from an already
+    /// visited neighbor, the smallest known neighbor distance plus one is its distance.
+    fn neighbor_distance(&self, coord: (u16, u16)) -> Option<u32> {
+        let (x, y) = coord;
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .filter_map(|(dx, dy): (i32, i32)| {
+                let passage = (
+                    u16::try_from(x as i32 + dx).ok()?,
+                    u16::try_from(y as i32 + dy).ok()?,
+                );
+                let neighbor = (
+                    u16::try_from(x as i32 + 2 * dx).ok()?,
+                    u16::try_from(y as i32 + 2 * dy).ok()?,
+                );
+                if matches!(self.cell_at(passage)?, GridCell::Wall(_)) {
+                    return None;
+                }
+                self.changes.get(&neighbor)?.distance
+            })
+            .min()
+            .map(|d| d + 1)
+    }
+
+    /// Apply a GridEvent to the compact state. Safe to call for both [`GridEvent::Initial`]` and [`GridEvent::Update`].
+    /// If an [`GridEvent::Initial`] is received, it clears the changes map and sets the initial cell and dimensions.
+    /// If an [`GridEvent::Update`] is received, it updates the changes map accordingly. The `old` value is ignored.
+    pub(super) fn add_event(&mut self, event: GridEvent) {
+        match event {
+            GridEvent::Initial {
+                cell,
+                width,
+                height,
+            } => {
+                self.initial = Some((cell, width, height));
+                self.changes.clear();
+                self.max_distance = 0;
+                self.visit_count = 0;
+            }
+            GridEvent::Update { coord, new, .. } => {
+                if let Some((initial, _, _)) = self.initial {
+                    // Give back the visit order of the most recently visited cell when it is reverted,
+                    // so stepping backward and forward again keeps the same order
+                    if let Some(order) = self.changes.get(&coord).and_then(|s| s.visit_order)
+                        && new != GridCell::VISITED
+                        && order + 1 == self.visit_count
+                    {
+                        self.visit_count -= 1;
+                    }
+                    if new == initial {
+                        self.changes.remove(&coord);
+                    } else {
+                        let distance = match new {
+                            GridCell::START => Some(0),
+                            GridCell::VISITED => self.neighbor_distance(coord),
+                            _ => None,
+                        };
+                        if let Some(distance) = distance {
+                            self.max_distance = self.max_distance.max(distance);
+                        }
+                        let visit_order = match self.changes.get(&coord) {
+                            // Keep the order of a cell that stays visited
+                            Some(state)
+                                if new == GridCell::VISITED && state.visit_order.is_some() =>
+                            {
+                                state.visit_order
+                            }
+                            _ if new == GridCell::VISITED => {
+                                self.visit_count += 1;
+                                Some(self.visit_count - 1)
+                            }
+                            _ => None,
+                        };
+                        self.changes.insert(
+                            coord,
+                            CellState {
+                                cell: new,
+                                distance,
+                                visit_order,
+                            },
+                        );
+                    }
+                }
+                // If no initial, ignore update
+            }
+        }
+    }
+
+    /// Get the gradient color of a visited cell at the given coordinate in the given display mode,
+    /// if the display mode colors it
+    fn mode_color(&self, coord: (u16, u16), mode: DisplayMode) -> Option<Color> {
+        let state = self.changes.get(&coord)?;
+        if state.cell != GridCell::VISITED {
+            return None;
+        }
+        match (mode, state.distance, state.visit_order) {
+            (DisplayMode::Heatmap, Some(distance), _) => {
+                let frac = distance as f32 / self.max_distance.max(1) as f32;
+                Some(Theme::current().adapt(gradient_color(&HEAT_GRADIENT, frac)))
+            }
+            (DisplayMode::VisitOrder, _, Some(order)) => {
+                let frac = order as f32 / self.visit_count.saturating_sub(1).max(1) as f32;
+                Some(Theme::current().adapt(gradient_color(&AGE_GRADIENT, frac)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the styled content to print for the cell at the given coordinate in the given display mode.
+    /// With [`CellLayout::HalfBlock`], this is the half block showing the pair of grid rows packed
+    /// into the cell's terminal row.
+    fn styled_cell(&self, coord: (u16, u16), mode: DisplayMode) -> Option<StyledContent<String>> {
+        match self.viewport.layout {
+            CellLayout::Full => {
+                let cell = self.cell_at(coord)?;
+                let styled = match self.mode_color(coord, mode) {
+                    Some(color) => "  ".to_string().on(color),
+                    None => cell.to_string().stylize(),
+                };
+                Some(styled)
+            }
+            CellLayout::HalfBlock => {
+                let (x, y) = coord;
+                let top = y - (y - self.viewport.offset.1) % 2;
+                // The bottom row of the pair may be past the end of the grid
+                let block_color = |y| {
+                    self.cell_at((x, y))
+                        .filter(|_| self.dims().is_some_and(|(_, height)| y < height))
+                        .and_then(|cell| self.mode_color((x, y), mode).or(cell.color()))
+                };
+                Some(half_block(block_color(top), block_color(top + 1)))
+            }
+        }
+    }
+
+    /// Draw a single cell at the given grid coordinate. Does nothing if initial not set.
+    pub(super) fn draw_cell(
+        &self,
+        stdout: &mut impl Write,
+        coord: (u16, u16),
+        mode: DisplayMode,
+    ) -> std::io::Result<()> {
+        if let Some(position) = self.viewport.to_screen(coord)
+            && let Some(styled) = self.styled_cell(coord, mode)
+        {
+            queue!(
+                stdout,
+                cursor::MoveTo(position.0, position.1),
+                style::PrintStyledContent(styled)
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Render the compact state to the provided stdout. This draws the visible part of the initial
+    /// filled grid, then overlays changed cells. Does nothing if initial not set.
+    pub(super) fn recover(
+        &self,
+        stdout: &mut impl Write,
+        mode: DisplayMode,
+    ) -> std::io::Result<()> {
+        if let (Some((initial, _, _)), Some((width, height))) = (self.initial, self.visible_dims())
+        {
+            let (origin_x, origin_y) = self.viewport.origin;
+            if self.viewport.layout == CellLayout::HalfBlock {
+                // Each half block depends on two cells, so draw every visible pair row by row
+                let (offset_x, offset_y) = self.viewport.offset;
+                for row in 0..height.div_ceil(2) {
+                    stdout.queue(cursor::MoveTo(origin_x, origin_y + row))?;
+                    for x in offset_x..offset_x + width {
+                        if let Some(styled) = self.styled_cell((x, offset_y + 2 * row), mode) {
+                            stdout.queue(style::PrintStyledContent(styled))?;
+                        }
+                    }
+                }
+                return stdout.flush();
+            }
+            // Render initial filled grid, moving to the start of each row so any origin works
+            for y in 0..height {
+                stdout.queue(cursor::MoveTo(origin_x, origin_y + y))?;
+                for _x in 0..width {
+                    stdout.queue(style::Print(initial))?;
+                }
+            }
+            // Overlay changed cells
+            for &coord in self.changes.keys() {
+                self.draw_cell(stdout, coord, mode)?;
+            }
+            stdout.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Half block character showing the top color in its upper half and the bottom color in its lower
+/// half. Missing colors are left transparent.
+fn half_block(top: Option<Color>, bottom: Option<Color>) -> StyledContent<String> {
+    match (top, bottom) {
+        (None, None) => " ".to_string().stylize(),
+        (Some(top), None) => "▀".to_string().with(top),
+        (None, Some(bottom)) => "▄".to_string().with(bottom),
+        (Some(top), Some(bottom)) => "▀".to_string().with(top).on(bottom),
+    }
+}
+
+/// Blue -> green -> yellow -> red gradient for distances, evenly spaced color stops
+const HEAT_GRADIENT: [(f32, f32, f32); 4] = [
+    (40.0, 90.0, 220.0),
+    (40.0, 200.0, 120.0),
+    (240.0, 220.0, 60.0),
+    (220.0, 50.0, 40.0),
+];
+/// Dark purple -> teal -> light yellow gradient for visit order, evenly spaced color stops
+const AGE_GRADIENT: [(f32, f32, f32); 3] = [
+    (68.0, 20.0, 90.0),
+    (30.0, 150.0, 140.0),
+    (250.0, 240.0, 150.0),
+];
+
+/// Map a fraction in [0, 1] to a color on the gradient given by at least two color stops
+fn gradient_color(stops: &[(f32, f32, f32)], frac: f32) -> Color {
+    let scaled = frac.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+    let i = (scaled.floor() as usize).min(stops.len() - 2);
+    let t = scaled - i as f32;
+    let lerp = |a: f32, b: f32| (a + (b - a) * t).round() as u8;
+    let (from, to) = (stops[i], stops[i + 1]);
+    Color::Rgb {
+        r: lerp(from.0, to.0),
+        g: lerp(from.1, to.1),
+        b: lerp(from.2, to.2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visited_cells_track_distance_from_start() {
+        let mut state = GridState::default();
+        state.add_event(GridEvent::Initial {
+            cell: GridCell::WALL,
+            width: 7,
+            height: 3,
+        });
+        let update = |coord, new| GridEvent::Update {
+            coord,
+            old: GridCell::WALL,
+            new,
+        };
+        // Carve a corridor of three maze cells: (1, 1) - (3, 1) - (5, 1)
+        for x in 1..6 {
+            state.add_event(update((x, 1), GridCell::EMPTY));
+        }
+        state.add_event(update((1, 1), GridCell::START));
+        state.add_event(update((3, 1), GridCell::VISITED));
+        state.add_event(update((5, 1), GridCell::VISITED));
+
+        assert_eq!(state.changes[&(1, 1)].distance, Some(0));
+        assert_eq!(state.changes[&(3, 1)].distance, Some(1));
+        assert_eq!(state.changes[&(5, 1)].distance, Some(2));
+        assert_eq!(state.max_distance, 2);
+        assert_eq!(state.changes[&(3, 1)].visit_order, Some(0));
+        assert_eq!(state.changes[&(5, 1)].visit_order, Some(1));
+
+        // Reverting the newest visited cell frees up its visit order
+        state.add_event(update((5, 1), GridCell::EMPTY));
+        assert_eq!(state.visit_count, 1);
+        state.add_event(update((5, 1), GridCell::VISITED));
+        assert_eq!(state.changes[&(5, 1)].visit_order, Some(1));
+    }
+
+    #[test]
+    fn half_block_viewport_keeps_row_pairs() {
+        let mut state = GridState::with_origin((0, 0), CellLayout::HalfBlock);
+        state.add_event(GridEvent::Initial {
+            cell: GridCell::WALL,
+            width: 11,
+            height: 11,
+        });
+        // 5 terminal rows hold 10 grid rows
+        state.set_view_size((11, 10));
+        assert_eq!(state.screen_dims(), Some((11, 5)));
+
+        // Scrolling to the bottom rounds up to an even row, so the last grid row is still shown
+        state.pan(0, 5);
+        assert_eq!(state.viewport.offset, (0, 2));
+        assert_eq!(state.viewport.to_screen((3, 10)), Some((3, 4)));
+        assert_eq!(state.viewport.to_screen((3, 9)), Some((3, 3)));
+        assert_eq!(state.viewport.to_screen((3, 1)), None);
+    }
+}
@@ -1,59 +1,379 @@
-use crate::maze::grid::GridEvent;
-use std::collections::VecDeque;
+use crate::{
+    app::visualize::grid_state::GridSnapshot,
+    maze::{cell::GridCell, grid::GridEvent},
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::PathBuf,
+};
 
+use super::grid_state::GridState;
+
+/// Number of events stored in each chunk of the history
+const CHUNK_EVENTS: usize = 4096;
+/// Minimum number of events between two keyframes. Keyframes are spaced further apart when
+/// the grid state is larger, so keyframes never take much more memory than the events themselves.
+const MIN_KEYFRAME_INTERVAL: usize = 1024;
+
+/// Phase of a run, generating the maze or solving it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    /// Starts with a [`GridEvent::Initial`]
+    Generation,
+    /// Starts when the start cell is placed on the generated maze
+    Solving,
+}
+
+impl std::fmt::Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Phase::Generation => write!(f, "generating"),
+            Phase::Solving => write!(f, "solving"),
+        }
+    }
+}
+
+/// A chunk of encoded grid events
+enum Chunk {
+    /// Encoded events kept in memory
+    Memory(Vec<u8>),
+    /// Encoded events written to the spill file, starting at the given byte offset
+    Spilled { offset: u64 },
+}
+
+/// Temporary file that older chunks are moved to, removed when dropped
+struct SpillFile {
+    file: File,
+    path: PathBuf,
+    /// Number of bytes written so far
+    len: u64,
+}
+
+impl SpillFile {
+    fn create() -> std::io::Result<Self> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        let path = std::env::temp_dir().join(format!(
+            "mazest-history-{}-{}.bin",
+            std::process::id(),
+            nanos
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        tracing::info!("Spilling rendering history to {}", path.display());
+        Ok(SpillFile { file, path, len: 0 })
+    }
+
+    /// Append bytes to the end of the file, returning the offset they were written at
+    fn append(&mut self, bytes: &[u8]) -> std::io::Result<u64> {
+        let offset = self.len;
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(bytes)?;
+        self.len += bytes.len() as u64;
+        Ok(offset)
+    }
+
+    /// Read a full chunk written at the given offset
+    fn read_chunk(&mut self, offset: u64) -> std::io::Result<Vec<u8>> {
+        let mut bytes = vec![0; CHUNK_EVENTS * GridEvent::ENCODED_LEN];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::warn!("Failed to remove history spill file: {}", e);
+        }
+    }
+}
+
+/// Complete history of rendered grid events, so the whole run can be browsed.
+///
+/// Events are stored encoded in fixed size chunks. Once more than the in-memory budget of events
+/// is stored, the oldest full chunks are moved to a temporary file. Snapshots of the grid state
+/// are kept as keyframes, so any point of the history can be recovered by restoring the nearest
+/// keyframe and replaying the events after it.
 pub struct GridEventHistory {
-    /// History of grid events, with the most recent event at the front.
-    event_history: VecDeque<GridEvent>,
-    /// Current index in the history for browsing. Should always be between 0 and event_history.len() - 1
-    /// 0 represents the most recent event.
-    history_index: usize,
-    /// Maximum number of events to keep in history. If 0, no history is kept.
-    max_num_events: usize,
+    /// Chunks of encoded events, oldest first. All chunks but the last one are full.
+    chunks: Vec<Chunk>,
+    /// Total number of events stored
+    len: usize,
+    /// Number of events currently applied to the displayed grid. Equal to `len` unless browsing.
+    position: usize,
+    /// Grid state snapshots, taken after the given number of events, in increasing order
+    keyframes: Vec<(usize, GridSnapshot)>,
+    /// Index of the first event of each phase, in increasing order
+    phases: Vec<(usize, Phase)>,
+    /// Maximum number of events kept in memory before older chunks are spilled to disk
+    max_in_memory_events: usize,
+    /// Number of chunks kept in memory
+    num_in_memory_chunks: usize,
+    /// Spill file, created on first use. Stays None if it could not be created.
+    spill_file: Option<SpillFile>,
+    /// Whether creating the spill file failed, in which case everything stays in memory
+    spill_failed: bool,
+    /// Most recently read spilled chunk and its index, since browsing reads events in sequence
+    cached_chunk: Option<(usize, Vec<u8>)>,
 }
 
 impl GridEventHistory {
-    pub fn new(max_num_events: usize) -> Self {
+    pub fn new(max_in_memory_events: usize) -> Self {
         GridEventHistory {
-            event_history: VecDeque::with_capacity(max_num_events),
-            history_index: 0,
-            max_num_events,
+            chunks: Vec::new(),
+            len: 0,
+            position: 0,
+            keyframes: Vec::new(),
+            phases: Vec::new(),
+            max_in_memory_events,
+            num_in_memory_chunks: 0,
+            spill_file: None,
+            spill_failed: false,
+            cached_chunk: None,
         }
     }
 
-    pub fn history_forward(&mut self) -> Option<GridEvent> {
-        match self.history_index {
-            0 => None, // Already at the most recent event
-            _ => {
-                self.history_index -= 1;
-                self.event_history.get(self.history_index).copied()
+    /// Total number of events stored
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Number of events currently applied to the displayed grid
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Index of the run containing the event at the given index, counting the runs from 0.
+    /// Every run starts with a generation phase.
+    pub fn run_containing(&self, index: usize) -> Option<usize> {
+        self.phases
+            .iter()
+            .take_while(|(start, _)| *start <= index)
+            .filter(|(_, phase)| *phase == Phase::Generation)
+            .count()
+            .checked_sub(1)
+    }
+
+    /// Phase containing the event at the given index, and the index range of its events
+    pub fn phase_containing(&self, index: usize) -> Option<(Phase, Range<usize>)> {
+        let i = self
+            .phases
+            .partition_point(|(start, _)| *start <= index)
+            .checked_sub(1)?;
+        let (start, phase) = self.phases[i];
+        let end = self.phases.get(i + 1).map_or(self.len, |(next, _)| *next);
+        Some((phase, start..end))
+    }
+
+    /// Get the event at the given index, reading it back from disk if it was spilled
+    fn get(&mut self, index: usize) -> std::io::Result<Option<GridEvent>> {
+        if index >= self.len {
+            return Ok(None);
+        }
+        let (chunk_index, event_index) = (index / CHUNK_EVENTS, index % CHUNK_EVENTS);
+        let bytes = match &self.chunks[chunk_index] {
+            Chunk::Memory(bytes) => bytes,
+            Chunk::Spilled { offset } => {
+                if self.cached_chunk.as_ref().map(|(i, _)| *i) != Some(chunk_index) {
+                    let spill_file = self.spill_file.as_mut().ok_or_else(|| {
+                        std::io::Error::other("History chunk was spilled without a spill file")
+                    })?;
+                    self.cached_chunk = Some((chunk_index, spill_file.read_chunk(*offset)?));
+                }
+                &self.cached_chunk.as_ref().expect("Chunk was just cached").1
+            }
+        };
+        let start = event_index * GridEvent::ENCODED_LEN;
+        let encoded = bytes[start..start + GridEvent::ENCODED_LEN]
+            .try_into()
+            .expect("Slice has the encoded event length");
+        GridEvent::decode(encoded)
+            .map(Some)
+            .ok_or_else(|| std::io::Error::other("Corrupted grid event in history"))
+    }
+
+    /// Step forward, returning the next event to apply, if any
+    pub fn history_forward(&mut self) -> std::io::Result<Option<GridEvent>> {
+        let event = self.get(self.position)?;
+        if event.is_some() {
+            self.position += 1;
+        }
+        Ok(event)
+    }
+
+    /// Step backward, returning the most recently applied event that should be reverted.
+    /// The oldest event can't be reverted, since there's no grid before it.
+    pub fn history_backward(&mut self) -> std::io::Result<Option<GridEvent>> {
+        if self.position <= 1 {
+            return Ok(None);
+        }
+        self.position -= 1;
+        self.get(self.position)
+    }
+
+    /// Append an event that was just applied to `state`, moving to the end of the history.
+    /// A keyframe of `state` is taken when one is due.
+    pub fn add_event(&mut self, event: GridEvent, state: &GridState) -> std::io::Result<()> {
+        if self.len.is_multiple_of(CHUNK_EVENTS) {
+            self.chunks.push(Chunk::Memory(Vec::with_capacity(
+                CHUNK_EVENTS * GridEvent::ENCODED_LEN,
+            )));
+            self.num_in_memory_chunks += 1;
+            self.spill_chunks()?;
+        }
+        match self.chunks.last_mut() {
+            Some(Chunk::Memory(bytes)) => bytes.extend_from_slice(&event.encode()),
+            _ => unreachable!("The last chunk is never spilled"),
+        }
+        // Placing the start cell after the maze was generated begins the solving phase
+        match event {
+            GridEvent::Initial { .. } => self.phases.push((self.len, Phase::Generation)),
+            GridEvent::Update { new, .. }
+                if new == GridCell::START
+                    && matches!(self.phases.last(), Some((_, Phase::Generation))) =>
+            {
+                self.phases.push((self.len, Phase::Solving))
             }
+            GridEvent::Update { .. } => {}
         }
+        self.len += 1;
+        self.position = self.len;
+
+        // A new grid is cheap to snapshot, and saves replaying the previous grid when seeking past it
+        let keyframe_due = match self.keyframes.last() {
+            Some((position, snapshot)) => {
+                self.len - position >= MIN_KEYFRAME_INTERVAL.max(snapshot.len())
+            }
+            None => true,
+        };
+        if keyframe_due || matches!(event, GridEvent::Initial { .. }) {
+            self.keyframes.push((self.len, state.snapshot()));
+        }
+        Ok(())
     }
 
-    pub fn history_backward(&mut self) -> Option<GridEvent> {
-        if self.history_index + 1 >= self.event_history.len() {
-            None
-        } else {
-            self.history_index += 1;
-            self.event_history.get(self.history_index).copied()
+    /// Move the oldest in-memory full chunks to the spill file while over the in-memory budget
+    fn spill_chunks(&mut self) -> std::io::Result<()> {
+        while !self.spill_failed
+            && self.num_in_memory_chunks * CHUNK_EVENTS > self.max_in_memory_events
+        {
+            if self.spill_file.is_none() {
+                match SpillFile::create() {
+                    Ok(spill_file) => self.spill_file = Some(spill_file),
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to create history spill file, keeping history in memory: {}",
+                            e
+                        );
+                        self.spill_failed = true;
+                        break;
+                    }
+                }
+            }
+            // Oldest chunk still in memory, never the last one being filled
+            let index = self.chunks.len() - self.num_in_memory_chunks;
+            if index + 1 >= self.chunks.len() {
+                break;
+            }
+            if let Chunk::Memory(bytes) = &self.chunks[index] {
+                let spill_file = self
+                    .spill_file
+                    .as_mut()
+                    .expect("Spill file was just created");
+                let offset = spill_file.append(bytes)?;
+                self.chunks[index] = Chunk::Spilled { offset };
+            }
+            self.num_in_memory_chunks -= 1;
         }
+        Ok(())
     }
 
-    pub fn add_event(&mut self, current_event: GridEvent) {
-        match self.max_num_events {
-            0 => {} // No history to maintain
-            _ => {
-                // Remove oldest events if we exceed max history size
-                self.event_history.truncate(self.max_num_events - 1);
-                // Add new event to the front of the history
-                self.event_history.push_front(current_event);
-                // Reset history index to the most recent event
-                self.history_index = 0;
+    /// Move to the given position, restoring `state` to the grid after that many events.
+    /// The nearest keyframe at or before the position is restored, then the events after it replayed.
+    pub fn seek(&mut self, position: usize, state: &mut GridState) -> std::io::Result<()> {
+        let position = position.min(self.len);
+        let keyframe = self
+            .keyframes
+            .partition_point(|(keyframe_position, _)| *keyframe_position <= position);
+        let start = match keyframe.checked_sub(1) {
+            Some(index) => {
+                let (keyframe_position, snapshot) = &self.keyframes[index];
+                state.restore(snapshot);
+                *keyframe_position
+            }
+            None => {
+                state.restore(&GridSnapshot::default());
+                0
+            }
+        };
+        for index in start..position {
+            if let Some(event) = self.get(index)? {
+                state.add_event(event);
             }
         }
+        self.position = position;
+        Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seek_recovers_state_across_spilled_chunks_and_grids() {
+        // Keep a single chunk in memory so older chunks are spilled
+        let mut history = GridEventHistory::new(CHUNK_EVENTS);
+        let mut state = GridState::default();
+        let mut add = |history: &mut GridEventHistory, event| {
+            state.add_event(event);
+            history.add_event(event, &state).unwrap();
+        };
+        let update = |i: usize, new| GridEvent::Update {
+            coord: ((i % 200) as u16, (i / 200) as u16),
+            old: GridCell::WALL,
+            new,
+        };
+        for _ in 0..2 {
+            add(
+                &mut history,
+                GridEvent::Initial {
+                    cell: GridCell::WALL,
+                    width: 200,
+                    height: 100,
+                },
+            );
+            for i in 0..3 * CHUNK_EVENTS {
+                add(&mut history, update(i, GridCell::EMPTY));
+            }
+        }
+        assert_eq!(history.len(), 2 * (3 * CHUNK_EVENTS + 1));
+        assert!(history.spill_file.is_some());
+
+        // Halfway through the first grid, then back to the end of it after the second grid started
+        let mut recovered = GridState::default();
+        history.seek(1 + CHUNK_EVENTS + 10, &mut recovered).unwrap();
+        assert_eq!(recovered.snapshot().len(), CHUNK_EVENTS + 10);
+        history.seek(3 * CHUNK_EVENTS + 1, &mut recovered).unwrap();
+        assert_eq!(recovered.snapshot().len(), 3 * CHUNK_EVENTS);
 
-    pub fn current_event(&self) -> Option<GridEvent> {
-        self.event_history.get(self.history_index).copied()
+        // Stepping back from the start of the second grid reverts its initial event
+        history.history_forward().unwrap();
+        assert!(matches!(
+            history.history_backward().unwrap(),
+            Some(GridEvent::Initial { .. })
+        ));
+        assert!(matches!(
+            history.history_backward().unwrap(),
+            Some(GridEvent::Update { .. })
+        ));
     }
 }
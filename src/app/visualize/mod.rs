@@ -1,12 +1,18 @@
+mod cast;
+mod gif_export;
+mod grid_state;
 mod history;
+mod race;
 mod renderer;
+mod replay;
 
 use std::{
     io::Stdout,
+    path::Path,
     sync::{
         Arc,
         atomic::AtomicBool,
-        mpsc::{Receiver, Sender},
+        mpsc::{Receiver, Sender, SyncSender},
     },
     time::Duration,
 };
@@ -16,16 +22,22 @@ use crossterm::{
     event::{self, KeyCode},
     execute,
     style::{self, Attribute, Color, Stylize},
+    terminal,
 };
 use rand::Rng;
 
 use crate::{
     app::{
-        self,
+        self, Options,
+        cli::GifOptions,
+        keymap::{Action, Context, Keymap},
+        visualize::cast::CastRecorder,
         visualize::renderer::{Renderer, RendererStatus},
+        visualize::replay::{ReplayHeader, ReplayRecorder},
     },
+    config::{Config, theme},
     generators::{Generator, generate_maze},
-    maze::{Maze, grid::GridEvent},
+    maze::{Maze, cell::CellLayout, grid::GridEvent},
     solvers::{Solver, solve_maze},
 };
 
@@ -34,6 +46,15 @@ enum UserInputEvent {
     Resize,
 }
 
+/// Direction to pan the viewport of a grid larger than the terminal
+#[derive(Debug, Clone, Copy)]
+pub enum PanDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
 #[derive(Debug)]
 pub enum UserActionEvent {
     /// Pause the animation
@@ -50,10 +71,39 @@ pub enum UserActionEvent {
     SpeedUp,
     /// Decrease animation speed
     SlowDown,
+    /// Toggle coloring visited cells by their distance from the start cell
+    ToggleHeatmap,
+    /// Toggle coloring visited cells by the order they were visited
+    ToggleVisitOrder,
+    /// Pan the viewport by one maze cell, or by half the visible region if `page` is set
+    Pan { direction: PanDirection, page: bool },
+    /// Toggle keeping the most recently updated cell in view
+    ToggleFollow,
+    /// Jump several events backward in the history
+    JumpBackward,
+    /// Jump several events forward in the history, or into the future
+    JumpForward,
+    /// Jump to the start of the current phase, or of the previous one if already there
+    PhaseStart,
+    /// Jump to the end of the current phase, or of the next one if already there
+    PhaseEnd,
+    /// Seek to the given percentage of the history
+    SeekPercent(u8),
+    /// Draw the key bindings over the grid, holding the animation until they are hidden
+    ShowHelp,
+    /// Hide the key bindings and redraw the grid
+    HideHelp,
     /// Cancel rendering
     Cancel,
 }
 
+/// Algorithms of a run, sent by the grid event source before the grid events of the run
+#[derive(Debug, Clone, Copy)]
+struct RunInfo {
+    generator: Generator,
+    solver: Solver,
+}
+
 /// Maximum number of grid events to buffer in the channel between compute and render threads
 const MAX_EVENTS_IN_CHANNEL_BUFFER: usize = 1000;
 /// Timeout for receiving input events, a.k.a. how often to check for render done/cancel flags
@@ -61,21 +111,193 @@ const INPUT_RECV_TIMEOUT: Duration = Duration::from_millis(100);
 /// Timeout for polling input events in the input thread, a.k.a.
 /// how often to check for render done/cancel flags
 const USER_INPUT_EVENT_POLL_TIMEOUT: Duration = Duration::from_millis(100);
-/// Maximum number of grid events to keep for history browsing when paused or grid state
-/// recovery
-const MAX_HISTORY_GRID_EVENTS: usize = 100;
+/// Maximum number of grid events of the rendering history to keep in memory. The whole run is kept
+/// for browsing when paused, older events are moved to a temporary file past this limit.
+const MAX_IN_MEMORY_HISTORY_EVENTS: usize = 1 << 20;
+
+/// How mazes are laid out on screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Layout {
+    /// One maze generated and solved at a time
+    Single,
+    /// One maze solved by two solvers side by side
+    RaceTwo,
+    /// One maze solved by four solvers in a 2x2 grid
+    RaceFour,
+}
+
+impl std::fmt::Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Layout::Single => write!(f, "Single maze"),
+            Layout::RaceTwo => write!(f, "Race 2 solvers side by side on the same maze"),
+            Layout::RaceFour => write!(f, "Race all 4 solvers on the same maze"),
+        }
+    }
+}
+
+impl Layout {
+    /// Number of (columns, rows) of panes in this layout
+    fn panes(&self) -> (u16, u16) {
+        match self {
+            Layout::Single => (1, 1),
+            Layout::RaceTwo => (2, 1),
+            Layout::RaceFour => (2, 2),
+        }
+    }
+}
+
+impl std::fmt::Display for CellLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CellLayout::Full => write!(f, "Full (symbols, two columns per cell)"),
+            CellLayout::HalfBlock => write!(f, "Compact (half blocks, fits 4x more cells)"),
+        }
+    }
+}
+
+/// Ask user for a maze solving algorithm, printing the selection
+/// Returns None if user cancels input with Esc
+fn ask_solver(
+    stdout: &mut Stdout,
+    prompt: &str,
+    default: Option<Solver>,
+) -> std::io::Result<Option<Solver>> {
+    let solver = app::select_from_menu(stdout, prompt, &app::SOLVERS, default)?;
+    if let Some(solver) = solver {
+        stdout.execute(style::PrintStyledContent(
+            format!("Selected solver: {}\r\n", solver)
+                .with(theme::ui().success)
+                .attribute(Attribute::Bold),
+        ))?;
+    }
+    Ok(solver)
+}
+
+/// Ask user how to draw grid cells
+/// Returns None if user cancels input with Esc
+fn ask_cell_layout(stdout: &mut Stdout) -> std::io::Result<Option<CellLayout>> {
+    // Compact cells are colored blocks, which can't be told apart without colors
+    let layouts: &[CellLayout] = if theme::Theme::current().no_color {
+        &[CellLayout::Full]
+    } else {
+        &[CellLayout::Full, CellLayout::HalfBlock]
+    };
+    app::select_from_menu(
+        stdout,
+        "Select cell style (use arrow keys and Enter, or Esc to exit):",
+        layouts,
+        None,
+    )
+}
+
+/// Print the animation controls
+fn print_controls(stdout: &mut Stdout) -> std::io::Result<()> {
+    stdout.execute(style::PrintStyledContent(
+        "Controls:\r\n"
+            .with(theme::ui().accent)
+            .attribute(Attribute::Bold),
+    ))?;
+    for line in Keymap::current().help(Context::Visualize) {
+        stdout.execute(style::PrintStyledContent(
+            format!("  {}\r\n", line).with(theme::ui().info),
+        ))?;
+    }
+    stdout.execute(style::Print("\r\n"))?;
+    Ok(())
+}
+
+/// Print a message followed by an exit prompt, and wait for the user to press Esc
+fn wait_for_exit(stdout: &mut Stdout, message: &str, color: Color) -> std::io::Result<()> {
+    stdout.execute(style::PrintStyledContent(
+        message.with(color).attribute(Attribute::Bold),
+    ))?;
+    stdout.execute(style::PrintStyledContent(
+        "Press Esc to exit...\r"
+            .with(theme::ui().hint)
+            .attribute(Attribute::Bold),
+    ))?;
+    app::wait_for_keypress(KeyCode::Esc)
+}
 
-/// Entry point of the visualizer app
-pub fn run(stdout: &mut Stdout) -> std::io::Result<()> {
+/// Create the asciicast recording if requested, before starting so a bad path is reported right away.
+/// Returns the error message if the file can't be created.
+fn create_cast_recorder(
+    options: &Options,
+) -> std::io::Result<Result<Option<CastRecorder>, String>> {
+    let Some(path) = &options.record else {
+        return Ok(Ok(None));
+    };
+    Ok(CastRecorder::create(path, terminal::size()?)
+        .map(Some)
+        .map_err(|e| format!("Failed to create recording {}: {}. ", path.display(), e)))
+}
+
+/// Generate and solve a maze without rendering it, and write the animation to a GIF file.
+/// Prints a summary once done.
+pub fn export_gif(path: &Path, options: &GifOptions) -> std::io::Result<()> {
+    let summary = gif_export::export(path, options)?;
+    println!(
+        "Wrote {} ({} frames, {} events per frame, {} KB){}",
+        path.display(),
+        summary.frames,
+        summary.events_per_frame,
+        summary.bytes.div_ceil(1024),
+        if summary.solved {
+            ""
+        } else {
+            ", goal not reached"
+        }
+    );
+    Ok(())
+}
+
+/// Entry point of the visualizer app, with the menus preselecting the `config` defaults
+pub fn run(stdout: &mut Stdout, options: &Options, config: &Config) -> std::io::Result<()> {
     execute!(
         stdout,
         style::SetAttribute(Attribute::Reverse),
-        style::PrintStyledContent("Visualization Mode\r\n".with(Color::Yellow)),
+        style::PrintStyledContent("Visualization Mode\r\n".with(theme::ui().accent)),
         style::SetAttribute(Attribute::NoReverse),
     )?;
 
+    // Ask user for the layout
+    let layout = match app::select_from_menu(
+        stdout,
+        "Select visualization layout (use arrow keys and Enter, or Esc to exit):",
+        &[Layout::Single, Layout::RaceTwo, Layout::RaceFour],
+        None,
+    )? {
+        Some(layout) => layout,
+        None => {
+            return Ok(());
+        }
+    };
+
+    // Ask user how to draw grid cells
+    let Some(cell_layout) = ask_cell_layout(stdout)? else {
+        return Ok(());
+    };
+
     // Ask user for maze dimensions
-    let (width, height) = match app::ask_maze_dimensions(stdout)? {
+    let dims = match layout {
+        // Single mazes larger than the terminal can be panned around
+        Layout::Single => app::ask_maze_dimensions(
+            stdout,
+            |term_width, term_height| app::fit_maze_dims(term_width, term_height, cell_layout),
+            true,
+            (config.width, config.height),
+        )?,
+        _ => app::ask_maze_dimensions(
+            stdout,
+            |term_width, term_height| {
+                race::fit_pane_maze_dims(term_width, term_height, layout.panes(), cell_layout)
+            },
+            false,
+            (config.width, config.height),
+        )?,
+    };
+    let (width, height) = match dims {
         Some(dims) => dims,
         None => {
             return Ok(());
@@ -87,11 +309,12 @@ pub fn run(stdout: &mut Stdout) -> std::io::Result<()> {
         stdout,
         "Select maze generation algorithm (use arrow keys and Enter, or Esc to exit):",
         &app::GENERATORS,
+        config.generator,
     )? {
         Some(generator) => {
             stdout.execute(style::PrintStyledContent(
                 format!("Selected generator: {}\r\n", generator)
-                    .with(Color::Green)
+                    .with(theme::ui().success)
                     .attribute(Attribute::Bold),
             ))?;
             generator
@@ -101,50 +324,291 @@ pub fn run(stdout: &mut Stdout) -> std::io::Result<()> {
         }
     };
 
-    // Ask user for maze solving algorithm
-    let mut solver = match app::select_from_menu(
-        stdout,
-        "Select maze solving algorithm (use arrow keys and Enter, or Esc to exit):",
-        &app::SOLVERS,
-    )? {
-        Some(solver) => {
+    // Ask user for maze solving algorithm(s)
+    let solvers = match layout {
+        Layout::Single => {
+            let Some(solver) = ask_solver(
+                stdout,
+                "Select maze solving algorithm (use arrow keys and Enter, or Esc to exit):",
+                config.solver,
+            )?
+            else {
+                return Ok(());
+            };
+            vec![solver]
+        }
+        Layout::RaceTwo => {
+            let Some(first) = ask_solver(
+                stdout,
+                "Select the first racing solver (use arrow keys and Enter, or Esc to exit):",
+                config.solver,
+            )?
+            else {
+                return Ok(());
+            };
+            let Some(second) = ask_solver(
+                stdout,
+                "Select the second racing solver (use arrow keys and Enter, or Esc to exit):",
+                None,
+            )?
+            else {
+                return Ok(());
+            };
+            vec![first, second]
+        }
+        Layout::RaceFour => app::SOLVERS.to_vec(),
+    };
+    // Selections to save as defaults. Races only have a first solver to save.
+    let selections = Config {
+        mode: Some(app::AppMode::Visualize),
+        width: Some(width),
+        height: Some(height),
+        generator: Some(generator),
+        solver: (layout != Layout::RaceFour)
+            .then_some(solvers[0])
+            .or(config.solver),
+        ..config.clone()
+    };
+
+    if layout != Layout::Single {
+        if !app::ask_start(stdout, &selections)? {
+            return Ok(());
+        }
+        print_controls(stdout)?;
+        if options.record.is_some() || options.save_replay.is_some() {
             stdout.execute(style::PrintStyledContent(
-                format!("Selected solver: {}\r\n", solver)
-                    .with(Color::Green)
-                    .attribute(Attribute::Bold),
+                "Recording is only available for a single maze, racing without recording\r\n"
+                    .with(theme::ui().accent),
             ))?;
-            solver
         }
-        None => {
-            return Ok(());
+        return race::run(
+            stdout,
+            (width, height),
+            generator,
+            &solvers,
+            layout.panes().0,
+            cell_layout,
+            config.speed,
+        );
+    }
+    let mut solver = solvers[0];
+
+    // Ask if user wants to loop generation and solving. A replay holds a single run.
+    let loop_animation = if options.save_replay.is_some() {
+        stdout.execute(style::PrintStyledContent(
+            "Saving a replay of a single run, looping is off\r\n".with(theme::ui().accent),
+        ))?;
+        false
+    } else {
+        match app::select_from_menu(
+            stdout,
+            "Loop maze generation and solving? Will randomize generator & solver combination. (use arrow keys and Enter, or Esc to exit):",
+            &["Yes", "No"],
+            config
+                .loop_animation
+                .map(|looping| if looping { "Yes" } else { "No" }),
+        )? {
+            Some(choice) => choice == "Yes",
+            None => {
+                return Ok(());
+            }
         }
     };
+    let selections = Config {
+        loop_animation: options
+            .save_replay
+            .is_none()
+            .then_some(loop_animation)
+            .or(config.loop_animation),
+        ..selections
+    };
+    if !app::ask_start(stdout, &selections)? {
+        return Ok(());
+    }
+    print_controls(stdout)?;
 
+    let recorder = match create_cast_recorder(options)? {
+        Ok(recorder) => recorder,
+        Err(message) => return wait_for_exit(stdout, &message, theme::ui().error),
+    };
+    // Replays are generated with a known seed, so the header describes the run exactly
+    let replay_recorder = match &options.save_replay {
+        Some(path) => {
+            let header = ReplayHeader {
+                size: (width, height),
+                generator,
+                solver,
+                seed: rand::rng().random(),
+            };
+            match ReplayRecorder::create(path, &header) {
+                Ok(replay_recorder) => Some((replay_recorder, header.seed)),
+                Err(e) => {
+                    let message = format!("Failed to create replay {}: {}. ", path.display(), e);
+                    return wait_for_exit(stdout, &message, theme::ui().error);
+                }
+            }
+        }
+        None => None,
+    };
+
+    // Generate the maze and solve it while rendering
+    let (completed, outcome) = animate(
+        (width, height),
+        cell_layout,
+        recorder,
+        options.fast_export,
+        config,
+        move |grid_event_tx, run_info_tx, should_stop| -> std::io::Result<bool> {
+            // Errors only happen once the renderer has stopped, which the compute thread notices
+            run_info_tx.send(RunInfo { generator, solver }).ok();
+            if let Some((replay_recorder, seed)) = replay_recorder {
+                let (grid_event_tx, recorder_handle) = replay_recorder.spawn(grid_event_tx);
+                let goal_reached =
+                    compute(width, height, grid_event_tx, generator, solver, Some(seed));
+                recorder_handle
+                    .join()
+                    .expect("Replay recorder thread panicked")?;
+                return Ok(goal_reached);
+            }
+            if !loop_animation {
+                return Ok(compute(
+                    width,
+                    height,
+                    grid_event_tx,
+                    generator,
+                    solver,
+                    None,
+                ));
+            }
+            // Looping mode: randomly select generator and solver each iteration
+            let mut rng = rand::rng();
+            loop {
+                let goal_reached = compute(
+                    width,
+                    height,
+                    grid_event_tx.clone(),
+                    generator,
+                    solver,
+                    None,
+                );
+                // Check if rendering was cancelled
+                if should_stop.load(std::sync::atomic::Ordering::Acquire) {
+                    tracing::info!("Compute thread detected render cancel, exiting loop");
+                    return Ok(goal_reached);
+                }
+                // Randomly select new generator and solver combination for next iteration
+                (generator, solver) = app::COMBOS[rng.random_range(0..app::COMBOS.len())];
+                run_info_tx.send(RunInfo { generator, solver }).ok();
+            }
+        },
+    )?;
+
+    let goal_reached = match outcome {
+        Ok(goal_reached) => goal_reached,
+        Err(e) => {
+            tracing::error!("Failed to save replay: {}", e);
+            return wait_for_exit(
+                stdout,
+                &format!("Failed to save replay: {}. ", e),
+                theme::ui().error,
+            );
+        }
+    };
+    if let RendererStatus::Cancelled = completed {
+        tracing::info!("Rendering was cancelled by user.");
+        return Ok(());
+    }
+
+    let msg = if goal_reached {
+        "Path found! "
+    } else {
+        "No path found. "
+    };
+    wait_for_exit(stdout, msg, theme::ui().success)
+}
+
+/// Entry point of replaying a run saved to a replay file
+pub fn replay(
+    stdout: &mut Stdout,
+    path: &Path,
+    options: &Options,
+    config: &Config,
+) -> std::io::Result<()> {
     execute!(
         stdout,
-        style::PrintStyledContent(
-            "Controls:\r\n"
-                .with(Color::Yellow)
-                .attribute(Attribute::Bold)
-        ),
-        style::PrintStyledContent("  Enter: Pause/Resume animation\r\n".with(Color::Cyan)),
-        style::PrintStyledContent("  ←/→: Step backward/forward when paused\r\n".with(Color::Cyan)),
-        style::PrintStyledContent("  ↑/↓: Speed up/slow down animation\r\n".with(Color::Cyan)),
-        style::PrintStyledContent("  Esc: Exit\r\n\r\n".with(Color::Cyan)),
+        style::SetAttribute(Attribute::Reverse),
+        style::PrintStyledContent("Replay Mode\r\n".with(theme::ui().accent)),
+        style::SetAttribute(Attribute::NoReverse),
     )?;
 
-    // Ask if user wants to loop generation and solving
-    let loop_animation = match app::select_from_menu(
-        stdout,
-        "Loop maze generation and solving? Will randomize generator & solver combination. (use arrow keys and Enter, or Esc to exit):",
-        &["Yes", "No"],
-    )? {
-        Some(choice) => choice == "Yes",
-        None => {
-            return Ok(());
+    let (header, reader) = match replay::open(path) {
+        Ok(replay) => replay,
+        Err(e) => {
+            let message = format!("Failed to open replay {}: {}. ", path.display(), e);
+            return wait_for_exit(stdout, &message, theme::ui().error);
         }
     };
+    stdout.execute(style::PrintStyledContent(
+        format!(
+            "Replaying a {}x{} maze generated with {} (seed {}) and solved with {}\r\n",
+            header.size.0, header.size.1, header.generator, header.seed, header.solver
+        )
+        .with(theme::ui().success)
+        .attribute(Attribute::Bold),
+    ))?;
+
+    let Some(cell_layout) = ask_cell_layout(stdout)? else {
+        return Ok(());
+    };
+    print_controls(stdout)?;
+
+    let recorder = match create_cast_recorder(options)? {
+        Ok(recorder) => recorder,
+        Err(message) => return wait_for_exit(stdout, &message, theme::ui().error),
+    };
+
+    // Feed the saved events to the renderer instead of computing them
+    let (completed, outcome) = animate(
+        header.size,
+        cell_layout,
+        recorder,
+        options.fast_export,
+        config,
+        move |grid_event_tx, run_info_tx, _| {
+            let (generator, solver) = (header.generator, header.solver);
+            run_info_tx.send(RunInfo { generator, solver }).ok();
+            replay::send_events(reader, grid_event_tx)
+        },
+    )?;
+    if let Err(e) = outcome {
+        tracing::error!("Failed to read replay: {}", e);
+        return wait_for_exit(
+            stdout,
+            &format!("Replay stopped, the file is damaged: {}. ", e),
+            theme::ui().error,
+        );
+    }
+    if let RendererStatus::Cancelled = completed {
+        tracing::info!("Replay was cancelled by user.");
+        return Ok(());
+    }
+    wait_for_exit(stdout, "Replay finished. ", theme::ui().success)
+}
 
+/// Render the grid events sent by the `source` function, run in its own thread, with all animation
+/// controls until rendering completes or the user cancels it.
+/// `source` sends the algorithms of each run before its grid events, and gets a flag set once
+/// rendering stops. The initial speed and history size are taken from
+/// `config` if set.
+/// Returns the renderer status and the result of `source`.
+fn animate<T: Send + 'static>(
+    maze_dims: (u8, u8),
+    cell_layout: CellLayout,
+    recorder: Option<CastRecorder>,
+    fast_export: bool,
+    config: &Config,
+    source: impl FnOnce(SyncSender<GridEvent>, Sender<RunInfo>, Arc<AtomicBool>) -> T + Send + 'static,
+) -> std::io::Result<(RendererStatus, T)> {
     // Flag to indicate other threads should stop. Set to true by the main thread on Esc key event.
     let should_stop = Arc::new(AtomicBool::new(false));
 
@@ -163,36 +627,35 @@ pub fn run(stdout: &mut Stdout) -> std::io::Result<()> {
         std::sync::mpsc::sync_channel::<GridEvent>(MAX_EVENTS_IN_CHANNEL_BUFFER);
     let (user_action_event_tx, user_action_event_rx) =
         std::sync::mpsc::channel::<UserActionEvent>();
+    let (run_info_tx, run_info_rx) = std::sync::mpsc::channel::<RunInfo>();
 
     // Spawn a thread to listen for grid updates and render the maze
     let render_cancel_for_render = should_stop.clone();
+    let max_in_memory_history_events = config
+        .history_events
+        .unwrap_or(MAX_IN_MEMORY_HISTORY_EVENTS);
+    let speed = config.speed;
     let render_thread_handle = std::thread::spawn(move || {
-        Renderer::new(MAX_HISTORY_GRID_EVENTS, Some((width, height))).render(
+        let mut renderer =
+            Renderer::new(max_in_memory_history_events, Some(maze_dims), cell_layout)
+                .with_run_info(run_info_rx);
+        if let Some(recorder) = recorder {
+            renderer = renderer.with_recorder(recorder, fast_export);
+        }
+        if let Some(speed) = speed {
+            renderer = renderer.with_speed(speed);
+        }
+        renderer.render(
             grid_event_rx,
             user_action_event_rx,
             &render_cancel_for_render,
         )
     });
 
-    // Spawn a thread to generate maze and solve it
-    let render_cancel_for_compute = should_stop.clone();
-    let compute_thread_handle = std::thread::spawn(move || -> bool {
-        if !loop_animation {
-            return compute(width, height, grid_event_tx, generator, solver);
-        }
-        // Looping mode: randomly select generator and solver each iteration
-        let mut rng = rand::rng();
-        loop {
-            let goal_reached = compute(width, height, grid_event_tx.clone(), generator, solver);
-            // Check if rendering was cancelled
-            if render_cancel_for_compute.load(std::sync::atomic::Ordering::Acquire) {
-                tracing::info!("Compute thread detected render cancel, exiting loop");
-                return goal_reached;
-            }
-            // Randomly select new generator and solver combination for next iteration
-            (generator, solver) = app::COMBOS[rng.random_range(0..app::COMBOS.len())];
-        }
-    });
+    // Spawn a thread to produce the grid events
+    let render_cancel_for_source = should_stop.clone();
+    let source_thread_handle =
+        std::thread::spawn(move || source(grid_event_tx, run_info_tx, render_cancel_for_source));
 
     // Main thread loop to listen for user input events during rendering
     let completed = app_loop(
@@ -206,34 +669,11 @@ pub fn run(stdout: &mut Stdout) -> std::io::Result<()> {
     // Wait for input thread to finish
     input_thread_handle.join().expect("Input thread panicked")?;
 
-    // Wait for compute thread to finish
-    let goal_reached = compute_thread_handle
+    // Wait for the source thread to finish
+    let outcome = source_thread_handle
         .join()
-        .expect("Compute thread panicked");
-
-    if let RendererStatus::Cancelled = completed {
-        tracing::info!("Rendering was cancelled by user.");
-        return Ok(());
-    }
-
-    let msg = if goal_reached {
-        "Path found! "
-    } else {
-        "No path found. "
-    };
-    stdout.execute(style::PrintStyledContent(
-        msg.with(Color::Green).attribute(Attribute::Bold),
-    ))?;
-
-    stdout.execute(style::PrintStyledContent(
-        "Press Esc to exit...\r"
-            .with(Color::Blue)
-            .attribute(Attribute::Bold),
-    ))?;
-
-    // Wait for user to press Esc
-    app::wait_for_keypress(KeyCode::Esc)?;
-    Ok(())
+        .expect("Grid event source thread panicked");
+    Ok((completed, outcome))
 }
 
 /// App loop after starting input and render threads
@@ -247,6 +687,8 @@ fn app_loop(
     tracing::info!("Started main app loop");
     // Flag to indicate if the animation is currently paused
     let mut is_paused = false;
+    // Flag to indicate if the key bindings are drawn over the grid
+    let mut is_showing_help = false;
     loop {
         // Check if render is done
         if render_thread_handle.is_finished() {
@@ -270,18 +712,27 @@ fn app_loop(
             }
             Ok(event) => match event {
                 UserInputEvent::KeyPress(key_event) => {
-                    match key_event.code {
-                        // Exit on Esc key
-                        KeyCode::Esc => {
-                            tracing::debug!("[app loop] Esc key pressed, notifying renderer");
+                    let action = Keymap::current().action(&key_event, Context::Visualize);
+                    match action {
+                        Some(Action::Exit) => {
+                            tracing::debug!("[app loop] Exit key pressed, notifying renderer");
                             // Error only happens if user_input_event_rx is dropped, which
                             // means render thread has exited already
                             user_action_event_tx.send(UserActionEvent::Cancel).ok();
                             should_stop.store(true, std::sync::atomic::Ordering::Release);
                             break;
                         }
-                        KeyCode::Enter => {
-                            // Toggle pause/resume on Enter key
+                        // Any other key closes the key bindings
+                        _ if is_showing_help => {
+                            is_showing_help = false;
+                            Some(UserActionEvent::HideHelp)
+                        }
+                        Some(Action::Help) => {
+                            is_showing_help = true;
+                            Some(UserActionEvent::ShowHelp)
+                        }
+                        Some(Action::Pause) => {
+                            // Toggle pause/resume
                             let event = if is_paused {
                                 UserActionEvent::Resume
                             } else {
@@ -291,23 +742,8 @@ fn app_loop(
                             is_paused = !is_paused;
                             Some(event)
                         }
-                        KeyCode::Left if is_paused => {
-                            // Step backward when paused
-                            Some(UserActionEvent::Backward)
-                        }
-                        KeyCode::Right if is_paused => {
-                            // Step forward when paused
-                            Some(UserActionEvent::Forward)
-                        }
-                        KeyCode::Up => {
-                            // Speed up animation
-                            Some(UserActionEvent::SpeedUp)
-                        }
-                        KeyCode::Down => {
-                            // Slow down animation
-                            Some(UserActionEvent::SlowDown)
-                        }
-                        _ => None, // Ignore other keys
+                        Some(action) => user_action(action, is_paused),
+                        None => None, // Ignore other keys
                     }
                 }
                 UserInputEvent::Resize => Some(UserActionEvent::Resize),
@@ -329,6 +765,39 @@ fn app_loop(
     render_thread_handle.join().expect("Render thread panicked")
 }
 
+/// Map an action of the keymap to the user action event it triggers, if any in the current
+/// pause state. Pausing, help and exit are handled by the app loop.
+fn user_action(action: Action, is_paused: bool) -> Option<UserActionEvent> {
+    let pan = |direction, page| Some(UserActionEvent::Pan { direction, page });
+    if let Some(tenths) = Action::SEEK.iter().position(|&seek| seek == action) {
+        // Seek to 0%, 10%, ..., 90% of the history when paused
+        return is_paused.then_some(UserActionEvent::SeekPercent(tenths as u8 * 10));
+    }
+    match action {
+        // Browsing the history is only available when paused
+        Action::StepBackward if is_paused => Some(UserActionEvent::Backward),
+        Action::StepForward if is_paused => Some(UserActionEvent::Forward),
+        Action::JumpBackward if is_paused => Some(UserActionEvent::JumpBackward),
+        Action::JumpForward if is_paused => Some(UserActionEvent::JumpForward),
+        Action::PhaseStart if is_paused => Some(UserActionEvent::PhaseStart),
+        Action::PhaseEnd if is_paused => Some(UserActionEvent::PhaseEnd),
+        Action::SpeedUp => Some(UserActionEvent::SpeedUp),
+        Action::SlowDown => Some(UserActionEvent::SlowDown),
+        Action::Heatmap => Some(UserActionEvent::ToggleHeatmap),
+        Action::VisitOrder => Some(UserActionEvent::ToggleVisitOrder),
+        Action::PanUp => pan(PanDirection::Up, false),
+        Action::PanDown => pan(PanDirection::Down, false),
+        Action::PanLeft => pan(PanDirection::Left, false),
+        Action::PanRight => pan(PanDirection::Right, false),
+        Action::PanPageUp => pan(PanDirection::Up, true),
+        Action::PanPageDown => pan(PanDirection::Down, true),
+        Action::PanPageLeft => pan(PanDirection::Left, true),
+        Action::PanPageRight => pan(PanDirection::Right, true),
+        Action::Follow => Some(UserActionEvent::ToggleFollow),
+        _ => None,
+    }
+}
+
 /// Listen for user input events (key presses and resize)
 /// This function runs in a separate thread, and is the only place where user input is read
 fn listen_to_user_input(
@@ -358,13 +827,11 @@ fn listen_to_user_input(
             _ => continue, // Ignore other events
         };
 
-        // Should exit input thread on Esc key
+        // Should exit input thread on the exit key
         let should_exit = matches!(
-            input_event,
-            UserInputEvent::KeyPress(event::KeyEvent {
-                code: KeyCode::Esc,
-                ..
-            })
+            &input_event,
+            UserInputEvent::KeyPress(key_event)
+                if Keymap::current().action(key_event, Context::Visualize) == Some(Action::Exit)
         );
 
         // Send the input event to the main thread
@@ -374,13 +841,13 @@ fn listen_to_user_input(
         }
 
         if should_exit {
-            tracing::debug!("[input loop] Esc key pressed, exiting");
+            tracing::debug!("[input loop] Exit key pressed, exiting");
             return Ok(());
         }
     }
 }
 
-/// Generate and solve the maze
+/// Generate and solve the maze, with the generator optionally seeded for reproducibility
 /// Returns whether the goal was reached
 fn compute(
     width: u8,
@@ -388,10 +855,11 @@ fn compute(
     grid_event_tx: std::sync::mpsc::SyncSender<GridEvent>,
     generator: Generator,
     solver: Solver,
+    seed: Option<u64>,
 ) -> bool {
     let mut maze = Maze::new(width, height, Some(grid_event_tx));
     // Generate the maze using the selected algorithm
-    generate_maze(&mut maze, generator, None);
+    generate_maze(&mut maze, generator, seed);
 
     // Solve the maze using the selected algorithm
     solve_maze(&mut maze, solver)
@@ -0,0 +1,536 @@
+use std::{
+    io::{Stdout, StdoutLock, Write},
+    sync::{Arc, atomic::AtomicBool, mpsc::Receiver},
+};
+
+use crossterm::{
+    ExecutableCommand, cursor,
+    event::KeyCode,
+    queue,
+    style::{self, Attribute, StyledContent, Stylize},
+    terminal::{self, ClearType},
+};
+use unicode_truncate::UnicodeTruncateStr;
+
+use crate::{
+    app::{
+        self,
+        clock::RunClock,
+        keymap::{Action, Keymap},
+        visualize::{
+            INPUT_RECV_TIMEOUT, MAX_EVENTS_IN_CHANNEL_BUFFER, USER_INPUT_EVENT_POLL_TIMEOUT,
+            UserActionEvent, UserInputEvent, app_loop,
+            grid_state::{DisplayMode, GridState},
+            listen_to_user_input,
+            renderer::{
+                RenderRefreshTimeScale, RendererStatus, TerminalFit, show_help,
+                wait_for_terminal_fit,
+            },
+        },
+    },
+    config::theme,
+    generators::{Generator, generate_maze},
+    maze::{Maze, cell::CellLayout, grid::GridEvent},
+    solvers::{Solver, solve_maze},
+};
+
+/// Number of blank columns between two panes side by side
+const PANE_GAP: u16 = 2;
+/// Number of rows above each pane reserved for its label
+const PANE_LABEL_ROWS: u16 = 1;
+
+/// Calculate max maze dimensions (width, height) so that `panes` (columns, rows) of mazes fit
+/// in a terminal of the given size when drawn with the given cell layout, with labels above each pane
+/// and the status rows below all panes
+pub(super) fn fit_pane_maze_dims(
+    term_width: u16,
+    term_height: u16,
+    panes: (u16, u16),
+    layout: CellLayout,
+) -> (u8, u8) {
+    let (columns, rows) = panes;
+    let pane_width = term_width.saturating_sub((columns - 1) * PANE_GAP) / columns;
+    let pane_height =
+        (term_height.saturating_sub(app::NUM_STATUS_ROWS) / rows).saturating_sub(PANE_LABEL_ROWS);
+    (
+        app::get_max_maze_size(pane_width, layout.cell_width()),
+        app::get_max_maze_size(pane_height * layout.rows_per_line(), 1),
+    )
+}
+
+/// A single maze being solved by one solver in the race
+struct Pane {
+    /// Solver working on this pane's copy of the maze
+    solver: Solver,
+    /// Grid events of the solver
+    grid_event_rx: Receiver<GridEvent>,
+    /// Current state of this pane's grid, drawn at the pane's position
+    grid_state: GridState,
+    /// Terminal position (column, row) of the label above the grid
+    label_origin: (u16, u16),
+    /// Number of grid events rendered so far
+    steps: usize,
+    /// Finishing place (1 for the winner) once the solver has sent all its events
+    place: Option<usize>,
+}
+
+/// Renderer that advances several panes of the same maze in lockstep, one grid event per pane
+/// at a time, so solvers can be compared side by side
+struct RaceRenderer<'a> {
+    /// Standard output handle, locked for the lifetime of the renderer
+    stdout: StdoutLock<'a>,
+    /// Panes in the race, laid out left to right, then top to bottom
+    panes: Vec<Pane>,
+    /// Terminal size (columns, rows) needed to show all panes and the status rows
+    required_size: (u16, u16),
+    /// Row of the status line below all panes
+    status_row: u16,
+    /// Render refresh time scale, shared by all panes
+    render_refresh_time_scale: RenderRefreshTimeScale,
+    /// How grid cells are currently colored in all panes
+    display_mode: DisplayMode,
+    /// Number of panes that have finished so far
+    num_finished: usize,
+    /// Generator of the maze shared by all panes, shown in the HUD
+    generator: Generator,
+    /// Running time shown in the HUD
+    clock: RunClock,
+}
+
+impl<'a> RaceRenderer<'a> {
+    /// Create a race renderer of one pane per solver, each starting from a snapshot of `maze`
+    /// generated by `generator`. Panes are laid out in rows of `columns` panes, with cells drawn
+    /// in the given layout.
+    fn new(
+        maze: &Maze,
+        generator: Generator,
+        solvers: Vec<(Solver, Receiver<GridEvent>)>,
+        columns: u16,
+        layout: CellLayout,
+    ) -> Self {
+        let grid = maze.grid();
+        let pane_width = grid.width() * layout.cell_width();
+        let pane_height = grid.height().div_ceil(layout.rows_per_line()) + PANE_LABEL_ROWS;
+        let rows = (solvers.len() as u16).div_ceil(columns);
+
+        let panes = solvers
+            .into_iter()
+            .enumerate()
+            .map(|(i, (solver, grid_event_rx))| {
+                let label_origin = (
+                    (i as u16 % columns) * (pane_width + PANE_GAP),
+                    (i as u16 / columns) * pane_height,
+                );
+                Pane {
+                    solver,
+                    grid_event_rx,
+                    grid_state: GridState::from_grid(
+                        grid,
+                        (label_origin.0, label_origin.1 + PANE_LABEL_ROWS),
+                        layout,
+                    ),
+                    label_origin,
+                    steps: 0,
+                    place: None,
+                }
+            })
+            .collect();
+
+        Self {
+            stdout: std::io::stdout().lock(),
+            panes,
+            required_size: (
+                columns * pane_width + (columns - 1) * PANE_GAP,
+                rows * pane_height + app::NUM_STATUS_ROWS,
+            ),
+            status_row: rows * pane_height,
+            render_refresh_time_scale: RenderRefreshTimeScale::calibrated(
+                maze.width(),
+                maze.height(),
+            ),
+            display_mode: DisplayMode::default(),
+            num_finished: 0,
+            generator,
+            clock: RunClock::start(),
+        }
+    }
+
+    /// Draw the label above the pane at the given index, truncated to the pane width
+    fn draw_label(&mut self, index: usize) -> std::io::Result<()> {
+        let pane = &self.panes[index];
+        let status = match pane.place {
+            Some(1) => "winner!".to_string(),
+            Some(place) => format!("finished #{}", place),
+            None => "solving...".to_string(),
+        };
+        let label = format!("{} | {} steps | {}", pane.solver, pane.steps, status);
+        let pane_width = pane.grid_state.screen_dims().map_or(0, |(width, _)| width);
+        let (label, label_width) = label.unicode_truncate(pane_width as usize);
+        let color = match pane.place {
+            Some(1) => theme::ui().success,
+            Some(_) => theme::ui().accent,
+            None => theme::ui().info,
+        };
+        queue!(
+            self.stdout,
+            cursor::MoveTo(pane.label_origin.0, pane.label_origin.1),
+            style::PrintStyledContent(label.with(color).attribute(Attribute::Bold)),
+            // Clear the rest of the previous label within the pane
+            style::Print(" ".repeat(pane_width as usize - label_width)),
+        )?;
+        Ok(())
+    }
+
+    /// Clear the screen and draw all labels and panes
+    fn draw_all(&mut self) -> std::io::Result<()> {
+        queue!(self.stdout, terminal::Clear(ClearType::All))?;
+        for i in 0..self.panes.len() {
+            self.draw_label(i)?;
+            self.panes[i]
+                .grid_state
+                .recover(&mut self.stdout, self.display_mode)?;
+        }
+        self.draw_hud()?;
+        self.stdout.flush()
+    }
+
+    /// Draw the HUD below the status line with the generator, number of solvers, speed, running
+    /// state and elapsed time
+    fn draw_hud(&mut self) -> std::io::Result<()> {
+        let (speed, levels) = self.render_refresh_time_scale.speed();
+        let hud = format!(
+            "{} | {} solvers, {} finished | speed {}/{} | {} | {} help",
+            self.generator,
+            self.panes.len(),
+            self.num_finished,
+            speed,
+            levels,
+            self.clock.hud_label(self.num_finished == self.panes.len()),
+            Keymap::current().keys(Action::Help),
+        );
+        app::print_status(
+            &mut self.stdout,
+            self.status_row,
+            app::StatusRow::Hud,
+            Some(hud.with(theme::ui().muted)),
+        )
+    }
+
+    fn log_to_terminal(
+        &mut self,
+        msg: StyledContent<impl std::fmt::Display + AsRef<str>>,
+    ) -> std::io::Result<()> {
+        app::log_terminal(&mut self.stdout, self.status_row, Some(msg))
+    }
+
+    /// Make sure the terminal is large enough for all panes, waiting for a resize if needed
+    fn check_resize(
+        &mut self,
+        user_action_event_rx: &Receiver<UserActionEvent>,
+    ) -> std::io::Result<RendererStatus> {
+        let description = format!("{} racing mazes", self.panes.len());
+        match wait_for_terminal_fit(
+            &mut self.stdout,
+            self.required_size,
+            &description,
+            user_action_event_rx,
+        )? {
+            TerminalFit::AlreadyFits => {}
+            TerminalFit::Restored => self.draw_all()?,
+            TerminalFit::Cancelled => return Ok(RendererStatus::Cancelled),
+        }
+        Ok(RendererStatus::Completed)
+    }
+
+    /// Advance every unfinished pane by one grid event.
+    /// Returns `Ok(false)` once all panes have finished.
+    fn step(&mut self) -> std::io::Result<bool> {
+        let mut newly_finished = Vec::new();
+        for (i, pane) in self.panes.iter_mut().enumerate() {
+            if pane.place.is_some() {
+                continue;
+            }
+            match pane.grid_event_rx.recv() {
+                Ok(event) => {
+                    pane.grid_state.add_event(event);
+                    if let GridEvent::Update { coord, .. } = event {
+                        pane.grid_state
+                            .draw_cell(&mut self.stdout, coord, self.display_mode)?;
+                    }
+                    pane.steps += 1;
+                }
+                // The solver has sent all of its events
+                Err(_) => newly_finished.push(i),
+            }
+        }
+        // Panes finishing in the same step share the same place
+        let place = self.num_finished + 1;
+        self.num_finished += newly_finished.len();
+        for &i in &newly_finished {
+            self.panes[i].place = Some(place);
+        }
+        for i in 0..self.panes.len() {
+            self.draw_label(i)?;
+        }
+        self.draw_hud()?;
+        self.stdout.flush()?;
+        Ok(self.num_finished < self.panes.len())
+    }
+
+    /// Handle a single user action event
+    /// Returns `Ok(RendererStatus::Cancelled)` if rendering was cancelled
+    fn handle_user_action_event(
+        &mut self,
+        event: &UserActionEvent,
+        user_action_event_rx: &Receiver<UserActionEvent>,
+    ) -> std::io::Result<RendererStatus> {
+        match event {
+            UserActionEvent::Forward => {
+                if !self.step()? {
+                    self.log_to_terminal("All solvers have finished".with(theme::ui().accent))?;
+                }
+            }
+            UserActionEvent::Backward
+            | UserActionEvent::JumpBackward
+            | UserActionEvent::JumpForward
+            | UserActionEvent::PhaseStart
+            | UserActionEvent::PhaseEnd
+            | UserActionEvent::SeekPercent(_) => {
+                self.log_to_terminal(
+                    "Only stepping forward is available when racing solvers"
+                        .with(theme::ui().accent),
+                )?;
+            }
+            UserActionEvent::SpeedUp | UserActionEvent::SlowDown => {
+                if let UserActionEvent::SpeedUp = event {
+                    self.render_refresh_time_scale.speed_up();
+                } else {
+                    self.render_refresh_time_scale.slow_down();
+                }
+                let bar = self
+                    .render_refresh_time_scale
+                    .make_scale_bar(self.required_size.0);
+                self.log_to_terminal(bar.stylize())?;
+            }
+            UserActionEvent::ToggleHeatmap | UserActionEvent::ToggleVisitOrder
+                if theme::Theme::current().no_color =>
+            {
+                self.log_to_terminal(
+                    "Heatmap and visit order views need colors, which NO_COLOR turns off"
+                        .with(theme::ui().accent),
+                )?;
+            }
+            UserActionEvent::ToggleHeatmap | UserActionEvent::ToggleVisitOrder => {
+                let mode = match event {
+                    UserActionEvent::ToggleHeatmap => DisplayMode::Heatmap,
+                    _ => DisplayMode::VisitOrder,
+                };
+                self.display_mode = if self.display_mode == mode {
+                    DisplayMode::Normal
+                } else {
+                    mode
+                };
+                self.draw_all()?;
+            }
+            UserActionEvent::Resize => {
+                return self.check_resize(user_action_event_rx);
+            }
+            UserActionEvent::Cancel => {
+                tracing::info!("Race rendering cancelled by user");
+                return Ok(RendererStatus::Cancelled);
+            }
+            UserActionEvent::Pan { .. } | UserActionEvent::ToggleFollow => {
+                self.log_to_terminal(
+                    "Racing mazes always fit the terminal, there is nothing to pan"
+                        .with(theme::ui().accent),
+                )?;
+            }
+            UserActionEvent::ShowHelp => {
+                if let RendererStatus::Cancelled =
+                    show_help(&mut self.stdout, user_action_event_rx)?
+                {
+                    return Ok(RendererStatus::Cancelled);
+                }
+                if let RendererStatus::Cancelled = self.check_resize(user_action_event_rx)? {
+                    return Ok(RendererStatus::Cancelled);
+                }
+                self.draw_all()?;
+            }
+            UserActionEvent::Pause | UserActionEvent::Resume | UserActionEvent::HideHelp => {}
+        }
+        self.draw_hud()?;
+        self.stdout.flush()?;
+        Ok(RendererStatus::Completed)
+    }
+
+    /// Render loop advancing all panes in lockstep until every solver has finished
+    /// Returns:
+    /// - `Ok(RendererStatus::Completed)` if rendering completed successfully
+    /// - `Ok(RendererStatus::Cancelled)` if rendering was cancelled
+    /// - `Err` if there was an I/O error
+    fn render(
+        &mut self,
+        user_action_event_rx: Receiver<UserActionEvent>,
+        should_stop: &AtomicBool,
+    ) -> std::io::Result<RendererStatus> {
+        queue!(self.stdout, cursor::Hide)?;
+        if let RendererStatus::Cancelled = self.check_resize(&user_action_event_rx)? {
+            return Ok(RendererStatus::Cancelled);
+        }
+        self.draw_all()?;
+
+        let mut is_paused = false;
+        loop {
+            if should_stop.load(std::sync::atomic::Ordering::Acquire) {
+                tracing::info!("Race rendering cancelled by main thread");
+                return Ok(RendererStatus::Cancelled);
+            }
+
+            // Block on user actions while paused, otherwise just check for them
+            let action_event = if is_paused {
+                user_action_event_rx.recv().ok()
+            } else {
+                user_action_event_rx.try_recv().ok()
+            };
+            if let Some(event) = action_event {
+                tracing::debug!("Received user action event: {:?}", event);
+                match event {
+                    UserActionEvent::Pause => {
+                        is_paused = true;
+                        self.clock.pause();
+                    }
+                    UserActionEvent::Resume => {
+                        is_paused = false;
+                        self.clock.resume();
+                        app::log_terminal(
+                            &mut self.stdout,
+                            self.status_row,
+                            None::<StyledContent<&str>>,
+                        )?;
+                    }
+                    _ => {}
+                }
+                if let RendererStatus::Cancelled =
+                    self.handle_user_action_event(&event, &user_action_event_rx)?
+                {
+                    return Ok(RendererStatus::Cancelled);
+                }
+            }
+
+            if is_paused {
+                continue;
+            }
+            if !self.step()? {
+                break;
+            }
+            std::thread::sleep(self.render_refresh_time_scale.current());
+        }
+
+        self.draw_hud()?;
+        // Move cursor below the panes after exiting
+        queue!(self.stdout, cursor::MoveTo(0, self.status_row))?;
+        self.stdout.flush()?;
+        tracing::info!("Race rendering completed successfully");
+        Ok(RendererStatus::Completed)
+    }
+}
+
+/// Generate one maze and race the given solvers on copies of it, one pane per solver laid out
+/// in rows of `columns` panes, with cells drawn in the given layout, starting at the given speed level
+/// if set
+pub(super) fn run(
+    stdout: &mut Stdout,
+    (width, height): (u8, u8),
+    generator: Generator,
+    solvers: &[Solver],
+    columns: u16,
+    layout: CellLayout,
+    speed: Option<usize>,
+) -> std::io::Result<()> {
+    // Generate the maze up front. Every pane starts from a snapshot of it.
+    let mut maze = Maze::new(width, height, None);
+    generate_maze(&mut maze, generator, None);
+
+    // Flag to indicate other threads should stop. Set to true by the main thread on Esc key event.
+    let should_stop = Arc::new(AtomicBool::new(false));
+
+    let (user_input_event_tx, user_input_event_rx) = std::sync::mpsc::channel::<UserInputEvent>();
+    let should_stop_for_input = should_stop.clone();
+    // Spawn a thread to listen for user input
+    let input_thread_handle = std::thread::spawn(move || -> std::io::Result<()> {
+        listen_to_user_input(
+            user_input_event_tx,
+            USER_INPUT_EVENT_POLL_TIMEOUT,
+            &should_stop_for_input,
+        )
+    });
+
+    // Spawn a thread per solver, each solving its own copy of the maze
+    let mut panes = Vec::with_capacity(solvers.len());
+    let mut solver_thread_handles = Vec::with_capacity(solvers.len());
+    for &solver in solvers {
+        let (grid_event_tx, grid_event_rx) =
+            std::sync::mpsc::sync_channel::<GridEvent>(MAX_EVENTS_IN_CHANNEL_BUFFER);
+        let mut pane_maze = maze.clone_with_sender(Some(grid_event_tx));
+        solver_thread_handles.push(std::thread::spawn(move || -> bool {
+            solve_maze(&mut pane_maze, solver)
+            // Maze is dropped here, as well as the grid_event_tx sender
+        }));
+        panes.push((solver, grid_event_rx));
+    }
+
+    let (user_action_event_tx, user_action_event_rx) =
+        std::sync::mpsc::channel::<UserActionEvent>();
+
+    // Spawn a thread to render all panes
+    let should_stop_for_render = should_stop.clone();
+    let render_thread_handle = std::thread::spawn(move || {
+        let mut renderer = RaceRenderer::new(&maze, generator, panes, columns, layout);
+        if let Some(speed) = speed {
+            renderer.render_refresh_time_scale.set_speed(speed);
+        }
+        renderer.render(user_action_event_rx, &should_stop_for_render)
+    });
+
+    // Main thread loop to listen for user input events during rendering
+    let completed = app_loop(
+        user_input_event_rx,
+        user_action_event_tx,
+        INPUT_RECV_TIMEOUT,
+        render_thread_handle,
+        should_stop,
+    )?;
+
+    // Wait for input thread to finish
+    input_thread_handle.join().expect("Input thread panicked")?;
+
+    // Wait for every solver thread to run to completion. Solvers don't stop on exit, but once
+    // the render thread has dropped their receivers, their updates are discarded without waiting.
+    let goals_reached = solver_thread_handles
+        .into_iter()
+        .map(|handle| handle.join().expect("Solver thread panicked"))
+        .collect::<Vec<_>>();
+
+    if let RendererStatus::Cancelled = completed {
+        tracing::info!("Race rendering was cancelled by user.");
+        return Ok(());
+    }
+
+    let msg = if goals_reached.iter().all(|&reached| reached) {
+        "All solvers found the path! "
+    } else {
+        "Some solvers found no path. "
+    };
+    stdout.execute(style::PrintStyledContent(
+        msg.with(theme::ui().success).attribute(Attribute::Bold),
+    ))?;
+    stdout.execute(style::PrintStyledContent(
+        "Press Esc to exit...\r"
+            .with(theme::ui().hint)
+            .attribute(Attribute::Bold),
+    ))?;
+
+    // Wait for user to press Esc
+    app::wait_for_keypress(KeyCode::Esc)?;
+    Ok(())
+}
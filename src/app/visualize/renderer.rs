@@ -1,25 +1,41 @@
 use std::{
-    collections::HashMap,
     fmt::Display,
-    io::{StdoutLock, Write},
+    io::Write,
     sync::{atomic::AtomicBool, mpsc::Receiver},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crossterm::{
-    QueueableCommand, cursor, queue,
-    style::{self, Attribute, Color, StyledContent, Stylize},
+    cursor, queue,
+    style::{self, Attribute, StyledContent, Stylize},
     terminal::{self, ClearType},
 };
 
 use crate::{
-    app::{self, visualize::UserActionEvent, visualize::history::GridEventHistory},
-    maze::{cell::GridCell, grid::GridEvent},
+    app::{
+        self,
+        clock::RunClock,
+        keymap::{self, Action, Context, Keymap},
+        visualize::cast::{CastRecorder, RenderOutput},
+        visualize::grid_state::{DisplayMode, GridState},
+        visualize::history::GridEventHistory,
+        visualize::{PanDirection, RunInfo, UserActionEvent},
+    },
+    config::theme,
+    maze::{
+        cell::{CellLayout, GlyphTheme},
+        grid::GridEvent,
+    },
 };
 
+/// Number of events jumped at a time with PageUp/PageDown when paused
+const JUMP_EVENTS: usize = 100;
+/// Minimum time between two refreshes of the HUD while the animation runs
+const HUD_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
 // TODO: add tests
 /// Struct to manage render refresh time scaling based on a quantized level scale
-struct RenderRefreshTimeScale {
+pub(super) struct RenderRefreshTimeScale {
     delta: Duration,
     /// number of discrete levels (quantization). e.g. 10
     levels: usize,
@@ -41,7 +57,7 @@ impl Default for RenderRefreshTimeScale {
 
 impl RenderRefreshTimeScale {
     /// Create a calibrated RenderRefreshTimeScale based on the grid dimensions
-    fn calibrated(grid_width: u8, grid_height: u8) -> Self {
+    pub(super) fn calibrated(grid_width: u8, grid_height: u8) -> Self {
         let mut scale = Self::default();
         // Map grid size to a sensible starting level.
         // Larger grids -> faster rendering (higher level index).
@@ -54,10 +70,15 @@ impl RenderRefreshTimeScale {
         scale
     }
 
+    /// Set the speed level, from 1 (slowest) to the number of levels (fastest), clamped to that range
+    pub(super) fn set_speed(&mut self, speed: usize) {
+        self.level = speed.clamp(1, self.levels) - 1;
+    }
+
     /// Create a scale bar string representing the current render refresh time scale
     /// The scale is quantized into `self.levels` segments. If terminal width is smaller
     /// than the requested number of segments, segments will be capped to width.
-    fn make_scale_bar(&self, width: u16) -> String {
+    pub(super) fn make_scale_bar(&self, width: u16) -> String {
         let w = width as usize;
         if w == 0 {
             return "".to_string();
@@ -73,10 +94,11 @@ impl RenderRefreshTimeScale {
         let seg_sizes = (0..seg_count).map(|i| base_seg_w + if i < rem { 1 } else { 0 });
 
         // Create the bar: filled segments up to `self.level`, rest empty
+        let (filled, empty) = GlyphTheme::current().bar();
         seg_sizes
             .enumerate()
             .map(|(i, seg_w)| {
-                let ch = if i <= self.level { '█' } else { '░' };
+                let ch = if i <= self.level { filled } else { empty };
                 ch.to_string().repeat(seg_w)
             })
             .collect::<String>()
@@ -84,13 +106,18 @@ impl RenderRefreshTimeScale {
 
     /// Get the current duration based on the square of the level
     /// Level levels-1 -> delta * 1^2 (fastest), level 0 -> delta * levels^2 (slowest)
-    fn current(&self) -> Duration {
+    pub(super) fn current(&self) -> Duration {
         let factor = ((self.levels - self.level) as u32).saturating_add(1);
         self.delta * factor * factor
     }
 
+    /// Current speed level and the number of levels, from 1 (slowest) to the number of levels
+    pub(super) fn speed(&self) -> (usize, usize) {
+        (self.level + 1, self.levels)
+    }
+
     /// Speed up the rendering by increasing the current level (toward levels-1).
-    fn speed_up(&mut self) {
+    pub(super) fn speed_up(&mut self) {
         if self.level < self.levels.saturating_sub(1) {
             self.level += 1;
         } else {
@@ -99,7 +126,7 @@ impl RenderRefreshTimeScale {
     }
 
     /// Slow down the rendering by decreasing the current level (toward 0).
-    fn slow_down(&mut self) {
+    pub(super) fn slow_down(&mut self) {
         if self.level > 0 {
             self.level -= 1;
         } else {
@@ -108,179 +135,186 @@ impl RenderRefreshTimeScale {
     }
 }
 
-/// Compact representation of the current grid state.
-/// Stores the initial fill cell and only the cells that differ from it.
-#[derive(Default)]
-struct GridState {
-    /// initial cell and grid dimensions (width, height)
-    initial: Option<(GridCell, u16, u16)>,
-    /// map of (x,y) -> cell for cells that differ from initial cell
-    changes: HashMap<(u16, u16), GridCell>,
+pub enum RendererStatus {
+    /// Rendering completed successfully
+    Completed,
+    /// Rendering was cancelled
+    Cancelled,
+}
+
+/// Outcome of [`wait_for_terminal_fit`]
+pub(super) enum TerminalFit {
+    /// The terminal was already large enough, nothing was drawn
+    AlreadyFits,
+    /// The terminal was too small and has been resized to fit. The screen was cleared,
+    /// so the caller should redraw everything
+    Restored,
+    /// Rendering was cancelled by the user while waiting
+    Cancelled,
 }
 
-impl GridState {
-    fn dims(&self) -> Option<(u16, u16)> {
-        self.initial.map(|(_, w, h)| (w, h))
+/// Check if the terminal is at least `required` (columns, rows) large.
+/// If not, display a message about `description` not fitting and wait for the user to press Esc
+/// or resize the terminal.
+/// Returns `Err` if there was an I/O error
+pub(super) fn wait_for_terminal_fit(
+    stdout: &mut impl Write,
+    required: (u16, u16),
+    description: &str,
+    user_action_event_rx: &Receiver<UserActionEvent>,
+) -> std::io::Result<TerminalFit> {
+    let fits = |(term_width, term_height): (u16, u16)| {
+        term_width >= required.0 && term_height >= required.1
+    };
+    if fits(terminal::size()?) {
+        return Ok(TerminalFit::AlreadyFits);
     }
 
-    /// Apply a GridEvent to the compact state. Safe to call for both [`GridEvent::Initial`]` and [`GridEvent::Update`].
-    /// If an [`GridEvent::Initial`] is received, it clears the changes map and sets the initial cell and dimensions.
-    /// If an [`GridEvent::Update`] is received, it updates the changes map accordingly. The `old` value is ignored.
-    fn add_event(&mut self, event: GridEvent) {
-        match event {
-            GridEvent::Initial {
-                cell,
-                width,
-                height,
-            } => {
-                self.initial = Some((cell, width, height));
-                self.changes.clear();
+    tracing::info!("Terminal size too small for grid display, pausing rendering");
+    let msg = format!(
+        "Terminal size is too small ({}x{}) for {} to display.\r\n",
+        required.0, required.1, description
+    );
+    queue!(
+        stdout,
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0),
+        style::PrintStyledContent(msg.with(theme::ui().accent).attribute(Attribute::Bold)),
+        style::PrintStyledContent(
+            "Please resize the terminal, or press Esc to exit...\r\n"
+                .with(theme::ui().hint)
+                .attribute(Attribute::Bold)
+        )
+    )?;
+    stdout.flush()?;
+
+    // Listen for user action events to resume or cancel
+    loop {
+        match user_action_event_rx.recv() {
+            Err(_e) => {
+                // Main thread has disconnected, treat as cancelled
+                tracing::info!("Rendering cancelled due to terminal resize");
+                return Ok(TerminalFit::Cancelled);
             }
-            GridEvent::Update { coord, new, .. } => {
-                if let Some((initial, _, _)) = self.initial {
-                    if new == initial {
-                        self.changes.remove(&coord);
-                    } else {
-                        self.changes.insert(coord, new);
-                    }
+            Ok(UserActionEvent::Cancel) => {
+                tracing::info!("Rendering cancelled by user due to terminal resize");
+                return Ok(TerminalFit::Cancelled);
+            }
+            Ok(UserActionEvent::Resize) => {
+                // Check terminal size again
+                if fits(terminal::size()?) {
+                    queue!(stdout, terminal::Clear(ClearType::All))?;
+                    return Ok(TerminalFit::Restored);
                 }
-                // If no initial, ignore update
+                // Still too small, continue waiting
             }
+            // Ignore other events
+            Ok(_) => {}
         }
     }
+}
 
-    /// Render the compact state to the provided stdout. This draws the initial filled grid,
-    /// then overlays changed cells. Does nothing if initial not set.
-    fn recover(&self, stdout: &mut StdoutLock) -> std::io::Result<()> {
-        if let Some((initial, width, height)) = self.initial {
-            // Render initial filled grid
-            stdout.queue(cursor::MoveTo(0, 0))?;
-            for _y in 0..height {
-                for _x in 0..width {
-                    stdout.queue(style::Print(initial))?;
-                }
-                stdout.queue(style::Print("\r\n"))?;
-            }
-            // Overlay changed cells
-            for (&(x, y), &cell) in self.changes.iter() {
-                queue!(
-                    stdout,
-                    cursor::MoveTo(x * GridCell::CELL_WIDTH, y),
-                    style::Print(cell)
-                )?;
+/// Draw the key bindings over the screen and wait for the user to hide them, redrawing them if
+/// the terminal is resized. The caller should redraw everything once they are hidden.
+/// Returns `Err` if there was an I/O error
+pub(super) fn show_help(
+    stdout: &mut impl Write,
+    user_action_event_rx: &Receiver<UserActionEvent>,
+) -> std::io::Result<RendererStatus> {
+    keymap::draw_help(stdout, Context::Visualize)?;
+    loop {
+        match user_action_event_rx.recv() {
+            // Main thread has disconnected, treat as cancelled
+            Err(_) | Ok(UserActionEvent::Cancel) => return Ok(RendererStatus::Cancelled),
+            Ok(UserActionEvent::HideHelp) => return Ok(RendererStatus::Completed),
+            Ok(UserActionEvent::Resize) => {
+                queue!(stdout, terminal::Clear(ClearType::All))?;
+                keymap::draw_help(stdout, Context::Visualize)?;
             }
-            stdout.flush()?;
+            // Ignore other events
+            Ok(_) => {}
         }
-        Ok(())
     }
 }
 
-pub enum RendererStatus {
-    /// Rendering completed successfully
-    Completed,
-    /// Rendering was cancelled
-    Cancelled,
-}
-
 /// Renderer to manage terminal rendering of grid events from the [`crate::app::visualize`] module.
 pub struct Renderer<'a> {
-    /// Standard output handle to write to the terminal.
+    /// Standard output handle to write to the terminal, copied to a recording if recording.
     /// Locked for exclusive access during rendering.
     /// This lock is held for the lifetime of the Renderer instance.
-    stdout: StdoutLock<'a>,
+    stdout: RenderOutput<'a>,
     /// History of grid events for browsing & recovery
     history: GridEventHistory,
     /// Compact current grid state (initial cell + diffs)
     grid_state: GridState,
     /// Render refresh time scale
     render_refresh_time_scale: RenderRefreshTimeScale,
+    /// How grid cells are currently colored
+    display_mode: DisplayMode,
+    /// Whether the viewport scrolls to keep the most recently updated cell in view
+    follow: bool,
+    /// Algorithms of each run, received from the grid event source
+    run_info_rx: Option<Receiver<RunInfo>>,
+    runs: Vec<RunInfo>,
+    /// Running time shown in the HUD
+    clock: RunClock,
+    /// Whether all grid events have been rendered
+    finished: bool,
+    /// Last time the HUD was drawn
+    hud_drawn_at: Option<Instant>,
 }
 
 impl<'a> Renderer<'a> {
     /// Create a new Renderer instance
-    /// `max_history_grid_events` specifies the maximum number of grid events to keep in history
+    /// `max_in_memory_history_events` specifies the maximum number of grid events of the history to
+    /// keep in memory before spilling older ones to disk
     /// `maze_dims` is an optional tuple of (width, height) to calibrate the render refresh time scale
-    pub fn new(max_history_grid_events: usize, maze_dims: Option<(u8, u8)>) -> Self {
+    /// `layout` specifies how grid cells are drawn on the terminal
+    pub fn new(
+        max_in_memory_history_events: usize,
+        maze_dims: Option<(u8, u8)>,
+        layout: CellLayout,
+    ) -> Self {
         Self {
-            stdout: std::io::stdout().lock(),
-            history: GridEventHistory::new(max_history_grid_events),
-            grid_state: GridState::default(),
+            stdout: RenderOutput::new(std::io::stdout().lock(), None, false),
+            history: GridEventHistory::new(max_in_memory_history_events),
+            grid_state: GridState::with_origin((0, 0), layout),
             render_refresh_time_scale: match maze_dims {
                 Some((width, height)) => RenderRefreshTimeScale::calibrated(width, height),
                 None => RenderRefreshTimeScale::default(),
             },
+            display_mode: DisplayMode::default(),
+            follow: true,
+            run_info_rx: None,
+            runs: Vec::new(),
+            clock: RunClock::start(),
+            finished: false,
+            hud_drawn_at: None,
         }
     }
 
-    /// Recover the grid state by replaying the history of grid events from the most recent initial event
-    /// to the current state.
-    /// This is when the renderer needs to revert past an initial event.
-    /// Returns:
-    /// - `Err` if there was an I/O error
-    /// - `Ok(true)` if recovery was performed
-    /// - `Ok(false)` if recovery was skipped due to missing initial event
-    fn recover_from_history(&mut self) -> std::io::Result<bool> {
-        // Walk backward to the most recent initial event or beginning of history
-        let mut counter = 0; // Count number of events to replay
-        let (width, height, cell) = loop {
-            if let Some(event) = self.history.history_backward() {
-                counter += 1;
-                if let GridEvent::Initial {
-                    width,
-                    height,
-                    cell,
-                } = event
-                {
-                    // Reached initial event, set grid state and break
-                    self.grid_state.initial = Some((cell, width, height));
-                    break (width, height, cell);
-                }
-            } else {
-                // No more events — exited normally
-                tracing::warn!(
-                    "No initial event found in history during recovery, skipping grid state recovery"
-                );
-                // Recover history position
-                for _ in 0..counter {
-                    self.history.history_forward();
-                }
-                return Ok(false);
-            }
-        };
+    /// Record everything rendered with the given recorder. If `fast` is set, rendering delays are
+    /// skipped and only advance the recording clock.
+    pub fn with_recorder(mut self, recorder: CastRecorder, fast: bool) -> Self {
+        self.stdout = RenderOutput::new(std::io::stdout().lock(), Some(recorder), fast);
+        self
+    }
 
-        // Render the initial filled grid
-        self.stdout.queue(cursor::MoveTo(0, 0))?;
-        for _y in 0..height {
-            for _x in 0..width {
-                self.stdout.queue(style::Print(cell))?;
-            }
-            self.stdout.queue(style::Print("\r\n"))?;
-        }
-        self.grid_state.add_event(GridEvent::Initial {
-            cell,
-            width,
-            height,
-        });
-        // Walk forward through the history to re-apply all update events
-        for _ in 0..counter {
-            let event = self.history.history_forward();
-            if let Some(GridEvent::Update { coord, old, new }) = event {
-                // Move the cursor to the specified coordinate and print the new cell
-                queue!(
-                    self.stdout,
-                    cursor::MoveTo(coord.0 * GridCell::CELL_WIDTH, coord.1),
-                    style::Print(new)
-                )?;
-                self.grid_state
-                    .add_event(GridEvent::Update { coord, old, new });
-            }
-        }
-        self.stdout.flush()?;
-        Ok(true)
+    /// Show the algorithms of each run in the HUD, as received before the grid events of the run
+    pub(super) fn with_run_info(mut self, run_info_rx: Receiver<RunInfo>) -> Self {
+        self.run_info_rx = Some(run_info_rx);
+        self
+    }
+
+    /// Start at the given speed level instead of the one calibrated to the grid dimensions
+    pub fn with_speed(mut self, speed: usize) -> Self {
+        self.render_refresh_time_scale.set_speed(speed);
+        self
     }
 
-    /// Check if terminal size is sufficient for the current grid dimensions (if set)
-    /// If not, display a message and wait for user to press Esc or resize the terminal.
+    /// Check if terminal size is sufficient to show at least part of the grid (if set), and fit the
+    /// viewport to the terminal. Grids larger than the terminal are scrolled.
+    /// If the terminal is too small, display a message and wait for user to press Esc or resize the terminal.
     /// Returns:
     /// - `Ok(RendererStatus::Completed)` if terminal size is sufficient
     /// - `Ok(RendererStatus::Cancelled)` if rendering was cancelled by user
@@ -289,80 +323,151 @@ impl<'a> Renderer<'a> {
         &mut self,
         user_action_event_rx: &Receiver<UserActionEvent>,
     ) -> std::io::Result<RendererStatus> {
-        match self.grid_state.dims() {
-            Some((width, height)) => {
-                let (term_width, term_height) = terminal::size()?;
-                if term_width < width * GridCell::CELL_WIDTH
-                    || term_height.saturating_sub(app::NUM_STATUS_ROWS) < height
-                {
-                    tracing::info!("Terminal size too small for grid display, pausing rendering");
-                    let msg = format!(
-                        "Terminal size is too small ({}x{}) for the grid dimensions ({}x{}) to display.\r\n",
-                        width * GridCell::CELL_WIDTH,
-                        height,
-                        width,
-                        height
-                    );
-                    queue!(
-                        self.stdout,
-                        terminal::Clear(ClearType::All),
-                        cursor::MoveTo(0, 0),
-                        style::PrintStyledContent(
-                            msg.with(Color::Yellow).attribute(Attribute::Bold)
-                        ),
-                        style::PrintStyledContent(
-                            "Please resize the terminal, or press Esc to exit...\r\n"
-                                .with(Color::Blue)
-                                .attribute(Attribute::Bold)
-                        )
-                    )?;
-                    self.stdout.flush()?;
+        if self.grid_state.dims().is_none() {
+            // No grid dimensions set, skip check
+            return Ok(RendererStatus::Completed);
+        }
+        // A single cell above the status rows is enough, the rest of the grid can be scrolled to
+        let layout = self.grid_state.layout();
+        let required = (layout.cell_width(), 1 + app::NUM_STATUS_ROWS);
+        let restored = match wait_for_terminal_fit(
+            &mut self.stdout,
+            required,
+            "any part of the grid",
+            user_action_event_rx,
+        )? {
+            TerminalFit::AlreadyFits => false,
+            TerminalFit::Restored => true,
+            TerminalFit::Cancelled => return Ok(RendererStatus::Cancelled),
+        };
+        let (term_width, term_height) = terminal::size()?;
+        self.stdout.resize((term_width, term_height))?;
+        let view_changed = self.grid_state.set_view_size((
+            term_width / layout.cell_width(),
+            term_height.saturating_sub(app::NUM_STATUS_ROWS) * layout.rows_per_line(),
+        ));
+        if restored || view_changed {
+            // Terminal resized, recover display
+            tracing::info!("Terminal resized, recovering grid display");
+            queue!(self.stdout, terminal::Clear(ClearType::All))?;
+            self.grid_state
+                .recover(&mut self.stdout, self.display_mode)?;
+            if self.grid_state.is_scrollable() {
+                self.log_view_indicator()?;
+            }
+            self.draw_hud(true)?;
+        }
+        Ok(RendererStatus::Completed)
+    }
 
-                    // Listen for user action events to resume or cancel
-                    loop {
-                        match user_action_event_rx.recv() {
-                            Err(_e) => {
-                                // Main thread has disconnected, treat as cancelled
-                                tracing::info!("Rendering cancelled due to terminal resize");
-                                return Ok(RendererStatus::Cancelled);
-                            }
-                            Ok(event) => match event {
-                                UserActionEvent::Cancel => {
-                                    tracing::info!(
-                                        "Rendering cancelled by user due to terminal resize"
-                                    );
-                                    return Ok(RendererStatus::Cancelled);
-                                }
-                                UserActionEvent::Resize => {
-                                    // Check terminal size again
-                                    let (new_term_width, new_term_height) = terminal::size()?;
-                                    if new_term_width >= width * GridCell::CELL_WIDTH
-                                        && new_term_height.saturating_sub(app::NUM_STATUS_ROWS)
-                                            >= height
-                                    {
-                                        // Terminal resized sufficiently, recover display
-                                        tracing::info!(
-                                            "Terminal resized sufficiently, recovering grid display"
-                                        );
-                                        self.grid_state.recover(&mut self.stdout)?;
-                                        break;
-                                    } else {
-                                        // Still too small, continue waiting
-                                        continue;
-                                    }
-                                }
-                                _ => {
-                                    // Ignore other events
-                                    continue;
-                                }
-                            },
-                        }
-                    }
-                }
-                Ok(RendererStatus::Completed)
+    /// Clear the terminal and draw the visible part of the grid from scratch
+    fn redraw(&mut self) -> std::io::Result<()> {
+        // Keep the visible region inside the grid, in case its dimensions changed
+        self.grid_state.pan(0, 0);
+        queue!(self.stdout, terminal::Clear(ClearType::All))?;
+        self.grid_state
+            .recover(&mut self.stdout, self.display_mode)?;
+        self.draw_hud(true)
+    }
+
+    /// Draw the HUD with the algorithms, phase, event counter, speed, running state and elapsed
+    /// time at the current position of the history. Unless `force` is set, the HUD is only drawn
+    /// if it hasn't been for a while, to keep up with fast animations.
+    fn draw_hud(&mut self, force: bool) -> std::io::Result<()> {
+        if !force
+            && self
+                .hud_drawn_at
+                .is_some_and(|drawn_at| drawn_at.elapsed() < HUD_REFRESH_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.hud_drawn_at = Some(Instant::now());
+
+        let (position, len) = (self.history.position(), self.history.len());
+        let last = position.checked_sub(1);
+        let mut parts = Vec::new();
+        // Information about each run is sent before its events, so it has arrived by now
+        if let Some(run) = last.and_then(|last| self.history.run_containing(last)) {
+            if let Some(run_info_rx) = &self.run_info_rx {
+                self.runs.extend(run_info_rx.try_iter());
+            }
+            if let Some(info) = self.runs.get(run) {
+                parts.push(format!("{} + {}", info.generator, info.solver));
+            }
+        }
+        if let Some((phase, _)) = last.and_then(|last| self.history.phase_containing(last)) {
+            parts.push(phase.to_string());
+        }
+        parts.push(format!("event {}/{}", position, len));
+        let (speed, levels) = self.render_refresh_time_scale.speed();
+        parts.push(format!("speed {}/{}", speed, levels));
+        parts.push(self.clock.hud_label(self.finished));
+        parts.push(format!("{} help", Keymap::current().keys(Action::Help)));
+
+        let grid_height = self
+            .grid_state
+            .screen_dims()
+            .map_or(0, |(_, height)| height);
+        app::print_status(
+            &mut self.stdout,
+            grid_height,
+            app::StatusRow::Hud,
+            Some(parts.join(" | ").with(theme::ui().muted)),
+        )
+    }
+
+    /// Move to the given position of the history and redraw the grid there. Positions past the
+    /// end of the history take in the grid events already available from the compute thread.
+    /// The oldest event is always kept applied.
+    fn seek(&mut self, target: usize, grid_event_rx: &Receiver<GridEvent>) -> std::io::Result<()> {
+        if target > self.history.len() {
+            // New events must be applied on top of the latest grid state
+            if self.history.position() != self.history.len() {
+                self.history
+                    .seek(self.history.len(), &mut self.grid_state)?;
             }
-            None => Ok(RendererStatus::Completed), // No grid dimensions set, skip check
+            while self.history.len() < target
+                && let Ok(event) = grid_event_rx.try_recv()
+            {
+                self.grid_state.add_event(event);
+                self.history.add_event(event, &self.grid_state)?;
+            }
+        }
+        let target = target.clamp(1.min(self.history.len()), self.history.len());
+        self.history.seek(target, &mut self.grid_state)?;
+        self.redraw()?;
+        self.log_progress()
+    }
+
+    /// Log a progress bar of the current position in the history, with the event index and total
+    fn log_progress(&mut self) -> std::io::Result<()> {
+        let (position, len) = (self.history.position(), self.history.len());
+        let phase = position
+            .checked_sub(1)
+            .and_then(|last| self.history.phase_containing(last))
+            .map_or(String::new(), |(phase, _)| format!(" {}", phase));
+        let label = format!(" {}/{}{}", position, len, phase);
+        let bar_width = (self.get_width()? as usize).saturating_sub(label.len());
+        let filled = (bar_width * position).checked_div(len).unwrap_or(0);
+        let (filled_char, empty_char) = GlyphTheme::current().bar();
+        let bar = format!(
+            "{}{}{}",
+            filled_char.to_string().repeat(filled),
+            empty_char.to_string().repeat(bar_width - filled),
+            label
+        );
+        self.log_to_terminal(bar.with(theme::ui().info))
+    }
+
+    /// Log which part of the grid is visible, and how to pan around
+    fn log_view_indicator(&mut self) -> std::io::Result<()> {
+        if let Some(indicator) = self.grid_state.view_indicator() {
+            let follow = if self.follow { "on" } else { "off" };
+            self.log_to_terminal(
+                format!("{} | follow {} (f)", indicator, follow).with(theme::ui().info),
+            )?;
         }
+        Ok(())
     }
 
     /// Render a single grid event to the terminal
@@ -387,59 +492,58 @@ impl<'a> Renderer<'a> {
                 width,
                 height,
             } => {
+                tracing::debug!("Rendering initial {}x{} grid of {:?}", width, height, cell);
+                self.grid_state.add_event(event);
+                // Fit the viewport to the new grid before drawing it
                 if let RendererStatus::Cancelled = self.check_resize(user_action_event_rx)? {
                     return Ok(RendererStatus::Cancelled);
                 }
-
-                self.stdout.queue(cursor::MoveTo(0, 0))?;
-                for _y in 0..height {
-                    for _x in 0..width {
-                        self.stdout.queue(style::Print(cell))?;
-                    }
-                    self.stdout.queue(style::Print("\r\n"))?;
-                }
-                self.stdout.flush()?;
+                self.grid_state.pan(0, 0);
+                self.grid_state
+                    .recover(&mut self.stdout, self.display_mode)?;
             }
-            GridEvent::Update { coord, new, .. } => {
+            GridEvent::Update { coord, .. } => {
+                // Always update compact grid state so recovery uses latest state
+                self.grid_state.add_event(event);
                 if self.grid_state.dims().is_some() {
                     if let RendererStatus::Cancelled = self.check_resize(user_action_event_rx)? {
                         return Ok(RendererStatus::Cancelled);
                     }
-                    // Move the cursor to the specified coordinate and print the
-                    // new cell using the grid dimensions
-                    queue!(
-                        self.stdout,
-                        cursor::MoveTo(coord.0 * GridCell::CELL_WIDTH, coord.1),
-                        style::Print(new)
-                    )?;
+                    if self.follow && self.grid_state.follow(coord) {
+                        // Scrolled to keep the updated cell in view, redraw everything
+                        self.grid_state
+                            .recover(&mut self.stdout, self.display_mode)?;
+                        self.log_view_indicator()?;
+                    } else {
+                        // Print the new cell from the grid state, so it is styled for the current display mode
+                        self.grid_state
+                            .draw_cell(&mut self.stdout, coord, self.display_mode)?;
+                    }
                     self.stdout.flush()?;
                 }
             }
         }
 
-        // Always update compact grid state so recovery uses latest state
-        self.grid_state.add_event(event);
-
         if save_to_history {
-            // Add event to history
-            self.history.add_event(event);
+            // Add event to history, along with the grid state it leads to for keyframes
+            self.history.add_event(event, &self.grid_state)?;
         }
         Ok(RendererStatus::Completed)
     }
 
     /// Get the current grid width in terminal columns
     fn get_width(&self) -> std::io::Result<u16> {
-        // Get grid width / terminal width for logging purposes
-        let width = match self.grid_state.dims() {
-            Some((w, _)) => w * GridCell::CELL_WIDTH,
+        // Get visible grid width / terminal width for logging purposes
+        let width = match self.grid_state.screen_dims() {
+            Some((w, _)) => w,
             None => terminal::size()?.0,
         };
         Ok(width)
     }
 
     fn clear_terminal_log(&mut self) -> std::io::Result<()> {
-        let grid_height = match self.grid_state.dims() {
-            // Move cursor right below the grid
+        let grid_height = match self.grid_state.screen_dims() {
+            // Move cursor right below the visible grid
             Some((_, height)) => height,
             // Default to top line if grid dimensions not yet set
             None => 0,
@@ -451,8 +555,8 @@ impl<'a> Renderer<'a> {
         &mut self,
         msg: style::StyledContent<impl Display + AsRef<str>>,
     ) -> std::io::Result<()> {
-        let grid_height = match self.grid_state.dims() {
-            // Move cursor right below the grid
+        let grid_height = match self.grid_state.screen_dims() {
+            // Move cursor right below the visible grid
             Some((_, height)) => height,
             // Default to top line if grid dimensions not yet set
             None => 0,
@@ -460,6 +564,37 @@ impl<'a> Renderer<'a> {
         app::log_terminal(&mut self.stdout, grid_height, Some(msg))
     }
 
+    /// Switch to the given display mode, or back to [`DisplayMode::Normal`] if it is already active,
+    /// then redraw the whole grid in the new display mode
+    fn toggle_display_mode(&mut self, mode: DisplayMode) -> std::io::Result<()> {
+        if theme::Theme::current().no_color {
+            return self.log_to_terminal(
+                "Heatmap and visit order views need colors, which NO_COLOR turns off"
+                    .with(theme::ui().accent),
+            );
+        }
+        self.display_mode = if self.display_mode == mode {
+            DisplayMode::Normal
+        } else {
+            mode
+        };
+        tracing::debug!("Switched display mode to {:?}", self.display_mode);
+        self.grid_state
+            .recover(&mut self.stdout, self.display_mode)?;
+        let msg = match self.display_mode {
+            DisplayMode::Heatmap => format!(
+                "Heatmap view on: visited cells colored by distance from start (max {})",
+                self.grid_state.max_distance
+            ),
+            DisplayMode::VisitOrder => format!(
+                "Visit order view on: visited cells colored from oldest to newest ({} visited)",
+                self.grid_state.visit_count
+            ),
+            DisplayMode::Normal => "Normal view".to_string(),
+        };
+        self.log_to_terminal(msg.with(theme::ui().info))
+    }
+
     /// Handle a single user action event in the paused state
     /// Returns `Ok(RendererStatus::Completed)` if rendering was completed successfully
     /// Returns `Ok(RendererStatus::Cancelled)` if rendering was cancelled
@@ -472,18 +607,19 @@ impl<'a> Renderer<'a> {
     ) -> std::io::Result<RendererStatus> {
         match event {
             UserActionEvent::Resume => {
+                self.clock.resume();
                 // Clear any log messages
                 self.clear_terminal_log()?;
                 tracing::debug!("Resuming rendering from pause");
                 // Step forward in the history and exit pause loop
-                while let Some(event) = self.history.history_forward() {
+                while let Some(event) = self.history.history_forward()? {
                     if let RendererStatus::Cancelled =
                         self.render_grid_event(event, user_action_event_rx, false)?
                     {
                         return Ok(RendererStatus::Cancelled);
                     }
-                    // Do we sleep here to simulate rendering time?
-                    std::thread::sleep(self.render_refresh_time_scale.current());
+                    // Wait to simulate rendering time, which also times the recording
+                    self.stdout.delay(self.render_refresh_time_scale.current());
                 }
             }
             UserActionEvent::Pause => {
@@ -491,7 +627,7 @@ impl<'a> Renderer<'a> {
             }
             UserActionEvent::Forward => {
                 // Step forward and get the event
-                let event = self.history.history_forward();
+                let event = self.history.history_forward()?;
                 if let Some(event) = event {
                     tracing::debug!("Rendering history forward event: {:?}", event);
                     if let RendererStatus::Cancelled =
@@ -499,7 +635,7 @@ impl<'a> Renderer<'a> {
                     {
                         return Ok(RendererStatus::Cancelled);
                     }
-                    self.log_to_terminal(event.with(Color::Green))?;
+                    self.log_to_terminal(event.with(theme::ui().success))?;
                 } else {
                     tracing::debug!("Attempting to step into the future");
                     match grid_event_rx.try_recv() {
@@ -510,13 +646,13 @@ impl<'a> Renderer<'a> {
                             {
                                 return Ok(RendererStatus::Cancelled);
                             }
-                            self.log_to_terminal(event.with(Color::Green))?;
+                            self.log_to_terminal(event.with(theme::ui().success))?;
                         }
                         Err(std::sync::mpsc::TryRecvError::Empty) => {
                             // No action event, continue
                             tracing::debug!("No future event available at the moment");
                             self.log_to_terminal(
-                                "No future event available at the moment".with(Color::Yellow),
+                                "No future event available at the moment".with(theme::ui().accent),
                             )?;
                         }
                         Err(std::sync::mpsc::TryRecvError::Disconnected) => {
@@ -526,77 +662,96 @@ impl<'a> Renderer<'a> {
                             tracing::debug!("Grid event channel disconnected");
                             self.log_to_terminal(
                                 "Grid event channel disconnected. Resume to exit the rendering"
-                                    .with(Color::Red),
+                                    .with(theme::ui().error),
                             )?;
                         }
                     };
                 }
             }
             UserActionEvent::Backward => {
-                // Get the current event
-                if let Some(event) = self.history.current_event() {
-                    // Craft the revert event
-                    match event {
-                        GridEvent::Initial { .. } => {
-                            if self.history.history_backward().is_none() {
-                                // Cannot go back further
-                                tracing::debug!("Already at the oldest event, cannot go backward");
-                                self.log_to_terminal(
-                                    "Already at the oldest event, cannot go backward"
-                                        .with(Color::Yellow),
-                                )?;
-                            } else {
-                                // Recover the grid state up to right before this initial event
-                                tracing::debug!(
-                                    "Reverting to state before initial event, recovering grid state"
-                                );
-                                if !self.recover_from_history()? {
-                                    // Recovery skipped due to missing initial event
-                                    self.log_to_terminal(
-                                        "No initial event found in history during revert, cannot go backward"
-                                            .with(Color::Yellow),
-                                    )?;
-                                    self.history.history_forward(); // Move back to current position
-                                } else {
-                                    self.log_to_terminal(
-                                        "Reverted to state before initial event"
-                                            .with(Color::Yellow),
-                                    )?;
-                                }
-                            }
-                        }
-                        GridEvent::Update { coord, old, new } => {
-                            // Move backward in history
-                            if self.history.history_backward().is_none() {
-                                // Cannot go back further
-                                self.log_to_terminal(
-                                    "Already at the oldest event after revert".with(Color::Yellow),
-                                )?;
-                            } else {
-                                // Only render the revert event when not at the oldest event
-                                let revert_event = GridEvent::Update {
-                                    coord,
-                                    old: new,
-                                    new: old,
-                                };
-
-                                tracing::debug!(
-                                    "Rendering history backward event: {:?}",
-                                    revert_event
-                                );
-                                if let RendererStatus::Cancelled = self.render_grid_event(
-                                    revert_event,
-                                    user_action_event_rx,
-                                    false,
-                                )? {
-                                    return Ok(RendererStatus::Cancelled);
-                                }
-                                self.log_to_terminal(revert_event.with(Color::Yellow))?;
-                            }
+                // Step back and get the event to revert
+                match self.history.history_backward()? {
+                    None => {
+                        // Cannot go back further
+                        tracing::debug!("Already at the oldest event, cannot go backward");
+                        self.log_to_terminal(
+                            "Already at the oldest event, cannot go backward"
+                                .with(theme::ui().accent),
+                        )?;
+                    }
+                    Some(GridEvent::Initial { .. }) => {
+                        // The grid before an initial event can't be derived from the event itself,
+                        // so recover it from the nearest keyframe of the history
+                        tracing::debug!(
+                            "Reverting to state before initial event, recovering grid state"
+                        );
+                        let position = self.history.position();
+                        self.history.seek(position, &mut self.grid_state)?;
+                        self.redraw()?;
+                        self.log_to_terminal(
+                            "Reverted to state before initial event".with(theme::ui().accent),
+                        )?;
+                    }
+                    Some(GridEvent::Update { coord, old, new }) => {
+                        let revert_event = GridEvent::Update {
+                            coord,
+                            old: new,
+                            new: old,
+                        };
+
+                        tracing::debug!("Rendering history backward event: {:?}", revert_event);
+                        if let RendererStatus::Cancelled =
+                            self.render_grid_event(revert_event, user_action_event_rx, false)?
+                        {
+                            return Ok(RendererStatus::Cancelled);
                         }
+                        self.log_to_terminal(revert_event.with(theme::ui().accent))?;
+                    }
+                }
+            }
+            UserActionEvent::JumpBackward => {
+                let target = self.history.position().saturating_sub(JUMP_EVENTS);
+                self.seek(target, grid_event_rx)?;
+            }
+            UserActionEvent::JumpForward => {
+                let target = self.history.position() + JUMP_EVENTS;
+                self.seek(target, grid_event_rx)?;
+            }
+            UserActionEvent::PhaseStart => {
+                let position = self.history.position();
+                if let Some((_, range)) = position
+                    .checked_sub(1)
+                    .and_then(|last| self.history.phase_containing(last))
+                {
+                    // Show the first event of the phase applied, or go to the previous phase
+                    let target = match range.start.checked_sub(1) {
+                        Some(previous) if position == range.start + 1 => self
+                            .history
+                            .phase_containing(previous)
+                            .map_or(position, |(_, previous)| previous.start + 1),
+                        _ => range.start + 1,
+                    };
+                    self.seek(target, grid_event_rx)?;
+                }
+            }
+            UserActionEvent::PhaseEnd => {
+                let position = self.history.position();
+                if let Some((_, range)) = position
+                    .checked_sub(1)
+                    .and_then(|last| self.history.phase_containing(last))
+                {
+                    // Show all events of the phase applied, or go to the next phase
+                    let target = match self.history.phase_containing(range.end) {
+                        Some((_, next)) if position == range.end => next.end,
+                        _ => range.end,
                     };
+                    self.seek(target, grid_event_rx)?;
                 }
             }
+            UserActionEvent::SeekPercent(percent) => {
+                let target = self.history.len() * *percent as usize / 100;
+                self.seek(target, grid_event_rx)?;
+            }
             UserActionEvent::SpeedUp => {
                 // Increase rendering speed (decrease refresh time)
                 self.render_refresh_time_scale.speed_up();
@@ -630,6 +785,67 @@ impl<'a> Renderer<'a> {
                         .stylize(),
                 )?;
             }
+            UserActionEvent::ToggleHeatmap => {
+                self.toggle_display_mode(DisplayMode::Heatmap)?;
+            }
+            UserActionEvent::ToggleVisitOrder => {
+                self.toggle_display_mode(DisplayMode::VisitOrder)?;
+            }
+            UserActionEvent::Pan { direction, page } => {
+                let (visible_width, visible_height) =
+                    self.grid_state.visible_dims().unwrap_or((0, 0));
+                // Pan by one maze cell, or by half the visible region for a page
+                let (step_x, step_y) = if *page {
+                    (
+                        (visible_width / 2).max(1) as i32,
+                        (visible_height / 2).max(1) as i32,
+                    )
+                } else {
+                    (2, 2)
+                };
+                let (dx, dy) = match direction {
+                    PanDirection::Left => (-step_x, 0),
+                    PanDirection::Right => (step_x, 0),
+                    PanDirection::Up => (0, -step_y),
+                    PanDirection::Down => (0, step_y),
+                };
+                if self.grid_state.pan(dx, dy) {
+                    self.grid_state
+                        .recover(&mut self.stdout, self.display_mode)?;
+                }
+                if self.grid_state.is_scrollable() {
+                    self.log_view_indicator()?;
+                } else {
+                    self.log_to_terminal(
+                        "The whole grid is already visible".with(theme::ui().accent),
+                    )?;
+                }
+            }
+            UserActionEvent::ToggleFollow => {
+                self.follow = !self.follow;
+                tracing::debug!("Viewport follow set to {}", self.follow);
+                self.log_view_indicator()?;
+            }
+            UserActionEvent::ShowHelp => {
+                let waiting = Instant::now();
+                let status = show_help(&mut self.stdout, user_action_event_rx)?;
+                // Keep the time spent reading the help in the recording
+                self.stdout.elapsed(waiting.elapsed());
+                if let RendererStatus::Cancelled = status {
+                    return Ok(RendererStatus::Cancelled);
+                }
+                // Fit the viewport to the terminal in case it was resized, then draw the grid again
+                if let RendererStatus::Cancelled = self.check_resize(user_action_event_rx)? {
+                    return Ok(RendererStatus::Cancelled);
+                }
+                self.redraw()?;
+                if self.grid_state.is_scrollable() {
+                    self.log_view_indicator()?;
+                }
+            }
+            UserActionEvent::HideHelp => {
+                // Only sent while the help is shown, already handled
+            }
             UserActionEvent::Cancel => {
                 // Clear any log messages
                 self.clear_terminal_log()?;
@@ -638,6 +854,7 @@ impl<'a> Renderer<'a> {
                 return Ok(RendererStatus::Cancelled);
             }
         };
+        self.draw_hud(true)?;
         Ok(RendererStatus::Completed)
     }
 
@@ -650,7 +867,11 @@ impl<'a> Renderer<'a> {
     ) -> std::io::Result<RendererStatus> {
         // Pause rendering until Resume event is received
         loop {
-            match user_action_event_rx.recv() {
+            let waiting = Instant::now();
+            let event = user_action_event_rx.recv();
+            // Keep the time spent paused in the recording
+            self.stdout.elapsed(waiting.elapsed());
+            match event {
                 Err(_e) => {
                     // Main thread has disconnected, exit pause loop
                     break;
@@ -703,6 +924,9 @@ impl<'a> Renderer<'a> {
                         UserActionEvent::Pause => {
                             // Block and handle subsequent user action events
                             tracing::info!("Pausing rendering on user request");
+                            self.clock.pause();
+                            self.log_progress()?;
+                            self.draw_hud(true)?;
                             if let RendererStatus::Cancelled = self.listen_to_user_action_events(
                                 &user_action_event_rx,
                                 &grid_event_rx,
@@ -714,6 +938,11 @@ impl<'a> Renderer<'a> {
                         UserActionEvent::SpeedUp
                         | UserActionEvent::SlowDown
                         | UserActionEvent::Resize
+                        | UserActionEvent::ToggleHeatmap
+                        | UserActionEvent::ToggleVisitOrder
+                        | UserActionEvent::Pan { .. }
+                        | UserActionEvent::ToggleFollow
+                        | UserActionEvent::ShowHelp
                         | UserActionEvent::Cancel => {
                             // Handle these events immediately
                             tracing::info!(
@@ -756,13 +985,16 @@ impl<'a> Renderer<'a> {
                         return Ok(RendererStatus::Cancelled);
                     }
 
-                    // Sleep a bit to simulate rendering time
-                    std::thread::sleep(self.render_refresh_time_scale.current());
+                    self.draw_hud(false)?;
+                    // Wait a bit to simulate rendering time, which also times the recording
+                    self.stdout.delay(self.render_refresh_time_scale.current());
                 }
             }
         }
+        self.finished = true;
+        self.draw_hud(true)?;
         // Move cursor below the maze after exiting
-        if let Some((_, height)) = self.grid_state.dims() {
+        if let Some((_, height)) = self.grid_state.screen_dims() {
             queue!(self.stdout, cursor::MoveTo(0, height))?;
             self.stdout.flush()?;
         }
@@ -0,0 +1,225 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::mpsc::{Receiver, SyncSender},
+    thread::JoinHandle,
+};
+
+use crate::{generators::Generator, maze::grid::GridEvent, solvers::Solver};
+
+use super::MAX_EVENTS_IN_CHANNEL_BUFFER;
+
+/// First line of every replay file, identifying the format and its version
+const MAGIC: &str = "mazest replay v1";
+
+/// Description of the recorded run, stored as `key value` lines at the start of a replay file.
+/// The header ends with an empty line, followed by the grid events encoded with [`GridEvent::encode`].
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ReplayHeader {
+    /// Maze dimensions (width, height)
+    pub(super) size: (u8, u8),
+    pub(super) generator: Generator,
+    pub(super) solver: Solver,
+    /// Seed the maze was generated with
+    pub(super) seed: u64,
+}
+
+impl ReplayHeader {
+    fn write(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writeln!(writer, "{}", MAGIC)?;
+        writeln!(writer, "size {}x{}", self.size.0, self.size.1)?;
+        writeln!(writer, "generator {}", self.generator)?;
+        writeln!(writer, "solver {}", self.solver)?;
+        writeln!(writer, "seed {}", self.seed)?;
+        writeln!(writer)
+    }
+
+    /// Read the header, leaving the reader at the first event
+    fn read(reader: &mut impl BufRead) -> std::io::Result<Self> {
+        let invalid =
+            |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if line.trim_end() != MAGIC {
+            return Err(invalid("Not a mazest replay file".to_string()));
+        }
+        let (mut size, mut generator, mut solver, mut seed) = (None, None, None, None);
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid("Replay file ends in its header".to_string()));
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "size" => {
+                    let parsed = value.split_once('x').and_then(|(width, height)| {
+                        Some((width.parse::<u8>().ok()?, height.parse::<u8>().ok()?))
+                    });
+                    size = Some(parsed.ok_or_else(|| invalid(format!("Invalid size: {}", value)))?);
+                }
+                "generator" => generator = Some(value.parse().map_err(invalid)?),
+                "solver" => solver = Some(value.parse().map_err(invalid)?),
+                "seed" => {
+                    seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| invalid(format!("Invalid seed: {}", value)))?,
+                    )
+                }
+                // Skip unknown keys, so newer files with extra information still replay
+                _ => tracing::warn!("Unknown replay header line: {}", line),
+            }
+        }
+        match (size, generator, solver, seed) {
+            (Some(size), Some(generator), Some(solver), Some(seed)) => Ok(Self {
+                size,
+                generator,
+                solver,
+                seed,
+            }),
+            _ => Err(invalid(
+                "Replay header is missing the size, generator, solver or seed".to_string(),
+            )),
+        }
+    }
+}
+
+/// Writes the grid events of a run to a replay file, while passing them on to the renderer
+pub(super) struct ReplayRecorder {
+    writer: BufWriter<File>,
+}
+
+impl ReplayRecorder {
+    /// Create the replay file at the given path and write the header
+    pub(super) fn create(path: &Path, header: &ReplayHeader) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        header.write(&mut writer)?;
+        tracing::info!("Saving replay to {}", path.display());
+        Ok(Self { writer })
+    }
+
+    /// Spawn a thread recording the events sent to the returned sender, and forwarding them to `grid_event_tx`.
+    /// The thread finishes once the returned sender is dropped. Events keep being recorded if the
+    /// renderer stops early, so the replay always holds the whole run.
+    pub(super) fn spawn(
+        self,
+        grid_event_tx: SyncSender<GridEvent>,
+    ) -> (SyncSender<GridEvent>, JoinHandle<std::io::Result<()>>) {
+        let (recorder_tx, recorder_rx) =
+            std::sync::mpsc::sync_channel::<GridEvent>(MAX_EVENTS_IN_CHANNEL_BUFFER);
+        let handle = std::thread::spawn(move || self.record(recorder_rx, grid_event_tx));
+        (recorder_tx, handle)
+    }
+
+    fn record(
+        mut self,
+        grid_event_rx: Receiver<GridEvent>,
+        grid_event_tx: SyncSender<GridEvent>,
+    ) -> std::io::Result<()> {
+        let mut forwarding = true;
+        for event in grid_event_rx {
+            self.writer.write_all(&event.encode())?;
+            forwarding = forwarding && grid_event_tx.send(event).is_ok();
+        }
+        self.writer.flush()
+    }
+}
+
+/// Open a replay file, reading its header
+pub(super) fn open(path: &Path) -> std::io::Result<(ReplayHeader, BufReader<File>)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let header = ReplayHeader::read(&mut reader)?;
+    Ok((header, reader))
+}
+
+/// Send the events of a replay to the renderer, until the end of the file or the renderer stops.
+/// Returns the number of events sent.
+pub(super) fn send_events(
+    mut reader: impl Read,
+    grid_event_tx: SyncSender<GridEvent>,
+) -> std::io::Result<usize> {
+    let mut sent = 0;
+    loop {
+        let mut bytes = [0; GridEvent::ENCODED_LEN];
+        let mut filled = 0;
+        while filled < bytes.len() {
+            match reader.read(&mut bytes[filled..])? {
+                0 if filled == 0 => return Ok(sent),
+                0 => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "Replay file ends in the middle of an event",
+                    ));
+                }
+                n => filled += n,
+            }
+        }
+        let event = GridEvent::decode(&bytes).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid event #{} in replay file", sent + 1),
+            )
+        })?;
+        if grid_event_tx.send(event).is_err() {
+            // Renderer has stopped
+            return Ok(sent);
+        }
+        sent += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::cell::GridCell;
+
+    #[test]
+    fn header_and_events_round_trip() {
+        let header = ReplayHeader {
+            size: (40, 25),
+            generator: Generator::Kruskal,
+            solver: Solver::Dijkstra,
+            seed: 42,
+        };
+        let events = [
+            GridEvent::Initial {
+                cell: GridCell::WALL,
+                width: 81,
+                height: 51,
+            },
+            GridEvent::Update {
+                coord: (1, 1),
+                old: GridCell::WALL,
+                new: GridCell::EMPTY,
+            },
+        ];
+        let mut bytes = Vec::new();
+        header.write(&mut bytes).unwrap();
+        let events_start = bytes.len();
+        for event in &events {
+            bytes.extend_from_slice(&event.encode());
+        }
+
+        let mut reader = bytes.as_slice();
+        let read = ReplayHeader::read(&mut reader).unwrap();
+        assert_eq!(read.size, (40, 25));
+        assert!(matches!(read.generator, Generator::Kruskal));
+        assert!(matches!(read.solver, Solver::Dijkstra));
+        assert_eq!(read.seed, 42);
+        assert_eq!(reader.len(), bytes.len() - events_start);
+
+        let (tx, rx) = std::sync::mpsc::sync_channel(events.len());
+        assert_eq!(send_events(reader, tx).unwrap(), events.len());
+        let received: Vec<_> = rx.iter().map(|event| event.encode()).collect();
+        assert_eq!(received, events.map(|event| event.encode()));
+
+        // A file cut off in the middle of an event is an error
+        let (tx, _rx) = std::sync::mpsc::sync_channel(events.len());
+        assert!(send_events(&bytes[events_start..bytes.len() - 1], tx).is_err());
+    }
+}
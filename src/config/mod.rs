@@ -0,0 +1,195 @@
+//! User configuration files, read from `$XDG_CONFIG_HOME/mazest` or `~/.config/mazest`, and
+//! data files written by the app to `$XDG_DATA_HOME/mazest` or `~/.local/share/mazest`.
+
+pub mod theme;
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{Action, AppMode, KeyBinding},
+    generators::Generator,
+    maze::cell::GlyphTheme,
+    solvers::Solver,
+};
+
+/// Name of the config file in the configuration directory
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Directory of the configuration files, or None if neither `XDG_CONFIG_HOME` nor `HOME` is set
+pub fn config_dir() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+    let base = match var("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(var("HOME")?).join(".config"),
+    };
+    Some(base.join("mazest"))
+}
+
+/// Directory of the data files, or None if neither `XDG_DATA_HOME` nor `HOME` is set
+pub fn data_dir() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+    let base = match var("XDG_DATA_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(var("HOME")?).join(".local").join("share"),
+    };
+    Some(base.join("mazest"))
+}
+
+/// Defaults of a launch, loaded from the config file. Menu values are preselected, and unset
+/// values fall back to the built-in defaults.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// App mode, `visualize` or `game`
+    #[serde(with = "by_name", skip_serializing_if = "Option::is_none")]
+    pub mode: Option<AppMode>,
+    /// Maze width
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u8>,
+    /// Maze height
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u8>,
+    #[serde(with = "by_name", skip_serializing_if = "Option::is_none")]
+    pub generator: Option<Generator>,
+    #[serde(with = "by_name", skip_serializing_if = "Option::is_none")]
+    pub solver: Option<Solver>,
+    /// Whether to loop maze generation and solving
+    #[serde(rename = "loop", skip_serializing_if = "Option::is_none")]
+    pub loop_animation: Option<bool>,
+    /// Initial animation speed, from 1 (slowest) to 20 (fastest)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<usize>,
+    /// Number of grid events of the rendering history kept in memory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_events: Option<usize>,
+    /// Glyph theme, detected from the terminal if unset
+    #[serde(with = "by_name", skip_serializing_if = "Option::is_none")]
+    pub glyphs: Option<GlyphTheme>,
+    /// Keys of the actions to remap, replacing their default keys
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<Action, Vec<KeyBinding>>,
+}
+
+impl Config {
+    fn path() -> std::io::Result<PathBuf> {
+        config_dir()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "No configuration directory, set HOME or XDG_CONFIG_HOME",
+                )
+            })
+    }
+
+    /// Load the config file, or the built-in defaults if there is none
+    pub fn load() -> std::io::Result<Self> {
+        let path = match Config::path() {
+            Ok(path) if path.exists() => path,
+            _ => return Ok(Config::default()),
+        };
+        let contents = std::fs::read_to_string(&path)?;
+        let config = toml::from_str(&contents).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid config file {}: {}", path.display(), e.message()),
+            )
+        })?;
+        tracing::info!("Loaded config from {}", path.display());
+        Ok(config)
+    }
+
+    /// Write the config file, returning its path
+    pub fn save(&self) -> std::io::Result<PathBuf> {
+        let path = Config::path()?;
+        let contents = toml::to_string(self).map_err(std::io::Error::other)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, contents)?;
+        tracing::info!("Saved config to {}", path.display());
+        Ok(path)
+    }
+}
+
+/// Serialize values by their display name, and parse them back with `FromStr`
+pub(crate) mod by_name {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_str(&value.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: FromStr<Err = String>,
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|name| name.parse().map_err(D::Error::custom))
+            .transpose()
+    }
+
+    /// Like [`serialize`], for values that are always set
+    pub fn serialize_value<T: Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    /// Like [`deserialize`], for values that are always set
+    pub fn deserialize_value<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr<Err = String>,
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_round_trips_through_toml() {
+        let config = Config {
+            mode: Some(AppMode::Game),
+            width: Some(30),
+            generator: Some(Generator::Prim),
+            solver: Some(Solver::AStar),
+            loop_animation: Some(false),
+            glyphs: Some(GlyphTheme::Ascii),
+            ..Default::default()
+        };
+        let contents = toml::to_string(&config).unwrap();
+        assert!(!contents.contains("height"));
+        assert_eq!(toml::from_str::<Config>(&contents).unwrap(), config);
+
+        let config: Config = toml::from_str(
+            "generator = \"kruskal\"\nloop = true\n[keys]\npause = [\"Space\", \"p\"]",
+        )
+        .unwrap();
+        assert_eq!(config.generator, Some(Generator::Kruskal));
+        assert_eq!(config.loop_animation, Some(true));
+        assert_eq!(config.keys[&Action::Pause].len(), 2);
+        let contents = toml::to_string(&config).unwrap();
+        assert_eq!(toml::from_str::<Config>(&contents).unwrap(), config);
+        assert!(toml::from_str::<Config>("generator = \"maze\"").is_err());
+        assert!(toml::from_str::<Config>("colour = \"red\"").is_err());
+        assert!(toml::from_str::<Config>("[keys]\npause = [\"Hyper+p\"]").is_err());
+    }
+}
@@ -0,0 +1,607 @@
+//! Color themes for grid cells and interface text.
+//!
+//! The theme starts from a [`Preset`], optionally customized by a `theme.toml` file in the
+//! configuration directory, e.g.:
+//!
+//! ```toml
+//! preset = "colorblind"
+//!
+//! [cells]
+//! start = "#0072b2"   # RGB
+//! goal = 214          # 256-color palette index
+//!
+//! [ui]
+//! accent = "dark_yellow"  # named terminal color
+//! ```
+//!
+//! Colors are reduced to what the terminal supports, and the `NO_COLOR` environment variable
+//! disables them altogether.
+
+use std::sync::OnceLock;
+
+use crossterm::style::Color;
+use serde::Deserialize;
+
+use crate::maze::cell::DoorColor;
+
+/// Name of the theme file in the configuration directory
+pub const THEME_FILE: &str = "theme.toml";
+
+/// Theme used by the whole app, installed once at startup
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Colors of grid cells
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellColors {
+    pub route: Color,
+    pub visited: Color,
+    pub start: Color,
+    pub goal: Color,
+    pub pacman: Color,
+    pub ghost: Color,
+    pub opponent: Color,
+    pub second_player: Color,
+    pub pellet: Color,
+    /// Colors of the doors and of the keys unlocking them
+    pub red_door: Color,
+    pub green_door: Color,
+    pub blue_door: Color,
+    pub wall: Color,
+    pub mark: Color,
+}
+
+impl CellColors {
+    /// Color of the doors of the given color and of their keys
+    pub fn door(&self, color: DoorColor) -> Color {
+        match color {
+            DoorColor::Red => self.red_door,
+            DoorColor::Green => self.green_door,
+            DoorColor::Blue => self.blue_door,
+        }
+    }
+}
+
+/// Colors of interface text, by role
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiColors {
+    /// Titles, menu prompts and notices
+    pub accent: Color,
+    /// Controls, status indicators and input prompts
+    pub info: Color,
+    /// Selections and good outcomes
+    pub success: Color,
+    /// Errors and invalid input
+    pub error: Color,
+    /// Instructions such as how to exit
+    pub hint: Color,
+    /// Secondary details
+    pub muted: Color,
+}
+
+/// Built-in themes
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Preset {
+    /// The classic terminal colors
+    #[default]
+    Default,
+    /// Bright, saturated colors for low-contrast screens and low vision
+    HighContrast,
+    /// The Okabe-Ito palette, telling start and goal apart as blue and orange
+    Colorblind,
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Preset::Default => write!(f, "default"),
+            Preset::HighContrast => write!(f, "high-contrast"),
+            Preset::Colorblind => write!(f, "colorblind"),
+        }
+    }
+}
+
+impl std::str::FromStr for Preset {
+    type Err = String;
+
+    /// Parse a preset from its name, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "default" => Ok(Preset::Default),
+            "high-contrast" => Ok(Preset::HighContrast),
+            "colorblind" => Ok(Preset::Colorblind),
+            _ => Err(format!(
+                "Unknown theme '{}', expected one of: default, high-contrast, colorblind",
+                s
+            )),
+        }
+    }
+}
+
+const fn rgb_color(r: u8, g: u8, b: u8) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+impl Preset {
+    fn colors(self) -> (CellColors, UiColors) {
+        match self {
+            Preset::Default => (
+                CellColors {
+                    route: Color::Yellow,
+                    visited: Color::Blue,
+                    start: Color::Green,
+                    goal: Color::Red,
+                    pacman: Color::Yellow,
+                    ghost: Color::Cyan,
+                    opponent: Color::Magenta,
+                    second_player: Color::Green,
+                    pellet: Color::White,
+                    red_door: Color::Red,
+                    green_door: Color::DarkGreen,
+                    blue_door: Color::Blue,
+                    wall: Color::White,
+                    mark: Color::Magenta,
+                },
+                UiColors {
+                    accent: Color::Yellow,
+                    info: Color::Cyan,
+                    success: Color::Green,
+                    error: Color::Red,
+                    hint: Color::Blue,
+                    muted: Color::DarkGrey,
+                },
+            ),
+            Preset::HighContrast => (
+                CellColors {
+                    route: rgb_color(255, 255, 0),
+                    visited: rgb_color(80, 160, 255),
+                    start: rgb_color(0, 255, 0),
+                    goal: rgb_color(255, 40, 40),
+                    pacman: rgb_color(255, 255, 0),
+                    ghost: rgb_color(0, 255, 255),
+                    opponent: rgb_color(255, 128, 0),
+                    second_player: rgb_color(0, 255, 128),
+                    pellet: rgb_color(255, 200, 160),
+                    red_door: rgb_color(255, 40, 40),
+                    green_door: rgb_color(0, 160, 0),
+                    blue_door: rgb_color(80, 160, 255),
+                    wall: rgb_color(255, 255, 255),
+                    mark: rgb_color(255, 0, 255),
+                },
+                UiColors {
+                    accent: rgb_color(255, 255, 0),
+                    info: rgb_color(0, 255, 255),
+                    success: rgb_color(0, 255, 0),
+                    error: rgb_color(255, 80, 80),
+                    hint: rgb_color(255, 255, 255),
+                    muted: rgb_color(200, 200, 200),
+                },
+            ),
+            Preset::Colorblind => (
+                CellColors {
+                    route: rgb_color(240, 228, 66),
+                    visited: rgb_color(86, 180, 233),
+                    start: rgb_color(0, 114, 178),
+                    goal: rgb_color(230, 159, 0),
+                    pacman: rgb_color(240, 228, 66),
+                    ghost: rgb_color(204, 121, 167),
+                    opponent: rgb_color(0, 158, 115),
+                    second_player: rgb_color(213, 94, 0),
+                    pellet: rgb_color(255, 255, 255),
+                    // Doors only share their colors with single cells or ghosts, never with the
+                    // players or the opponent
+                    red_door: rgb_color(230, 159, 0),
+                    green_door: rgb_color(204, 121, 167),
+                    blue_door: rgb_color(0, 114, 178),
+                    wall: rgb_color(255, 255, 255),
+                    mark: rgb_color(213, 94, 0),
+                },
+                UiColors {
+                    accent: rgb_color(240, 228, 66),
+                    info: rgb_color(86, 180, 233),
+                    success: rgb_color(0, 158, 115),
+                    error: rgb_color(213, 94, 0),
+                    hint: rgb_color(204, 121, 167),
+                    muted: Color::DarkGrey,
+                },
+            ),
+        }
+    }
+}
+
+/// Number of colors the terminal can display
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColorDepth {
+    /// 24-bit RGB colors
+    TrueColor,
+    /// The xterm 256-color palette
+    Ansi256,
+    /// The 16 named terminal colors
+    Ansi16,
+}
+
+impl ColorDepth {
+    /// Detect the color depth from the `COLORTERM` and `TERM` environment variables
+    fn detect() -> Self {
+        let var = |name: &str| std::env::var(name).unwrap_or_default().to_lowercase();
+        let (colorterm, term) = (var("COLORTERM"), var("TERM"));
+        if colorterm == "truecolor" || colorterm == "24bit" || term.contains("direct") {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    /// Closest color the terminal can display
+    fn adapt(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorDepth::Ansi256, Color::Rgb { r, g, b }) => Color::AnsiValue(ansi256([r, g, b])),
+            (ColorDepth::Ansi16, Color::Rgb { .. } | Color::AnsiValue(_)) => ansi16(rgb(color)),
+            _ => color,
+        }
+    }
+}
+
+/// Channel values of the 6x6x6 color cube of the 256-color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+/// The 16 named colors, in palette order
+const NAMED_COLORS: [Color; 16] = [
+    Color::Black,
+    Color::DarkRed,
+    Color::DarkGreen,
+    Color::DarkYellow,
+    Color::DarkBlue,
+    Color::DarkMagenta,
+    Color::DarkCyan,
+    Color::Grey,
+    Color::DarkGrey,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+];
+
+/// RGB value of a terminal color, using the xterm defaults for named and palette colors
+pub fn rgb(color: Color) -> [u8; 3] {
+    match color {
+        Color::Rgb { r, g, b } => [r, g, b],
+        Color::Black => [0, 0, 0],
+        Color::DarkRed => [205, 0, 0],
+        Color::DarkGreen => [0, 205, 0],
+        Color::DarkYellow => [205, 205, 0],
+        Color::DarkBlue => [0, 0, 238],
+        Color::DarkMagenta => [205, 0, 205],
+        Color::DarkCyan => [0, 205, 205],
+        Color::Grey => [229, 229, 229],
+        Color::DarkGrey => [127, 127, 127],
+        Color::Red => [255, 0, 0],
+        Color::Green => [0, 255, 0],
+        Color::Yellow => [255, 255, 0],
+        Color::Blue => [92, 92, 255],
+        Color::Magenta => [255, 0, 255],
+        Color::Cyan => [0, 255, 255],
+        Color::White => [255, 255, 255],
+        Color::AnsiValue(index @ 0..=15) => rgb(NAMED_COLORS[index as usize]),
+        Color::AnsiValue(index @ 16..=231) => {
+            let index = index - 16;
+            [index / 36, index / 6 % 6, index % 6].map(|level| CUBE_LEVELS[level as usize])
+        }
+        Color::AnsiValue(index) => [8 + 10 * (index - 232); 3],
+        Color::Reset => [127, 127, 127],
+    }
+}
+
+fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(&a, b)| (a as i32 - b as i32).pow(2) as u32)
+        .sum()
+}
+
+/// Closest 256-color palette index, from the color cube or the grayscale ramp
+fn ansi256(color: [u8; 3]) -> u8 {
+    let level = |value: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - value as i32).abs())
+            .expect("Cube levels are not empty") as u8
+    };
+    let [r, g, b] = color.map(level);
+    let cube = 16 + 36 * r + 6 * g + b;
+    let average = color.iter().map(|&c| c as u16).sum::<u16>() / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    if distance(rgb(Color::AnsiValue(gray)), color) < distance(rgb(Color::AnsiValue(cube)), color) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// Closest of the 16 named colors
+fn ansi16(color: [u8; 3]) -> Color {
+    NAMED_COLORS
+        .into_iter()
+        .min_by_key(|&named| distance(rgb(named), color))
+        .expect("Named colors are not empty")
+}
+
+/// Color in a theme file: a palette index, an RGB value such as `"#ff8800"`, or a color name
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ColorValue {
+    Index(u8),
+    Text(String),
+}
+
+impl ColorValue {
+    fn parse(&self) -> Result<Color, String> {
+        match self {
+            ColorValue::Index(index) => Ok(Color::AnsiValue(*index)),
+            ColorValue::Text(text) => {
+                if let Some(hex) = text.strip_prefix('#')
+                    && hex.len() == 6
+                    && let Ok(value) = u32::from_str_radix(hex, 16)
+                {
+                    let [_, r, g, b] = value.to_be_bytes();
+                    return Ok(Color::Rgb { r, g, b });
+                }
+                Color::try_from(text.to_lowercase().as_str()).map_err(|_| {
+                    format!(
+                        "Invalid color '{}', expected a name such as \"dark_blue\", \"#rrggbb\" or a number from 0 to 255",
+                        text
+                    )
+                })
+            }
+        }
+    }
+
+    /// Replace the target color with this one, if given
+    fn apply(value: &Option<Self>, target: &mut Color) -> Result<(), String> {
+        if let Some(value) = value {
+            *target = value.parse()?;
+        }
+        Ok(())
+    }
+}
+
+/// Contents of a theme file. Every field is optional, falling back to the preset.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    preset: Option<String>,
+    cells: CellColorsFile,
+    ui: UiColorsFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CellColorsFile {
+    route: Option<ColorValue>,
+    visited: Option<ColorValue>,
+    start: Option<ColorValue>,
+    goal: Option<ColorValue>,
+    pacman: Option<ColorValue>,
+    ghost: Option<ColorValue>,
+    opponent: Option<ColorValue>,
+    second_player: Option<ColorValue>,
+    pellet: Option<ColorValue>,
+    red_door: Option<ColorValue>,
+    green_door: Option<ColorValue>,
+    blue_door: Option<ColorValue>,
+    wall: Option<ColorValue>,
+    mark: Option<ColorValue>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct UiColorsFile {
+    accent: Option<ColorValue>,
+    info: Option<ColorValue>,
+    success: Option<ColorValue>,
+    error: Option<ColorValue>,
+    hint: Option<ColorValue>,
+    muted: Option<ColorValue>,
+}
+
+/// Colors of grid cells and interface text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub cells: CellColors,
+    pub ui: UiColors,
+    /// Colors are disabled by the `NO_COLOR` environment variable, so displays relying on
+    /// color alone are unavailable
+    pub no_color: bool,
+    depth: ColorDepth,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::from_preset(Preset::Default)
+    }
+}
+
+impl Theme {
+    pub fn from_preset(preset: Preset) -> Self {
+        let (cells, ui) = preset.colors();
+        Self {
+            cells,
+            ui,
+            no_color: false,
+            depth: ColorDepth::TrueColor,
+        }
+    }
+
+    /// Parse the contents of a theme file
+    fn parse(contents: &str) -> Result<Self, String> {
+        let file: ThemeFile = toml::from_str(contents).map_err(|e| e.message().to_string())?;
+        let preset = match &file.preset {
+            Some(name) => name.parse()?,
+            None => Preset::default(),
+        };
+        let mut theme = Theme::from_preset(preset);
+        let (cells, ui) = (&mut theme.cells, &mut theme.ui);
+        ColorValue::apply(&file.cells.route, &mut cells.route)?;
+        ColorValue::apply(&file.cells.visited, &mut cells.visited)?;
+        ColorValue::apply(&file.cells.start, &mut cells.start)?;
+        ColorValue::apply(&file.cells.goal, &mut cells.goal)?;
+        ColorValue::apply(&file.cells.pacman, &mut cells.pacman)?;
+        ColorValue::apply(&file.cells.ghost, &mut cells.ghost)?;
+        ColorValue::apply(&file.cells.opponent, &mut cells.opponent)?;
+        ColorValue::apply(&file.cells.second_player, &mut cells.second_player)?;
+        ColorValue::apply(&file.cells.pellet, &mut cells.pellet)?;
+        ColorValue::apply(&file.cells.red_door, &mut cells.red_door)?;
+        ColorValue::apply(&file.cells.green_door, &mut cells.green_door)?;
+        ColorValue::apply(&file.cells.blue_door, &mut cells.blue_door)?;
+        ColorValue::apply(&file.cells.wall, &mut cells.wall)?;
+        ColorValue::apply(&file.cells.mark, &mut cells.mark)?;
+        ColorValue::apply(&file.ui.accent, &mut ui.accent)?;
+        ColorValue::apply(&file.ui.info, &mut ui.info)?;
+        ColorValue::apply(&file.ui.success, &mut ui.success)?;
+        ColorValue::apply(&file.ui.error, &mut ui.error)?;
+        ColorValue::apply(&file.ui.hint, &mut ui.hint)?;
+        ColorValue::apply(&file.ui.muted, &mut ui.muted)?;
+        Ok(theme)
+    }
+
+    /// Load the theme of the given preset, or else from the theme file if there is one.
+    /// Colors are adapted to the terminal, and disabled if `NO_COLOR` is set.
+    pub fn load(preset: Option<Preset>) -> std::io::Result<Self> {
+        let path = super::config_dir().map(|dir| dir.join(THEME_FILE));
+        let theme = match (preset, path) {
+            (Some(preset), _) => Theme::from_preset(preset),
+            (None, Some(path)) if path.exists() => {
+                let contents = std::fs::read_to_string(&path)?;
+                let theme = Theme::parse(&contents).map_err(|e| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Invalid theme file {}: {}", path.display(), e),
+                    )
+                })?;
+                tracing::info!("Loaded theme from {}", path.display());
+                theme
+            }
+            (None, _) => Theme::default(),
+        };
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Ok(theme.with_depth(ColorDepth::detect(), no_color))
+    }
+
+    /// Theme with every color adapted to the given color depth
+    fn with_depth(self, depth: ColorDepth, no_color: bool) -> Self {
+        let adapt = |color| depth.adapt(color);
+        let (c, u) = (self.cells, self.ui);
+        Self {
+            cells: CellColors {
+                route: adapt(c.route),
+                visited: adapt(c.visited),
+                start: adapt(c.start),
+                goal: adapt(c.goal),
+                pacman: adapt(c.pacman),
+                ghost: adapt(c.ghost),
+                opponent: adapt(c.opponent),
+                second_player: adapt(c.second_player),
+                pellet: adapt(c.pellet),
+                red_door: adapt(c.red_door),
+                green_door: adapt(c.green_door),
+                blue_door: adapt(c.blue_door),
+                wall: adapt(c.wall),
+                mark: adapt(c.mark),
+            },
+            ui: UiColors {
+                accent: adapt(u.accent),
+                info: adapt(u.info),
+                success: adapt(u.success),
+                error: adapt(u.error),
+                hint: adapt(u.hint),
+                muted: adapt(u.muted),
+            },
+            no_color,
+            depth,
+        }
+    }
+
+    /// Use this theme for the rest of the run. Only the first installed theme is used.
+    pub fn install(self) {
+        if THEME.set(self).is_err() {
+            tracing::warn!("A theme is already installed, ignoring the new one");
+        }
+    }
+
+    /// Theme in use, the default theme if none was installed
+    pub fn current() -> &'static Theme {
+        THEME.get_or_init(Theme::default)
+    }
+
+    /// Closest color the terminal can display, for colors computed at runtime such as gradients
+    pub fn adapt(&self, color: Color) -> Color {
+        self.depth.adapt(color)
+    }
+}
+
+/// Interface text colors of the theme in use
+pub fn ui() -> &'static UiColors {
+    &Theme::current().ui
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_file_overrides_preset_colors() {
+        let theme = Theme::parse(
+            r##"
+            preset = "colorblind"
+            [cells]
+            start = "#0000ff"
+            goal = 214
+            [ui]
+            accent = "Dark_Yellow"
+            "##,
+        )
+        .unwrap();
+        let colorblind = Theme::from_preset(Preset::Colorblind);
+        assert_eq!(theme.cells.start, rgb_color(0, 0, 255));
+        assert_eq!(theme.cells.goal, Color::AnsiValue(214));
+        assert_eq!(theme.cells.wall, colorblind.cells.wall);
+        assert_eq!(theme.ui.accent, Color::DarkYellow);
+
+        assert!(Theme::parse("preset = \"sepia\"").is_err());
+        assert!(Theme::parse("[cells]\nstart = \"#00ff\"").is_err());
+        assert!(Theme::parse("[cells]\nfloor = \"red\"").is_err());
+    }
+
+    #[test]
+    fn doors_stand_out_from_players() {
+        for preset in [Preset::Default, Preset::HighContrast, Preset::Colorblind] {
+            let cells = Theme::from_preset(preset).cells;
+            let doors = DoorColor::ALL.map(|color| cells.door(color));
+            for other in [
+                cells.pacman,
+                cells.second_player,
+                cells.opponent,
+                cells.mark,
+            ] {
+                assert!(!doors.contains(&other), "{} preset", preset);
+            }
+            assert!(doors[0] != doors[1] && doors[1] != doors[2] && doors[0] != doors[2]);
+        }
+    }
+
+    #[test]
+    fn colors_fall_back_to_terminal_depth() {
+        let orange = rgb_color(255, 135, 0);
+        assert_eq!(ColorDepth::TrueColor.adapt(orange), orange);
+        assert_eq!(ColorDepth::Ansi256.adapt(orange), Color::AnsiValue(208));
+        assert_eq!(
+            ColorDepth::Ansi256.adapt(rgb_color(128, 128, 128)),
+            Color::AnsiValue(244)
+        );
+        assert_eq!(ColorDepth::Ansi16.adapt(orange), Color::DarkYellow);
+        assert_eq!(ColorDepth::Ansi16.adapt(Color::AnsiValue(196)), Color::Red);
+        assert_eq!(ColorDepth::Ansi16.adapt(Color::Blue), Color::Blue);
+    }
+}
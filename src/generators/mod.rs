@@ -30,7 +30,7 @@ fn get_rng(seed: Option<u64>) -> StdRng {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Generator {
     RecurBacktrack,
     Prim,
@@ -49,6 +49,27 @@ impl std::fmt::Display for Generator {
     }
 }
 
+impl std::str::FromStr for Generator {
+    type Err = String;
+
+    /// Parse a generator from a short name (e.g. `prim`) or its display name, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let generator = match s.to_lowercase().as_str() {
+            "backtrack" | "recursive backtracking" => Generator::RecurBacktrack,
+            "prim" | "prim's algorithm" => Generator::Prim,
+            "division" | "recursive division" => Generator::RecurDiv,
+            "kruskal" | "kruskal's algorithm" => Generator::Kruskal,
+            _ => {
+                return Err(format!(
+                    "Unknown generator '{}', expected one of: backtrack, prim, division, kruskal",
+                    s
+                ));
+            }
+        };
+        Ok(generator)
+    }
+}
+
 pub fn generate_maze(maze: &mut Maze, generator: Generator, seed: Option<u64>) {
     match generator {
         Generator::RecurBacktrack => recursive_backtrack(maze, seed),
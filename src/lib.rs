@@ -1,4 +1,5 @@
 pub mod app;
+pub mod config;
 pub mod generators;
 mod maze;
 pub mod solvers;
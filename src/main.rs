@@ -1,4 +1,4 @@
-use mazest::app::App;
+use mazest::app::{App, Options, USAGE};
 
 fn main() -> std::io::Result<()> {
     // Initialize logging only in debug mode. Guard is kept alive for the duration of the program.
@@ -15,6 +15,21 @@ fn main() -> std::io::Result<()> {
         tracing::info!("Logging initialized.");
         guard
     };
-    let mut app = App::default();
-    app.run()
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let mut app = App::new(options);
+    if let Err(e) = app.run() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+    Ok(())
 }
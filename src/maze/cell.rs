@@ -1,8 +1,11 @@
 use crossterm::style::{Color, Stylize};
 
-use std::fmt;
+use std::{
+    fmt,
+    sync::atomic::{AtomicU8, Ordering},
+};
 
-use crate::maze::Orientation;
+use crate::{config::theme::Theme, maze::Orientation};
 
 /// Represents a cell in the grid, which can be either a path or a wall.
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -19,8 +22,127 @@ impl GridCell {
     pub const START: GridCell = GridCell::Path(PathType::Start);
     pub const VISITED: GridCell = GridCell::Path(PathType::Visited);
     pub const PACMAN: GridCell = GridCell::Path(PathType::Pacman);
+    pub const GHOST: GridCell = GridCell::Path(PathType::Ghost);
+    pub const OPPONENT: GridCell = GridCell::Path(PathType::Opponent);
+    pub const SECOND_PLAYER: GridCell = GridCell::Path(PathType::SecondPlayer);
+    pub const SECOND_TRAIL: GridCell = GridCell::Path(PathType::SecondTrail);
+    pub const PELLET: GridCell = GridCell::Path(PathType::Pellet);
+    /// Highest byte of the one byte cell encoding, see `u8::from`.
+    pub const MAX_ENCODING: u8 = 19;
     /// The width of each cell when rendered, in character widths.
     pub const CELL_WIDTH: u16 = 2;
+
+    /// Color of the cell in the current theme, also used when cells are drawn as colored blocks
+    /// rather than symbols. Returns None for empty cells, which are left transparent.
+    pub fn color(&self) -> Option<Color> {
+        let colors = &Theme::current().cells;
+        let color = match self {
+            GridCell::Path(path) => match path {
+                PathType::Route(_) => colors.route,
+                PathType::Empty => return None,
+                PathType::Visited => colors.visited,
+                PathType::Start => colors.start,
+                PathType::Goal => colors.goal,
+                PathType::Pacman => colors.pacman,
+                PathType::Ghost => colors.ghost,
+                PathType::Opponent => colors.opponent,
+                PathType::SecondPlayer | PathType::SecondTrail => colors.second_player,
+                PathType::Pellet => colors.pellet,
+                PathType::Key(color) => colors.door(*color),
+            },
+            GridCell::Wall(wall) => match wall {
+                WallType::Wall => colors.wall,
+                WallType::Mark => colors.mark,
+                WallType::Door(color) => colors.door(*color),
+            },
+        };
+        Some(color)
+    }
+}
+
+impl From<GridCell> for u8 {
+    /// Compact one byte encoding of a cell, used to store and replay grid events
+    fn from(cell: GridCell) -> Self {
+        match cell {
+            GridCell::Path(path) => match path {
+                PathType::Route(Orientation::Horizontal) => 0,
+                PathType::Route(Orientation::Vertical) => 1,
+                PathType::Empty => 2,
+                PathType::Visited => 3,
+                PathType::Start => 4,
+                PathType::Goal => 5,
+                PathType::Pacman => 6,
+                PathType::Ghost => 7,
+                PathType::Opponent => 10,
+                PathType::SecondPlayer => 11,
+                PathType::SecondTrail => 12,
+                PathType::Pellet => 13,
+                PathType::Key(color) => 14 + color as u8,
+            },
+            GridCell::Wall(wall) => match wall {
+                WallType::Wall => 8,
+                WallType::Mark => 9,
+                WallType::Door(color) => 17 + color as u8,
+            },
+        }
+    }
+}
+
+impl TryFrom<u8> for GridCell {
+    type Error = u8;
+
+    /// Decode a cell encoded with `u8::from`, returning the byte back if it is not a valid cell
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        let cell = match byte {
+            0 => GridCell::Path(PathType::Route(Orientation::Horizontal)),
+            1 => GridCell::Path(PathType::Route(Orientation::Vertical)),
+            2 => GridCell::EMPTY,
+            3 => GridCell::VISITED,
+            4 => GridCell::START,
+            5 => GridCell::GOAL,
+            6 => GridCell::PACMAN,
+            7 => GridCell::GHOST,
+            8 => GridCell::WALL,
+            9 => GridCell::MARK,
+            10 => GridCell::OPPONENT,
+            11 => GridCell::SECOND_PLAYER,
+            12 => GridCell::SECOND_TRAIL,
+            13 => GridCell::PELLET,
+            14..=16 => GridCell::Path(PathType::Key(DoorColor::ALL[byte as usize - 14])),
+            17..=19 => GridCell::Wall(WallType::Door(DoorColor::ALL[byte as usize - 17])),
+            _ => return Err(byte),
+        };
+        Ok(cell)
+    }
+}
+
+/// How grid cells are laid out on the terminal
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum CellLayout {
+    /// Each cell is a symbol of [`GridCell::CELL_WIDTH`] columns, one grid row per terminal row
+    #[default]
+    Full,
+    /// Each cell is a single column colored block, with two grid rows packed into each terminal
+    /// row using half block characters
+    HalfBlock,
+}
+
+impl CellLayout {
+    /// Number of terminal columns per grid cell
+    pub fn cell_width(self) -> u16 {
+        match self {
+            CellLayout::Full => GridCell::CELL_WIDTH,
+            CellLayout::HalfBlock => 1,
+        }
+    }
+
+    /// Number of grid rows per terminal row
+    pub fn rows_per_line(self) -> u16 {
+        match self {
+            CellLayout::Full => 1,
+            CellLayout::HalfBlock => 2,
+        }
+    }
 }
 
 /// Represents different types of path cells in the maze.
@@ -41,6 +163,16 @@ pub enum PathType {
     Pacman,
     /// Ghost cell
     Ghost,
+    /// Computer-controlled runner racing Pacman
+    Opponent,
+    /// Second player of a two-player game
+    SecondPlayer,
+    /// Cell visited by the second player
+    SecondTrail,
+    /// Pellet to collect before reaching the goal
+    Pellet,
+    /// Key unlocking the doors of its color
+    Key(DoorColor),
 }
 
 /// Represents different types of wall cells in the maze.
@@ -49,31 +181,206 @@ pub enum WallType {
     #[default]
     Wall,
     Mark,
+    /// Door blocking a passage, opened with the key of its color
+    Door(DoorColor),
 }
 
-impl fmt::Display for GridCell {
+/// Color telling doors and the keys unlocking them apart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DoorColor {
+    Red,
+    Green,
+    Blue,
+}
+
+impl DoorColor {
+    pub const ALL: [DoorColor; 3] = [DoorColor::Red, DoorColor::Green, DoorColor::Blue];
+}
+
+impl fmt::Display for DoorColor {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let styled_symbol = match self {
-            GridCell::Path(path) => match path {
-                PathType::Route(orientation) => match orientation {
-                    Orientation::Horizontal => "".with(Color::Yellow),
-                    Orientation::Vertical => " ".with(Color::Yellow),
-                    // Orientation::Horizontal => "🟨".with(Color::Yellow),
-                    // Orientation::Vertical => "🟨".with(Color::Yellow),
+        match self {
+            DoorColor::Red => write!(f, "red"),
+            DoorColor::Green => write!(f, "green"),
+            DoorColor::Blue => write!(f, "blue"),
+        }
+    }
+}
+
+/// Set of symbols used to display grid cells
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlyphTheme {
+    /// Box-drawing and geometric characters, which most Unicode fonts render at a single width
+    Unicode,
+    /// Plain ASCII characters, for the Linux console and non-UTF-8 locales
+    Ascii,
+    /// Colored emoji, for terminals with full emoji support
+    Emoji,
+}
+
+/// Glyph theme used to display cells, as a [`GlyphTheme`] discriminant
+static GLYPH_THEME: AtomicU8 = AtomicU8::new(GlyphTheme::Emoji as u8);
+
+impl GlyphTheme {
+    /// Glyph theme currently used to display cells
+    pub fn current() -> Self {
+        match GLYPH_THEME.load(Ordering::Relaxed) {
+            0 => GlyphTheme::Unicode,
+            1 => GlyphTheme::Ascii,
+            _ => GlyphTheme::Emoji,
+        }
+    }
+
+    /// Display cells with this theme from now on
+    pub fn set(self) {
+        GLYPH_THEME.store(self as u8, Ordering::Relaxed);
+    }
+
+    /// Pick a theme the terminal can display, based on the `TERM` and locale environment variables.
+    /// Emoji are only used on local UTF-8 terminals, as SSH sessions often lack the fonts or
+    /// disagree on their width.
+    pub fn detect() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let term = var("TERM").unwrap_or_default();
+        // The first set locale variable takes precedence
+        let locale = var("LC_ALL")
+            .or_else(|| var("LC_CTYPE"))
+            .or_else(|| var("LANG"))
+            .unwrap_or_default()
+            .to_lowercase();
+        let utf8 = locale.contains("utf-8") || locale.contains("utf8");
+        if !utf8 || matches!(term.as_str(), "linux" | "dumb" | "vt100" | "vt220") {
+            GlyphTheme::Ascii
+        } else if var("SSH_CONNECTION").is_some() || var("SSH_TTY").is_some() {
+            GlyphTheme::Unicode
+        } else {
+            GlyphTheme::Emoji
+        }
+    }
+
+    /// Symbol of a cell, [`GridCell::CELL_WIDTH`] columns wide
+    pub fn symbol(self, cell: GridCell) -> &'static str {
+        match self {
+            GlyphTheme::Unicode => match cell {
+                GridCell::Path(path) => match path {
+                    PathType::Route(Orientation::Horizontal) => "──",
+                    PathType::Route(Orientation::Vertical) => "│ ",
+                    PathType::Empty => "  ",
+                    PathType::Visited => "· ",
+                    PathType::Start => "▶ ",
+                    PathType::Goal => "⚑ ",
+                    PathType::Pacman => "◕ ",
+                    PathType::Ghost => "ᗣ ",
+                    PathType::Opponent => "◔ ",
+                    PathType::SecondPlayer => "◑ ",
+                    PathType::SecondTrail => "∘ ",
+                    PathType::Pellet => "• ",
+                    PathType::Key(_) => "⚷ ",
+                },
+                GridCell::Wall(wall) => match wall {
+                    WallType::Wall => "██",
+                    WallType::Mark => "▒▒",
+                    WallType::Door(_) => "▓▓",
                 },
-                PathType::Empty => "  ".with(Color::Reset),
-                // PathType::Visited => "* ".with(Color::Blue),
-                PathType::Visited => "* ".with(Color::Blue),
-                PathType::Start => "🟩".with(Color::Green),
-                PathType::Goal => "🟥".with(Color::Red),
-                PathType::Pacman => "🟡".with(Color::Yellow),
-                PathType::Ghost => "👻".with(Color::Cyan),
             },
-            GridCell::Wall(wall) => match wall {
-                WallType::Wall => "⬜".with(Color::White),
-                WallType::Mark => "🟪".with(Color::Magenta),
+            GlyphTheme::Ascii => match cell {
+                GridCell::Path(path) => match path {
+                    PathType::Route(Orientation::Horizontal) => "==",
+                    PathType::Route(Orientation::Vertical) => "||",
+                    PathType::Empty => "  ",
+                    PathType::Visited => "..",
+                    PathType::Start => "SS",
+                    PathType::Goal => "GG",
+                    PathType::Pacman => "C ",
+                    PathType::Ghost => "M ",
+                    PathType::Opponent => "Q ",
+                    PathType::SecondPlayer => "c ",
+                    PathType::SecondTrail => "::",
+                    PathType::Pellet => "o ",
+                    // Letters tell the colors apart without colors
+                    PathType::Key(DoorColor::Red) => "r~",
+                    PathType::Key(DoorColor::Green) => "g~",
+                    PathType::Key(DoorColor::Blue) => "b~",
+                },
+                GridCell::Wall(wall) => match wall {
+                    WallType::Wall => "##",
+                    WallType::Mark => "%%",
+                    WallType::Door(DoorColor::Red) => "R|",
+                    WallType::Door(DoorColor::Green) => "G|",
+                    WallType::Door(DoorColor::Blue) => "B|",
+                },
             },
-        };
+            GlyphTheme::Emoji => match cell {
+                GridCell::Path(path) => match path {
+                    PathType::Route(orientation) => match orientation {
+                        Orientation::Horizontal => "",
+                        Orientation::Vertical => " ",
+                        // Orientation::Horizontal => "🟨",
+                        // Orientation::Vertical => "🟨",
+                    },
+                    PathType::Empty => "  ",
+                    // PathType::Visited => "* ",
+                    PathType::Visited => "* ",
+                    PathType::Start => "🟩",
+                    PathType::Goal => "🟥",
+                    PathType::Pacman => "🟡",
+                    PathType::Ghost => "👻",
+                    PathType::Opponent => "🤖",
+                    PathType::SecondPlayer => "🟢",
+                    PathType::SecondTrail => "+ ",
+                    PathType::Pellet => "🍒",
+                    // Colored symbols, as emoji keys and doors can't be told apart by color
+                    PathType::Key(_) => "⚷ ",
+                },
+                GridCell::Wall(wall) => match wall {
+                    WallType::Wall => "⬜",
+                    WallType::Mark => "🟪",
+                    WallType::Door(_) => "▓▓",
+                },
+            },
+        }
+    }
+
+    /// Filled and empty characters of the progress and speed bars
+    pub fn bar(self) -> (char, char) {
+        match self {
+            GlyphTheme::Ascii => ('#', '-'),
+            GlyphTheme::Unicode | GlyphTheme::Emoji => ('█', '░'),
+        }
+    }
+}
+
+impl fmt::Display for GlyphTheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GlyphTheme::Unicode => write!(f, "unicode"),
+            GlyphTheme::Ascii => write!(f, "ascii"),
+            GlyphTheme::Emoji => write!(f, "emoji"),
+        }
+    }
+}
+
+impl std::str::FromStr for GlyphTheme {
+    type Err = String;
+
+    /// Parse a glyph theme from its name, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "unicode" => Ok(GlyphTheme::Unicode),
+            "ascii" => Ok(GlyphTheme::Ascii),
+            "emoji" => Ok(GlyphTheme::Emoji),
+            _ => Err(format!(
+                "Unknown glyph theme '{}', expected one of: unicode, ascii, emoji",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for GridCell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = GlyphTheme::current().symbol(*self);
+        let styled_symbol = symbol.with(self.color().unwrap_or(Color::Reset));
 
         #[cfg(debug_assertions)]
         {
@@ -88,3 +395,25 @@ impl fmt::Display for GridCell {
         write!(f, "{}", styled_symbol)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyph_themes_fill_the_cell_width() {
+        use unicode_width::UnicodeWidthStr;
+        for theme in [GlyphTheme::Unicode, GlyphTheme::Ascii, GlyphTheme::Emoji] {
+            for cell in (0..=u8::MAX).filter_map(|byte| GridCell::try_from(byte).ok()) {
+                assert_eq!(
+                    theme.symbol(cell).width(),
+                    GridCell::CELL_WIDTH as usize,
+                    "{:?} in the {} theme",
+                    cell,
+                    theme
+                );
+            }
+        }
+        assert!(GlyphTheme::Ascii.symbol(GridCell::WALL).is_ascii());
+    }
+}
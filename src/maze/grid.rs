@@ -49,6 +49,50 @@ impl std::fmt::Display for GridEvent {
     }
 }
 
+impl GridEvent {
+    /// Number of bytes of an encoded event
+    pub const ENCODED_LEN: usize = 7;
+
+    /// Encode the event into a fixed number of bytes, so events can be stored compactly
+    /// and indexed without a separate offset table
+    pub fn encode(&self) -> [u8; Self::ENCODED_LEN] {
+        match *self {
+            GridEvent::Initial {
+                cell,
+                width,
+                height,
+            } => {
+                let [w0, w1] = width.to_le_bytes();
+                let [h0, h1] = height.to_le_bytes();
+                [0, cell.into(), w0, w1, h0, h1, 0]
+            }
+            GridEvent::Update { coord, old, new } => {
+                let [x0, x1] = coord.0.to_le_bytes();
+                let [y0, y1] = coord.1.to_le_bytes();
+                [1, x0, x1, y0, y1, old.into(), new.into()]
+            }
+        }
+    }
+
+    /// Decode an event encoded with [`GridEvent::encode`]. Returns None if the bytes are not a valid event.
+    pub fn decode(bytes: &[u8; Self::ENCODED_LEN]) -> Option<Self> {
+        let word = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        match bytes[0] {
+            0 => Some(GridEvent::Initial {
+                cell: GridCell::try_from(bytes[1]).ok()?,
+                width: word(2),
+                height: word(4),
+            }),
+            1 => Some(GridEvent::Update {
+                coord: (word(1), word(3)),
+                old: GridCell::try_from(bytes[5]).ok()?,
+                new: GridCell::try_from(bytes[6]).ok()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
 impl Grid {
     pub fn new(
         width: u16,
@@ -72,6 +116,21 @@ impl Grid {
         }
     }
 
+    /// Create a copy of this grid whose updates are sent to `grid_event_tx` instead.
+    /// No [`GridEvent::Initial`] is sent for the copy, since the receiver is expected to
+    /// get the copied cells some other way.
+    pub fn clone_with_sender(
+        &self,
+        grid_event_tx: Option<std::sync::mpsc::SyncSender<GridEvent>>,
+    ) -> Self {
+        Grid {
+            data: self.data.clone(),
+            width: self.width,
+            height: self.height,
+            grid_event_tx,
+        }
+    }
+
     pub fn height(&self) -> u16 {
         self.height
     }
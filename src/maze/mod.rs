@@ -42,6 +42,33 @@ impl Maze {
         maze
     }
 
+    /// Creates a copy of this maze whose updates are sent to `grid_event_tx` instead.
+    /// See [`Grid::clone_with_sender`].
+    pub fn clone_with_sender(
+        &self,
+        grid_event_tx: Option<std::sync::mpsc::SyncSender<GridEvent>>,
+    ) -> Self {
+        Maze {
+            grid: self.grid.clone_with_sender(grid_event_tx),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Creates a copy of this maze without an event sender, keeping only its walls.
+    /// Every other cell of the copy is empty, so it can be solved from scratch.
+    pub fn walls_only(&self) -> Self {
+        let mut maze = self.clone_with_sender(None);
+        (0..maze.grid.height()).for_each(|y| {
+            (0..maze.grid.width()).for_each(|x| {
+                if !matches!(maze.grid[(x, y)], GridCell::Wall(_)) {
+                    maze.grid.set((x, y), GridCell::EMPTY);
+                }
+            });
+        });
+        maze
+    }
+
     /// Returns the height of the maze in cells.
     pub fn height(&self) -> u8 {
         self.height
@@ -207,6 +234,33 @@ impl Maze {
         matches!(self.grid[wall_coord], GridCell::Wall(_))
     }
 
+    /// Checks if two neighboring cells are connected, with no wall between them.
+    /// Returns false if the cells are not neighbors.
+    pub fn is_passage_between(&self, a: (u8, u8), b: (u8, u8)) -> bool {
+        if !self.is_in_bounds(a) || !self.is_in_bounds(b) {
+            return false;
+        }
+        if a.1 == b.1 && a.0.abs_diff(b.0) == 1 {
+            !self.is_wall_cell_after((a.0.min(b.0), a.1), Orientation::Vertical)
+        } else if a.0 == b.0 && a.1.abs_diff(b.1) == 1 {
+            !self.is_wall_cell_after((a.0, a.1.min(b.1)), Orientation::Horizontal)
+        } else {
+            false
+        }
+    }
+
+    /// Returns the grid cell between two neighboring maze cells.
+    pub fn cell_between(&self, a: (u8, u8), b: (u8, u8)) -> &GridCell {
+        &self.grid[(a.0 as u16 + b.0 as u16 + 1, a.1 as u16 + b.1 as u16 + 1)]
+    }
+
+    /// Sets the grid cell between two neighboring maze cells, returning its grid coordinates.
+    pub fn set_cell_between(&mut self, a: (u8, u8), b: (u8, u8), cell: GridCell) -> (u16, u16) {
+        let coord = (a.0 as u16 + b.0 as u16 + 1, a.1 as u16 + b.1 as u16 + 1);
+        self.grid.set(coord, cell);
+        coord
+    }
+
     /// Set the grid cell after the specified maze cell in the given orientation.
     /// `orientation` determines the orientation of the path to set:
     /// - `Vertical`: Sets the path cell below the specified cell (between `from` and `(from.0, from.1+1)`)
@@ -311,6 +365,19 @@ pub fn get_neighbors(coord: (u8, u8), maze: &Maze) -> impl Iterator<Item = (u8,
     neighbors.into_iter().filter(move |&c| maze.is_in_bounds(c))
 }
 
+#[cfg(test)]
+impl Maze {
+    /// Maze with a single corridor along its top row, leaving the other cells walled in.
+    /// Shared by the tests of the game modules.
+    pub(crate) fn corridor(width: u8, height: u8) -> Self {
+        let mut maze = Maze::new(width, height, None);
+        for x in 0..width - 1 {
+            maze.remove_wall_cell_after((x, 0), Orientation::Vertical);
+        }
+        maze
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -331,6 +398,14 @@ mod tests {
         assert_eq!(maze.grid[(3, 5)], GridCell::EMPTY);
     }
 
+    #[test]
+    fn test_corridor() {
+        let maze = Maze::corridor(3, 2);
+        assert!(maze.is_passage_between((0, 0), (1, 0)));
+        assert!(maze.is_passage_between((1, 0), (2, 0)));
+        assert!(!maze.is_passage_between((0, 0), (0, 1)));
+    }
+
     #[test]
     fn test_out_of_bounds() {
         let maze = Maze::new(5, 5, None);
@@ -6,7 +6,12 @@ mod bfs;
 mod dfs;
 mod dijkstra;
 
-use crate::maze::{Maze, cell::GridCell};
+use crate::maze::{
+    Maze,
+    cell::{GridCell, PathType},
+    get_neighbors,
+    grid::GridEvent,
+};
 use astar::solve_astart;
 use bfs::solve_bfs;
 use dfs::solve_dfs;
@@ -69,7 +74,7 @@ impl PartialOrd for TrackedCell {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Solver {
     Dfs,
     Bfs,
@@ -88,6 +93,27 @@ impl std::fmt::Display for Solver {
     }
 }
 
+impl std::str::FromStr for Solver {
+    type Err = String;
+
+    /// Parse a solver from a short name (e.g. `bfs`) or its display name, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let solver = match s.to_lowercase().as_str() {
+            "dfs" | "depth-first search (dfs)" => Solver::Dfs,
+            "bfs" | "breadth-first search (bfs)" => Solver::Bfs,
+            "dijkstra" | "dijkstra's algorithm" => Solver::Dijkstra,
+            "astar" | "a* search algorithm" => Solver::AStar,
+            _ => {
+                return Err(format!(
+                    "Unknown solver '{}', expected one of: dfs, bfs, dijkstra, astar",
+                    s
+                ));
+            }
+        };
+        Ok(solver)
+    }
+}
+
 pub fn solve_maze(maze: &mut Maze, solver: Solver) -> bool {
     let start = (0, 0);
     let goal = (maze.width() - 1, maze.height() - 1);
@@ -101,3 +127,120 @@ pub fn solve_maze(maze: &mut Maze, solver: Solver) -> bool {
         Solver::AStar => solve_astart(maze, start, goal),
     }
 }
+
+/// Find a route from `start` to `goal` with the given solver, on a copy of the maze keeping only
+/// its walls so that no grid events are sent and the maze is left untouched.
+/// Returns the cells of the route from `start` to `goal` included, or None if there is no route.
+pub fn find_route(
+    maze: &Maze,
+    solver: Solver,
+    start: (u8, u8),
+    goal: (u8, u8),
+) -> Option<Vec<(u8, u8)>> {
+    route_through(maze.walls_only(), solver, start, goal)
+}
+
+/// Route found by a solver, with the cells it explored to find it
+pub struct Exploration {
+    /// Cells the solver visited, in order
+    pub explored: Vec<(u8, u8)>,
+    /// Cells of the route from the start to the goal included
+    pub route: Vec<(u8, u8)>,
+}
+
+/// Like [`find_route`], also returning the cells the solver explored.
+pub fn explore_route(
+    maze: &Maze,
+    solver: Solver,
+    start: (u8, u8),
+    goal: (u8, u8),
+) -> Option<Exploration> {
+    // Solve on another thread, collecting the updates of the copy as the solver sends them
+    let (grid_event_tx, grid_event_rx) = std::sync::mpsc::sync_channel(64);
+    let copy = maze.walls_only().clone_with_sender(Some(grid_event_tx));
+    std::thread::scope(|scope| {
+        let solving = scope.spawn(move || route_through(copy, solver, start, goal));
+        let explored = grid_event_rx
+            .iter()
+            .filter_map(|event| match event {
+                GridEvent::Update { coord, new, .. } if new == GridCell::VISITED => {
+                    Some(((coord.0 / 2) as u8, (coord.1 / 2) as u8))
+                }
+                _ => None,
+            })
+            .collect();
+        let route = solving.join().expect("Solver thread panicked")?;
+        Some(Exploration { explored, route })
+    })
+}
+
+/// Solve a copy of a maze keeping only its walls, returning the route cells from `start` to `goal`
+fn route_through(
+    mut copy: Maze,
+    solver: Solver,
+    start: (u8, u8),
+    goal: (u8, u8),
+) -> Option<Vec<(u8, u8)>> {
+    if start == goal {
+        return Some(vec![start]);
+    }
+    copy.set(goal, GridCell::GOAL);
+    let found = match solver {
+        Solver::Dfs => solve_dfs(&mut copy, start, goal),
+        Solver::Bfs => solve_bfs(&mut copy, start, goal),
+        Solver::Dijkstra => solve_dijkstra(&mut copy, start, goal),
+        Solver::AStar => solve_astart(&mut copy, start, goal),
+    };
+    if !found {
+        return None;
+    }
+
+    // Follow the route cells the solver marked between the cells of the route
+    let mut route = vec![start];
+    let mut previous = None;
+    let mut current = start;
+    while current != goal {
+        let next = get_neighbors(current, &copy).find(|&next| {
+            Some(next) != previous
+                && matches!(
+                    copy.cell_between(current, next),
+                    GridCell::Path(PathType::Route(_))
+                )
+        })?;
+        previous = Some(current);
+        current = next;
+        route.push(current);
+    }
+    Some(route)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Orientation;
+
+    #[test]
+    fn find_route_leaves_the_maze_untouched() {
+        // Open a corridor along the top row, then down the right column
+        let mut maze = Maze::new(3, 3, None);
+        maze.remove_wall_cell_after((0, 0), Orientation::Vertical);
+        maze.remove_wall_cell_after((1, 0), Orientation::Vertical);
+        maze.remove_wall_cell_after((2, 0), Orientation::Horizontal);
+        maze.remove_wall_cell_after((2, 1), Orientation::Horizontal);
+        maze.set((1, 0), GridCell::VISITED);
+
+        for solver in [Solver::Dfs, Solver::Bfs, Solver::Dijkstra, Solver::AStar] {
+            assert_eq!(
+                find_route(&maze, solver, (0, 0), (2, 2)),
+                Some(vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)])
+            );
+            assert_eq!(find_route(&maze, solver, (0, 0), (0, 1)), None);
+        }
+        assert_eq!(maze[(1, 0)], GridCell::VISITED);
+        assert_eq!(maze[(0, 0)], GridCell::EMPTY);
+
+        let exploration = explore_route(&maze, Solver::Bfs, (0, 0), (2, 2)).unwrap();
+        assert_eq!(exploration.explored, [(1, 0), (2, 0), (2, 1)]);
+        assert_eq!(exploration.route.len(), 5);
+    }
+}